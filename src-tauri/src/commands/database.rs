@@ -1,15 +1,20 @@
 use crate::state::AppState;
-use crate::core::database::{HashEntryOutput, DatabaseManager};
-use tauri::{command, State};
+use crate::config::profile::DatabaseMode;
+use crate::core::database::{HashQuery, HashPage, DatabaseManager, SyncReport, ImportMode, KeyUsage};
+use crate::core::backup::{self, DumpFormat, ImportReport};
+use crate::core::reconcile::{self, ReconcileMode, ReconcileReport};
+use crate::core::glossary::{self, GlossaryTerm};
+use std::path::Path;
+use tauri::{command, State, Window};
 
 #[command]
 pub async fn save_hash_name(state: State<'_, AppState>, hash: String, name: String, folder: String) -> Result<(), String> {
     let mode = {
         let profile = state.profile.read().await;
-        profile.database_mode.clone()
+        profile.database_mode
     };
 
-    if mode == "off" {
+    if mode == DatabaseMode::Off {
         return Ok(());
     }
 
@@ -34,14 +39,14 @@ pub async fn get_name_by_hash(state: State<'_, AppState>, hash: String) -> Resul
 }
 
 #[command]
-pub async fn list_hash_names(state: State<'_, AppState>) -> Result<Vec<HashEntryOutput>, String> {
+pub async fn list_hash_names(state: State<'_, AppState>, query: Option<HashQuery>) -> Result<HashPage, String> {
     // Clone the db to release the lock before async I/O
     let db = {
         let db_lock = state.db.read().await;
         db_lock.clone().ok_or("Database not initialized")?
     };
     
-    db.list_all().await.map_err(|e| e.to_string())
+    db.query_entries(&query.unwrap_or_default()).await.map_err(|e| e.to_string())
 }
 
 #[command]
@@ -67,16 +72,16 @@ pub async fn clear_all_database(state: State<'_, AppState>) -> Result<(), String
 }
 
 #[command]
-pub async fn pull_remote_database(state: State<'_, AppState>) -> Result<(), String> {
-    let (url, token, user, pass) = {
+pub async fn pull_remote_database(window: Window, state: State<'_, AppState>) -> Result<SyncReport, String> {
+    let (url, token, user, pass, batch_size) = {
         let profile = state.profile.read().await;
-        if profile.database_mode != "remote" {
+        if profile.database_mode != DatabaseMode::Remote {
             return Err("Not in remote mode".to_string());
         }
         if profile.remote_db_url.is_empty() {
              return Err("Remote URL not set".to_string());
         }
-        (profile.remote_db_url.clone(), profile.remote_db_token.clone(), profile.remote_db_user.clone(), profile.remote_db_pass.clone())
+        (profile.remote_db_url.clone(), profile.remote_db_token.clone(), profile.remote_db_user.clone(), profile.remote_db_pass.clone(), profile.sync_batch_size)
     };
 
     // Clone the db to release the lock before async I/O
@@ -85,20 +90,42 @@ pub async fn pull_remote_database(state: State<'_, AppState>) -> Result<(), Stri
         db_lock.clone().ok_or("Database not initialized")?
     };
 
-    db.pull_from_remote(&url, &token, &user, &pass).await.map_err(|e| e.to_string())
+    db.pull_from_remote(&url, &token, &user, &pass, batch_size, &window).await.map_err(|e| e.to_string())
 }
 
 #[command]
-pub async fn push_remote_database(state: State<'_, AppState>) -> Result<(), String> {
-    let (url, token, user, pass) = {
+pub async fn push_remote_database(window: Window, state: State<'_, AppState>) -> Result<SyncReport, String> {
+    let (url, token, user, pass, batch_size) = {
         let profile = state.profile.read().await;
-        if profile.database_mode != "remote" {
+        if profile.database_mode != DatabaseMode::Remote {
             return Err("Not in remote mode".to_string());
         }
         if profile.remote_db_url.is_empty() {
              return Err("Remote URL not set".to_string());
         }
-        (profile.remote_db_url.clone(), profile.remote_db_token.clone(), profile.remote_db_user.clone(), profile.remote_db_pass.clone())
+        (profile.remote_db_url.clone(), profile.remote_db_token.clone(), profile.remote_db_user.clone(), profile.remote_db_pass.clone(), profile.sync_batch_size)
+    };
+
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    db.push_to_remote(&url, &token, &user, &pass, batch_size, &window).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn sync_remote_database(window: Window, state: State<'_, AppState>) -> Result<SyncReport, String> {
+    let (url, token, user, pass, batch_size) = {
+        let profile = state.profile.read().await;
+        if profile.database_mode != DatabaseMode::Remote {
+            return Err("Not in remote mode".to_string());
+        }
+        if profile.remote_db_url.is_empty() {
+             return Err("Remote URL not set".to_string());
+        }
+        (profile.remote_db_url.clone(), profile.remote_db_token.clone(), profile.remote_db_user.clone(), profile.remote_db_pass.clone(), profile.sync_batch_size)
     };
 
     // Clone the db to release the lock before async I/O
@@ -107,7 +134,7 @@ pub async fn push_remote_database(state: State<'_, AppState>) -> Result<(), Stri
         db_lock.clone().ok_or("Database not initialized")?
     };
 
-    db.push_to_remote(&url, &token, &user, &pass).await.map_err(|e| e.to_string())
+    db.sync_with_remote(&url, &token, &user, &pass, batch_size, &window).await.map_err(|e| e.to_string())
 }
 
 #[command]
@@ -126,3 +153,152 @@ pub async fn test_database_connection(state: State<'_, AppState>) -> Result<Stri
         Err(e) => Err(format!("Connection failed: {}", e))
     }
 }
+
+#[command]
+pub async fn export_database(state: State<'_, AppState>, path: String, format: Option<String>) -> Result<usize, String> {
+    let path = Path::new(&path);
+    let format = DumpFormat::resolve(format.as_deref(), path).map_err(|e| e.to_string())?;
+
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    backup::export_to_file(&db, path, format).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn import_database(state: State<'_, AppState>, path: String, format: Option<String>, mode: Option<String>) -> Result<ImportReport, String> {
+    let path = Path::new(&path);
+    let format = DumpFormat::resolve(format.as_deref(), path).map_err(|e| e.to_string())?;
+    let mode: ImportMode = mode.as_deref().unwrap_or("merge").parse().map_err(|e: anyhow::Error| e.to_string())?;
+
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    backup::import_from_file(&db, path, format, mode).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn import_history_file(window: Window, state: State<'_, AppState>, folder: String) -> Result<ImportReport, String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    backup::import_history_folder(&window, &db, Path::new(&folder)).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn reconcile_history(window: Window, state: State<'_, AppState>, root: String, mode: Option<String>) -> Result<ReconcileReport, String> {
+    let mode: ReconcileMode = mode.as_deref().unwrap_or("report").parse().map_err(|e: anyhow::Error| e.to_string())?;
+
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    reconcile::reconcile_library(&window, &db, Path::new(&root), mode).await.map_err(|e| e.to_string())
+}
+
+/// Credits per API key and day, from `since` (`YYYY-MM-DD`) on.
+#[command]
+pub async fn list_key_usage(state: State<'_, AppState>, since: Option<String>) -> Result<Vec<KeyUsage>, String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    db.list_key_usage(since).await.map_err(|e| e.to_string())
+}
+
+/// Series that have glossary terms.
+#[command]
+pub async fn list_glossary_series(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    db.list_glossary_series().await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn list_glossary(state: State<'_, AppState>, series: String) -> Result<Vec<GlossaryTerm>, String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    db.list_glossary(&glossary::series_key(&series)).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn save_glossary_term(state: State<'_, AppState>, series: String, source: String, target: String, notes: Option<String>) -> Result<(), String> {
+    if source.trim().is_empty() || target.trim().is_empty() {
+        return Err("Source and target are required".to_string());
+    }
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    let term = GlossaryTerm {
+        series: glossary::series_key(&series),
+        source: source.trim().to_string(),
+        target: target.trim().to_string(),
+        notes: notes.filter(|n| !n.trim().is_empty()),
+    };
+    db.save_glossary_term(&term).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_glossary_term(state: State<'_, AppState>, series: String, source: String) -> Result<(), String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    db.delete_glossary_term(&glossary::series_key(&series), &source).await.map_err(|e| e.to_string())
+}
+
+/// Loads a `source,target,notes` CSV into a series' glossary (`merge` or `replace`).
+#[command]
+pub async fn import_glossary(state: State<'_, AppState>, series: String, path: String, mode: Option<String>) -> Result<usize, String> {
+    let mode: ImportMode = mode.as_deref().unwrap_or("merge").parse().map_err(|e: anyhow::Error| e.to_string())?;
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    glossary::import_from_file(&db, &series, Path::new(&path), mode).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn export_glossary(state: State<'_, AppState>, series: String, path: String) -> Result<usize, String> {
+    // Clone the db to release the lock before async I/O
+    let db = {
+        let db_lock = state.db.read().await;
+        db_lock.clone().ok_or("Database not initialized")?
+    };
+
+    glossary::export_to_file(&db, &series, Path::new(&path)).await.map_err(|e| e.to_string())
+}
+
+/// The series a folder's glossary would be looked up by.
+#[command]
+pub async fn detect_series(folder_path: String) -> Result<Option<String>, String> {
+    Ok(glossary::find_series(Path::new(&folder_path)))
+}
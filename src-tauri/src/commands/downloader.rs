@@ -1,19 +1,12 @@
-use tauri::{AppHandle, Emitter};
+use tauri::Window;
+use crate::utils::logger::{DownloadProgress, ProgressLogger};
 use std::path::Path;
 use tokio::fs;
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT, REFERER};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use regex::Regex;
 use base64::{Engine as _, engine::general_purpose};
 
-#[derive(Clone, Serialize)]
-struct DownloadProgress {
-    current: usize,
-    total: usize,
-    status: String,
-    filename: String,
-}
-
 #[tauri::command]
 pub async fn save_base64_image(
     target_path: String,
@@ -39,6 +32,12 @@ pub async fn save_base64_image(
     Ok(format!("Saved fallback image to {}", target_path))
 }
 
+const PROGRESS_EVENT: &str = "download_progress";
+
+fn progress(current: usize, total: usize, status: &str, filename: &str) -> DownloadProgress {
+    DownloadProgress { current, total, status: status.to_string(), filename: filename.to_string() }
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 struct NHentaiGallery {
@@ -68,25 +67,29 @@ struct NHentaiImage {
     h: u32,
 }
 
+/// Downloads the images of `url` (nhentai, pixiv or any page linking images) into `target_dir`.
+pub async fn download_url(logger: &impl ProgressLogger, url: String, target_dir: String, user_agent: Option<String>) -> Result<String, String> {
+    // Use provided UA or fallback to a generic recent Chrome
+    let ua = user_agent.unwrap_or_else(|| "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36".to_string());
+
+    if url.contains("nhentai") {
+        download_nhentai(logger, url, target_dir, ua).await
+    } else if url.contains("pixiv.net") {
+        download_pixiv(logger, url, target_dir, ua).await
+    } else {
+        download_generic(logger, url, target_dir, ua).await
+    }
+}
+
 #[tauri::command]
 pub async fn wrapper_download_url(
-    app_handle: AppHandle,
+    window: Window,
     url: String,
     _folder_name: Option<String>,
     target_dir: String,
     user_agent: Option<String>,
 ) -> Result<String, String> {
-    
-    // Use provided UA or fallback to a generic recent Chrome
-    let ua = user_agent.unwrap_or_else(|| "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36".to_string());
-
-    let result = if url.contains("nhentai") {
-        download_nhentai(app_handle.clone(), url.clone(), target_dir.clone(), ua.clone()).await
-    } else if url.contains("pixiv.net") {
-        download_pixiv(app_handle.clone(), url.clone(), target_dir.clone(), ua.clone()).await
-    } else {
-        download_generic(app_handle.clone(), url.clone(), target_dir.clone(), ua.clone()).await
-    };
+    let result = download_url(&window, url, target_dir, user_agent).await;
 
     match result {
         Ok(msg) => Ok(msg),
@@ -103,36 +106,17 @@ pub async fn wrapper_download_url(
     }
 }
 
-// ---------------------------------------------------------------------------
-// Generic Scraper: Scans ANY page for .jpg, .png, .webp links and downloads them.
-// Useful for direct links or simple gallery sites.
-// ---------------------------------------------------------------------------
-async fn download_generic(app_handle: AppHandle, url: String, target_dir: String, ua: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
-
-    // 1. Check if the URL itself is an image
-    if url.ends_with(".jpg") || url.ends_with(".jpeg") || url.ends_with(".png") || url.ends_with(".webp") {
-        let filename = url.split('/').last().unwrap_or("image.png").to_string();
-        let file_path = Path::new(&target_dir).join(&filename);
-        
-        // Single file download
-        app_handle.emit("download_progress", DownloadProgress {
-            current: 0,
-            total: 1,
-            status: "downloading_single".to_string(),
-            filename: filename.clone(),
-        }).map_err(|e| e.to_string())?;
-
-        let bytes = client.get(&url).header(USER_AGENT, &ua).send().await.map_err(|e| e.to_string())?
-            .bytes().await.map_err(|e| e.to_string())?;
-        
-        fs::write(&file_path, bytes).await.map_err(|e| e.to_string())?;
-        
-        return Ok(format!("Saved single image to {}", file_path.display()));
-    }
+/// Image links of a page (generic scraper only), for the Storage API to fetch directly.
+#[tauri::command]
+pub async fn list_image_urls(url: String, user_agent: Option<String>) -> Result<Vec<String>, String> {
+    let ua = user_agent.unwrap_or_else(|| "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36".to_string());
+    let (_, links) = scrape_image_links(&reqwest::Client::new(), &url, &ua).await?;
+    Ok(links.into_iter().map(|u| u.to_string()).collect())
+}
 
-    // 2. It's a webpage, fetch HTML and scan for images
-    let resp = client.get(&url).header(USER_AGENT, &ua).send().await.map_err(|e| e.to_string())?;
+// Fetches the page and returns its final URL and the deduplicated image links on it
+async fn scrape_image_links(client: &reqwest::Client, url: &str, ua: &str) -> Result<(reqwest::Url, Vec<reqwest::Url>), String> {
+    let resp = client.get(url).header(USER_AGENT, ua).send().await.map_err(|e| e.to_string())?;
     let base_url = resp.url().clone(); // For resolving relative links
     let html = resp.text().await.map_err(|e| e.to_string())?;
 
@@ -158,6 +142,34 @@ async fn download_generic(app_handle: AppHandle, url: String, target_dir: String
     if image_links.is_empty() {
         return Err("No images found on this page via generic scraper.".to_string());
     }
+    Ok((base_url, image_links))
+}
+
+// ---------------------------------------------------------------------------
+// Generic Scraper: Scans ANY page for .jpg, .png, .webp links and downloads them.
+// Useful for direct links or simple gallery sites.
+// ---------------------------------------------------------------------------
+async fn download_generic(logger: &impl ProgressLogger, url: String, target_dir: String, ua: String) -> Result<String, String> {
+    let client = reqwest::Client::new();
+
+    // 1. Check if the URL itself is an image
+    if url.ends_with(".jpg") || url.ends_with(".jpeg") || url.ends_with(".png") || url.ends_with(".webp") {
+        let filename = url.split('/').next_back().unwrap_or("image.png").to_string();
+        let file_path = Path::new(&target_dir).join(&filename);
+        
+        // Single file download
+        logger.download(PROGRESS_EVENT, progress(0, 1, "downloading_single", &filename));
+
+        let bytes = client.get(&url).header(USER_AGENT, &ua).send().await.map_err(|e| e.to_string())?
+            .bytes().await.map_err(|e| e.to_string())?;
+        
+        fs::write(&file_path, bytes).await.map_err(|e| e.to_string())?;
+        
+        return Ok(format!("Saved single image to {}", file_path.display()));
+    }
+
+    // 2. It's a webpage, fetch HTML and scan for images
+    let (base_url, image_links) = scrape_image_links(&client, &url, &ua).await?;
 
     // Create a folder based on page title or domain
     let folder_name = base_url.host_str().unwrap_or("generic_download");
@@ -171,15 +183,10 @@ async fn download_generic(app_handle: AppHandle, url: String, target_dir: String
 
     // Download loop
     for (i, img_url) in image_links.iter().enumerate() {
-        let filename = img_url.path_segments().and_then(|s| s.last()).unwrap_or("image.png");
+        let filename = img_url.path_segments().and_then(|mut s| s.next_back()).unwrap_or("image.png");
         let file_path = final_dir.join(filename);
 
-        app_handle.emit("download_progress", DownloadProgress {
-            current: i + 1,
-            total,
-            status: "downloading".to_string(),
-            filename: filename.to_string(),
-        }).map_err(|e| e.to_string())?;
+        logger.download(PROGRESS_EVENT, progress(i + 1, total, "downloading", filename));
 
         // Download with tolerance for failures
         if let Ok(response) = client.get(img_url.clone()).header(USER_AGENT, &ua).send().await {
@@ -189,17 +196,12 @@ async fn download_generic(app_handle: AppHandle, url: String, target_dir: String
         }
     }
 
-    app_handle.emit("download_progress", DownloadProgress {
-        current: total,
-        total,
-        status: "completed".to_string(),
-        filename: "Done".to_string(),
-    }).map_err(|e| e.to_string())?;
+    logger.download(PROGRESS_EVENT, progress(total, total, "completed", "Done"));
 
     Ok(format!("Generic scrape downloaded {} images to {}", total, final_dir.display()))
 }
 
-async fn download_nhentai(app_handle: AppHandle, url: String, target_dir: String, ua: String) -> Result<String, String> {
+async fn download_nhentai(logger: &impl ProgressLogger, url: String, target_dir: String, ua: String) -> Result<String, String> {
     let client = reqwest::Client::new();
     
     // Extract ID (Gallery ID) from URL
@@ -253,12 +255,7 @@ async fn download_nhentai(app_handle: AppHandle, url: String, target_dir: String
         let filename = format!("{:03}.{}", i + 1, ext);
         let file_path = final_dir.join(&filename);
         
-        app_handle.emit("download_progress", DownloadProgress {
-            current: i + 1,
-            total: total_pages,
-            status: "downloading".to_string(),
-            filename: filename.clone(),
-        }).map_err(|e| e.to_string())?;
+        logger.download(PROGRESS_EVENT, progress(i + 1, total_pages, "downloading", &filename));
 
         let img_bytes = client.get(&img_url)
              .header(USER_AGENT, &ua)
@@ -272,18 +269,13 @@ async fn download_nhentai(app_handle: AppHandle, url: String, target_dir: String
         fs::write(&file_path, img_bytes).await.map_err(|e| e.to_string())?;
     }
     
-    app_handle.emit("download_progress", DownloadProgress {
-        current: total_pages,
-        total: total_pages,
-        status: "completed".to_string(),
-        filename: "Done".to_string(),
-    }).map_err(|e| e.to_string())?;
+    logger.download(PROGRESS_EVENT, progress(total_pages, total_pages, "completed", "Done"));
 
     Ok(format!("Downloaded to {}", final_dir.display()))
 }
 
 // Minimal Pixiv implementation
-async fn download_pixiv(app_handle: AppHandle, url: String, target_dir: String, ua: String) -> Result<String, String> {
+async fn download_pixiv(logger: &impl ProgressLogger, url: String, target_dir: String, ua: String) -> Result<String, String> {
     let client = reqwest::Client::new();
     
     let re = Regex::new(r"artworks/(\d+)").map_err(|e| e.to_string())?;
@@ -332,15 +324,10 @@ async fn download_pixiv(app_handle: AppHandle, url: String, target_dir: String,
     
     for (i, page) in json.body.iter().enumerate() {
         let img_url = &page.urls.original;
-        let filename = img_url.split('/').last().unwrap_or("image.png");
+        let filename = img_url.split('/').next_back().unwrap_or("image.png");
         let file_path = final_dir.join(filename);
         
-        app_handle.emit("download_progress", DownloadProgress {
-            current: i + 1,
-            total: total,
-            status: "downloading".to_string(),
-            filename: filename.to_string(),
-        }).map_err(|e| e.to_string())?;
+        logger.download(PROGRESS_EVENT, progress(i + 1, total, "downloading", filename));
 
         let img_bytes = client.get(img_url)
              .headers(headers.clone())
@@ -354,12 +341,7 @@ async fn download_pixiv(app_handle: AppHandle, url: String, target_dir: String,
         fs::write(&file_path, img_bytes).await.map_err(|e| e.to_string())?;
     }
 
-    app_handle.emit("download_progress", DownloadProgress {
-        current: total,
-        total: total,
-        status: "completed".to_string(),
-        filename: "Done".to_string(),
-    }).map_err(|e| e.to_string())?;
+    logger.download(PROGRESS_EVENT, progress(total, total, "completed", "Done"));
 
     Ok(format!("Downloaded to {}", final_dir.display()))
 }
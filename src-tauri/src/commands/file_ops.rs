@@ -3,6 +3,7 @@ use tauri_plugin_shell::ShellExt;
 use std::path::{Path}; 
 use tauri::command;
 use serde::{Serialize, Deserialize};
+use tracing::{error, info};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileNode {
@@ -15,11 +16,11 @@ pub struct FileNode {
 
 #[tauri::command]
 pub fn open_folder(app: AppHandle, path: String) -> Result<(), String> {
-    println!("Opening folder: {}", path);
+    info!("Opening folder: {}", path);
     #[allow(deprecated)]
     app.shell().open(&path, None).map_err(|e| {
         let err = e.to_string();
-        eprintln!("Failed to open folder: {}", err);
+        error!("Failed to open folder: {}", err);
         err
     })
 }
@@ -29,10 +30,10 @@ pub fn open_translations_folder(app: AppHandle) -> Result<(), String> {
     let config_dir = app.path().app_config_dir().unwrap_or(std::path::PathBuf::from("."));
     let trans_dir = config_dir.join("translations");
     
-    println!("Opening translations folder: {:?}", trans_dir);
+    info!("Opening translations folder: {:?}", trans_dir);
     
     if !trans_dir.exists() {
-        println!("Creating translations directory...");
+        info!("Creating translations directory...");
         let _ = std::fs::create_dir_all(&trans_dir);
     }
     
@@ -44,7 +45,7 @@ pub fn open_translations_folder(app: AppHandle) -> Result<(), String> {
     #[allow(deprecated)]
     app.shell().open(trans_dir.to_string_lossy().to_string(), None).map_err(|e| {
         let err = e.to_string();
-        eprintln!("Failed to open translations folder: {}", err);
+        error!("Failed to open translations folder: {}", err);
         err
     })
 }
@@ -69,14 +70,12 @@ pub fn list_subdirectories(path: String) -> Result<Vec<String>, String> {
 
     let mut folders = Vec::new();
     if let Ok(entries) = std::fs::read_dir(root) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if entry.path().is_dir() {
-                    if let Ok(name) = entry.file_name().into_string() {
-                       if !name.starts_with('.') { // Skip hidden folders
-                           folders.push(name);
-                       }
-                    }
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                if let Ok(name) = entry.file_name().into_string() {
+                   if !name.starts_with('.') { // Skip hidden folders
+                       folders.push(name);
+                   }
                 }
             }
         }
@@ -1,4 +1,5 @@
-use tauri::{AppHandle, Emitter};
+use tauri::Window;
+use crate::utils::logger::{DownloadProgress, ProgressLogger};
 use std::path::Path;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -21,17 +22,22 @@ struct MangadexChapterData {
     data_saver: Vec<String>,
 }
 
-#[derive(Clone, Serialize)]
-struct DownloadProgress {
-    current: usize,
-    total: usize,
-    status: String,
-    filename: String,
-}
-
 #[tauri::command]
 pub async fn download_mangadex_chapter(
-    app_handle: AppHandle,
+    window: Window,
+    url: String,
+    folder_name: String,
+    target_dir: String,
+    use_data_saver: bool,
+    user_agent: String,
+    series_name: Option<String>,
+) -> Result<String, String> {
+    download_chapter(&window, url, folder_name, target_dir, use_data_saver, user_agent, series_name).await
+}
+
+/// Downloads a MangaDex chapter into `target_dir/[series_name]/folder_name`.
+pub async fn download_chapter(
+    logger: &impl ProgressLogger,
     url: String,
     folder_name: String,
     target_dir: String,
@@ -110,7 +116,7 @@ pub async fn download_mangadex_chapter(
         let file_path = download_path.join(filename);
 
         // Emit progress start for this file
-        let _ = app_handle.emit("mangadex-progress", DownloadProgress {
+        logger.download("mangadex-progress", DownloadProgress {
             current: index + 1,
             total: total_images,
             status: "downloading".to_string(),
@@ -128,7 +134,7 @@ pub async fn download_mangadex_chapter(
     }
 
     // Final success emit
-    let _ = app_handle.emit("mangadex-progress", DownloadProgress {
+    logger.download("mangadex-progress", DownloadProgress {
         current: total_images,
         total: total_images,
         status: "completed".to_string(),
@@ -183,7 +189,7 @@ pub async fn load_mangadex_history(target_dir: String) -> Result<MangadexHistory
     Ok(history)
 }
 
-fn extract_uuid_from_url(url: &str) -> Option<String> {
+pub fn extract_uuid_from_url(url: &str) -> Option<String> {
     // Supports: 
     // https://mangadex.org/chapter/UUID
     // https://mangadex.org/chapter/UUID/1
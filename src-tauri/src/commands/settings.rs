@@ -1,33 +1,47 @@
-use tauri::{State, AppHandle, Manager};
+use tauri::{State, AppHandle, Manager, Window};
 use crate::state::AppState;
-use crate::config::profile::Profile;
+use crate::core::api::{ApiClient, ApiEndpoints};
+use crate::core::catalog::{resolve_catalog, Catalog};
+use crate::config::profile::{Profile, SettingsError};
+use crate::config::profiles::{ProfileInfo, ProfileStore};
+use crate::utils::debug::{self, BundleInfo};
 use std::path::PathBuf;
 use std::fs;
 
-fn get_config_path(app: &AppHandle) -> PathBuf {
-    // Android: /data/user/0/com.furkan.tapi/files/config/profile.json
-    // Desktop: ~/.config/com.furkan.tapi/profile.json (or similar)
-    app.path().app_config_dir().unwrap_or(PathBuf::from(".")).join("profile.json")
+fn get_config_dir(app: &AppHandle) -> PathBuf {
+    // Android: /data/user/0/com.furkan.tapi/files/config/profiles/
+    // Desktop: ~/.config/com.furkan.tapi/profiles/ (or similar)
+    app.path().app_config_dir().unwrap_or(PathBuf::from("."))
+}
+
+pub fn get_profile_store(app: &AppHandle) -> ProfileStore {
+    ProfileStore::new(&get_config_dir(app))
 }
 
 #[tauri::command]
-pub async fn save_settings(app: AppHandle, state: State<'_, AppState>, settings: Profile) -> Result<(), String> {
-    // Update in-memory state
-    {
-        let mut profile = state.profile.write().await;
-        *profile = settings.clone();
-    }
-    
-    let path = get_config_path(&app);
-    
-    // Ensure directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+pub async fn save_settings(app: AppHandle, state: State<'_, AppState>, settings: serde_json::Value) -> Result<(), SettingsError> {
+    // Rejected with per-field errors before anything touches the disk
+    let mut settings = Profile::from_settings_json(settings)?;
+    let failed = |e: anyhow::Error| SettingsError::Failed { message: format!("Failed to save profile: {}", e) };
+
+    let mut profile = state.profile.write().await;
+    settings.keep_runtime_state(&profile);
+
+    // Save to disk (with encryption) under the active profile
+    get_profile_store(&app).save_active(&mut settings).map_err(failed)?;
+
+    // Turning the keyring off moved the secrets back into the file
+    if profile.use_keyring && !settings.use_keyring {
+        settings.forget_keyring().map_err(failed)?;
+    } else {
+        profile.forget_dropped_keys(&settings).map_err(failed)?;
     }
 
-    // Save to disk (with encryption)
-    settings.save(&path).map_err(|e| format!("Failed to save profile: {}", e))?;
-    
+    // Update in-memory state
+    state.set_language(settings.message_language()).await;
+    *profile = settings;
+    state.settings_changed.notify_one();
+
     Ok(())
 }
 
@@ -38,3 +52,103 @@ pub async fn load_settings(state: State<'_, AppState>) -> Result<Profile, String
     Ok(profile.clone())
 }
 
+#[tauri::command]
+pub async fn list_profiles(app: AppHandle) -> Result<Vec<ProfileInfo>, String> {
+    get_profile_store(&app).list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_profile(app: AppHandle, name: String) -> Result<(), String> {
+    get_profile_store(&app).create(&name).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clone_profile(app: AppHandle, from: String, to: String) -> Result<(), String> {
+    get_profile_store(&app).clone_profile(&from, &to).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn switch_profile(app: AppHandle, state: State<'_, AppState>, name: String) -> Result<Profile, String> {
+    let new_profile = get_profile_store(&app).switch(&name).map_err(|e| e.to_string())?;
+
+    state.set_language(new_profile.message_language()).await;
+    let mut profile = state.profile.write().await;
+    *profile = new_profile.clone();
+    state.settings_changed.notify_one();
+    Ok(new_profile)
+}
+
+#[tauri::command]
+pub async fn delete_profile(app: AppHandle, name: String) -> Result<(), String> {
+    get_profile_store(&app).delete(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unlock_profile(state: State<'_, AppState>, passphrase: String) -> Result<Profile, String> {
+    let mut profile = state.profile.write().await;
+    profile.unlock(&passphrase).map_err(|e| e.to_string())?;
+    Ok(profile.clone())
+}
+
+/// Enables the master passphrase, or goes back to machine-bound encryption when `passphrase` is None.
+#[tauri::command]
+pub async fn set_master_passphrase(app: AppHandle, state: State<'_, AppState>, passphrase: Option<String>) -> Result<(), String> {
+    let mut profile = state.profile.write().await;
+    let mut updated = profile.clone();
+    updated.set_passphrase(passphrase.as_deref()).map_err(|e| e.to_string())?;
+    get_profile_store(&app).save_active(&mut updated).map_err(|e| format!("Failed to save profile: {}", e))?;
+    *profile = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn export_profile(state: State<'_, AppState>, path: String, passphrase: String) -> Result<(), String> {
+    let content = {
+        let profile = state.profile.read().await;
+        profile.export_portable(&passphrase).map_err(|e| e.to_string())?
+    };
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Imports an exported profile as a new profile `name`, re-encrypted for this machine.
+#[tauri::command]
+pub async fn import_profile(app: AppHandle, path: String, passphrase: String, name: String) -> Result<(), String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let profile = Profile::import_portable(&content, &passphrase).map_err(|e| e.to_string())?;
+
+    let store = get_profile_store(&app);
+    if store.exists(&name) {
+        return Err(format!("Profile '{}' already exists", name));
+    }
+    store.save(&name, &profile).map_err(|e| e.to_string())
+}
+
+/// Models, fonts and prices from the provider (cached in the database for a day).
+#[tauri::command]
+pub async fn get_catalog(window: Window, state: State<'_, AppState>, refresh: Option<bool>) -> Result<Catalog, String> {
+    let client = {
+        let profile = state.profile.read().await;
+        let endpoints = ApiEndpoints { catalog: profile.catalog_url.clone(), ..Default::default() };
+        ApiClient::new_with_endpoints(profile.api_key.clone().unwrap_or_default(), endpoints)
+    };
+    // Clone the db to release the lock before async I/O
+    let db = state.db.read().await.clone();
+    Ok(resolve_catalog(&window, &client, db.as_ref(), refresh.unwrap_or(false)).await)
+}
+
+/// Debug bundles of past runs (enabled by the profile's debug setting), newest first.
+#[tauri::command]
+pub async fn list_debug_bundles() -> Result<Vec<BundleInfo>, String> {
+    Ok(debug::list_bundles())
+}
+
+/// Zips a debug bundle (the newest when `name` is empty) to `path` for a bug report.
+#[tauri::command]
+pub async fn zip_debug_bundle(name: Option<String>, path: String) -> Result<String, String> {
+    let name = name.filter(|n| !n.trim().is_empty());
+    debug::zip_bundle(name.as_deref(), std::path::Path::new(&path))
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
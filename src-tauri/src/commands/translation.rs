@@ -2,7 +2,16 @@ use tauri::{State, Window};
 use crate::state::AppState;
 use crate::modes::cli_mode::start_cli_translation;
 use crate::modes::archive_mode::start_archive_translation;
+use crate::modes::storage_mode;
+use crate::modes::ocr_mode::start_ocr_run;
+use crate::core::ocr::TextFormat;
+use crate::modes::script_mode::start_script_render;
+use crate::core::script::{Script, ScriptFormat};
+use crate::core::report::RunReport;
+use crate::core::processor::TranslationOptions;
+use crate::config::language::t;
 use std::path::Path;
+use tracing::info;
 
 #[tauri::command]
 pub async fn start_translation(
@@ -18,40 +27,172 @@ pub async fn start_translation(
     inpaint_only: Option<bool>,
     min_font_size: Option<u32>,
     output_folder: Option<String>,
-    included_paths: Option<Vec<String>>
-) -> Result<(), String> {
-    println!("Starting translation for {} with model {}", folder_path, model);
+    included_paths: Option<Vec<String>>,
+    dry_run: Option<bool>
+) -> Result<RunReport, String> {
+    info!("Starting translation for {} with model {}", folder_path, model);
     
     let api_key = {
         let profile = state.profile.read().await;
-        profile.api_key.clone().ok_or("API Key not found in settings")?
+        profile.ensure_unlocked().map_err(|e| e.to_string())?;
+        // A dry run only estimates, it never calls the API
+        if !profile.has_api_key() && dry_run != Some(true) {
+            return Err(t("ERROR_API_KEY_NOT_SET", &[]));
+        }
+        // Only used when the profile has no key pool
+        profile.api_key.clone().unwrap_or_default()
     };
 
     let path = Path::new(&folder_path);
     let mode_str = mode.unwrap_or_else(|| "cli".to_string());
-    let t_lang = target_lang.unwrap_or_else(|| "en".to_string());
-    let font_str = font.unwrap_or_else(|| "wildwords".to_string());
-    
-    let txt_align = text_align.unwrap_or_else(|| "auto".to_string());
-    let stroke = stroke_disabled.unwrap_or(false);
-    let inpaint = inpaint_only.unwrap_or(false);
-    let min_size = min_font_size.unwrap_or(12);
-    
-    let profile_state = Some(state.profile.clone());
-    let db_state = Some(state.db.clone());
+    let options = TranslationOptions {
+        model,
+        target_lang: target_lang.unwrap_or_else(|| "en".to_string()),
+        font: font.unwrap_or_else(|| "wildwords".to_string()),
+        text_align: text_align.unwrap_or_else(|| "auto".to_string()),
+        stroke_disabled: stroke_disabled.unwrap_or(false),
+        inpaint_only: inpaint_only.unwrap_or(false),
+        min_font_size: min_font_size.unwrap_or(12),
+        included_paths,
+        dry_run: dry_run.unwrap_or(false),
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
 
-    match mode_str.as_str() {
+    let report = match mode_str.as_str() {
         "archive" => {
-            start_archive_translation(&window, path, &model, &api_key, &t_lang, &font_str, &txt_align, stroke, inpaint, min_size, profile_state, db_state, output_folder, included_paths)
+            start_archive_translation(&window, path, output_folder, options)
                 .await
-                .map_err(|e| e.to_string())?;
+                .map_err(|e| e.to_string())?
         },
         _ => {
-            start_cli_translation(&window, path, &model, &api_key, &t_lang, &font_str, &txt_align, stroke, inpaint, min_size, profile_state, db_state, output_folder, included_paths)
+            start_cli_translation(&window, path, output_folder, options)
                 .await
-                .map_err(|e| e.to_string())?;
+                .map_err(|e| e.to_string())?
         }
-    }
+    };
         
-    Ok(())
+    Ok(report)
+}
+
+/// Translates images by URL through the Storage API (no local files are uploaded).
+#[tauri::command]
+pub async fn start_storage_translation(
+    window: Window,
+    state: State<'_, AppState>,
+    urls: Vec<String>,
+    output_folder: String,
+    model: String,
+    target_lang: Option<String>,
+    font: Option<String>,
+    text_align: Option<String>,
+    stroke_disabled: Option<bool>,
+    inpaint_only: Option<bool>,
+    min_font_size: Option<u32>,
+    dry_run: Option<bool>
+) -> Result<RunReport, String> {
+    let api_key = {
+        let profile = state.profile.read().await;
+        profile.ensure_unlocked().map_err(|e| e.to_string())?;
+        if !profile.has_api_key() && dry_run != Some(true) {
+            return Err(t("ERROR_API_KEY_NOT_SET", &[]));
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+
+    let options = TranslationOptions {
+        model,
+        target_lang: target_lang.unwrap_or_else(|| "en".to_string()),
+        font: font.unwrap_or_else(|| "wildwords".to_string()),
+        text_align: text_align.unwrap_or_else(|| "auto".to_string()),
+        stroke_disabled: stroke_disabled.unwrap_or(false),
+        inpaint_only: inpaint_only.unwrap_or(false),
+        min_font_size: min_font_size.unwrap_or(12),
+        dry_run: dry_run.unwrap_or(false),
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    storage_mode::start_storage_translation(&window, &urls, Path::new(&output_folder), options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// OCR only: writes per-page JSON and a chapter text file (`plain`, `markdown` or `tsv`).
+#[tauri::command]
+pub async fn start_ocr(
+    window: Window,
+    state: State<'_, AppState>,
+    folder_path: String,
+    output_folder: Option<String>,
+    text_format: Option<String>,
+    included_paths: Option<Vec<String>>
+) -> Result<RunReport, String> {
+    let api_key = {
+        let profile = state.profile.read().await;
+        profile.ensure_unlocked().map_err(|e| e.to_string())?;
+        if !profile.has_api_key() {
+            return Err(t("ERROR_API_KEY_NOT_SET", &[]));
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+
+    let format: TextFormat = text_format.as_deref().unwrap_or("plain").parse().map_err(|e: anyhow::Error| e.to_string())?;
+
+    let options = TranslationOptions {
+        included_paths,
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    start_ocr_run(&window, Path::new(&folder_path), output_folder, format, options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Writes the bilingual script of an OCR output folder to `path` (`.csv` or JSON).
+#[tauri::command]
+pub async fn export_script(ocr_folder: String, path: String) -> Result<Script, String> {
+    let script = Script::from_ocr_dir(Path::new(&ocr_folder)).map_err(|e| e.to_string())?;
+    let path = Path::new(&path);
+    script.save(path, ScriptFormat::from_path(path)).map_err(|e| e.to_string())?;
+    Ok(script)
+}
+
+/// Re-renders pages from an edited script without running OCR or translation again.
+#[tauri::command]
+pub async fn render_script(
+    window: Window,
+    state: State<'_, AppState>,
+    script_path: String,
+    folder_path: String,
+    output_folder: Option<String>,
+    model: String,
+    target_lang: Option<String>,
+    font: Option<String>,
+    text_align: Option<String>,
+    stroke_disabled: Option<bool>,
+    min_font_size: Option<u32>,
+    dry_run: Option<bool>
+) -> Result<RunReport, String> {
+    let api_key = {
+        let profile = state.profile.read().await;
+        profile.ensure_unlocked().map_err(|e| e.to_string())?;
+        if !profile.has_api_key() && dry_run != Some(true) {
+            return Err(t("ERROR_API_KEY_NOT_SET", &[]));
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+
+    let options = TranslationOptions {
+        model,
+        target_lang: target_lang.unwrap_or_else(|| "en".to_string()),
+        font: font.unwrap_or_else(|| "wildwords".to_string()),
+        text_align: text_align.unwrap_or_else(|| "auto".to_string()),
+        stroke_disabled: stroke_disabled.unwrap_or(false),
+        min_font_size: min_font_size.unwrap_or(12),
+        dry_run: dry_run.unwrap_or(false),
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    start_script_render(&window, Path::new(&script_path), Path::new(&folder_path), output_folder, options)
+        .await
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,100 @@
+use crate::config::profile::Profile;
+use tracing::warn;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// How often a running translation writes credit usage back to the profile file.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Debounced credit-usage persistence for long runs. Changes are made in memory under a short
+/// write lock; the stored profile is reloaded and its usage fields written at most once per
+/// interval, on a blocking thread, so neither the lock nor the runtime waits for the disk.
+pub struct ProfileAutosave {
+    profile: Arc<RwLock<Profile>>,
+    interval: Duration,
+    last_save: Instant,
+    dirty: bool,
+    pending: Option<JoinHandle<()>>,
+}
+
+impl ProfileAutosave {
+    pub fn new(profile: Arc<RwLock<Profile>>, interval: Duration) -> Self {
+        Self {
+            profile,
+            interval,
+            last_save: Instant::now(),
+            dirty: false,
+            pending: None,
+        }
+    }
+
+    /// Notes a change; starts a background save once the interval has passed.
+    pub async fn touch(&mut self) {
+        self.dirty = true;
+        // One write at a time, the next one picks up everything changed meanwhile
+        if self.pending.as_ref().is_some_and(|h| !h.is_finished()) {
+            return;
+        }
+        if self.last_save.elapsed() >= self.interval {
+            self.spawn_save().await;
+        }
+    }
+
+    /// Writes what is still unsaved and waits for it (end of run).
+    pub async fn flush(&mut self) {
+        if let Some(handle) = self.pending.take() {
+            let _ = handle.await;
+        }
+        if self.dirty {
+            self.spawn_save().await;
+            if let Some(handle) = self.pending.take() {
+                let _ = handle.await;
+            }
+        }
+    }
+
+    async fn spawn_save(&mut self) {
+        let (path, usage) = {
+            let profile = self.profile.read().await;
+            (profile.source_path.clone(), CreditUsage::of(&profile))
+        };
+        self.dirty = false;
+        self.last_save = Instant::now();
+        // Profiles that were not loaded from a file (CLI defaults) are never written
+        let Some(path) = path else { return };
+        self.pending = Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = usage.save_to(&path) {
+                warn!("PROFILE AUTOSAVE ERROR: {}", e);
+            }
+        }));
+    }
+}
+
+// The fields a run updates. The in-memory profile also carries overrides (flags, environment,
+// keys entered but not saved), so only these go to the file.
+struct CreditUsage {
+    total_credits_used: u64,
+    credit_balance: Option<u64>,
+    credit_balance_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl CreditUsage {
+    fn of(profile: &Profile) -> Self {
+        Self {
+            total_credits_used: profile.total_credits_used,
+            credit_balance: profile.credit_balance,
+            credit_balance_at: profile.credit_balance_at,
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        let mut stored = Profile::load(path)?;
+        stored.total_credits_used = self.total_credits_used;
+        stored.credit_balance = self.credit_balance;
+        stored.credit_balance_at = self.credit_balance_at;
+        stored.save(path)
+    }
+}
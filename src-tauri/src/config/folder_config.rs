@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+pub const FOLDER_CONFIG_FILE: &str = ".tapi.toml";
+
+/// Per-folder overrides read from `<input folder>/.tapi.toml`, e.g.
+///
+/// ```toml
+/// target_lang = "tr"
+/// font = "animeace"
+/// model = "gemini-2.5-flash"
+/// output = "../translated/series"
+/// series = "One Piece"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FolderConfig {
+    pub target_lang: Option<String>,
+    pub font: Option<String>,
+    pub model: Option<String>,
+    /// Output location, relative paths are resolved against the input folder
+    pub output: Option<String>,
+    /// Series whose glossary applies, when the folder names do not say
+    pub series: Option<String>,
+}
+
+impl FolderConfig {
+    /// Reads the folder's `.tapi.toml`. No file means no overrides.
+    pub fn load(folder: &Path) -> Result<Option<Self>> {
+        let path = folder.join(FOLDER_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let config: FolderConfig = toml::from_str(&content)
+            .map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?;
+        Ok(Some(config))
+    }
+
+    pub fn output_dir(&self, folder: &Path) -> Option<PathBuf> {
+        self.output.as_ref()
+            .filter(|o| !o.trim().is_empty())
+            .map(|o| folder.join(o.trim()))
+    }
+
+    /// Applies the overrides on top of the run settings, describing each change for the log.
+    pub fn apply(&self, folder: &Path, model: &mut String, target_lang: &mut String, font: &mut String, output_folder: &mut Option<String>) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        for (key, value, target) in [
+            ("model", &self.model, model),
+            ("target_lang", &self.target_lang, target_lang),
+            ("font", &self.font, font),
+        ] {
+            if let Some(v) = value.as_ref().filter(|v| !v.trim().is_empty()) {
+                changes.push(format!("{} = {}", key, v.trim()));
+                *target = v.trim().to_string();
+            }
+        }
+
+        if let Some(dir) = self.output_dir(folder) {
+            changes.push(format!("output = {}", dir.display()));
+            *output_folder = Some(dir.to_string_lossy().to_string());
+        }
+
+        changes
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+/// Profile values of the form `keyring:<account>` live in the secret store.
+pub const KEYRING_PREFIX: &str = "keyring:";
+const SERVICE: &str = "com.furkan.tapi";
+// TAPI_KEYRING=off disables the OS keyring, TAPI_KEYRING=mock uses an in-memory store
+const KEYRING_ENV: &str = "TAPI_KEYRING";
+
+/// Platform secret store (Secret Service, macOS Keychain, Windows Credential Manager) or a stand-in.
+pub trait SecretStore: Send + Sync {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, value: &str) -> Result<()>;
+    fn delete(&self, account: &str) -> Result<()>;
+    /// Whether values outlive the process. Profiles only store `keyring:` references in
+    /// stores that do; otherwise the reference would point at nothing on the next start.
+    fn is_persistent(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub struct OsKeyring;
+
+#[cfg(not(target_os = "android"))]
+impl OsKeyring {
+    fn entry(account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(SERVICE, account).map_err(|e| anyhow!("Keyring error: {}", e))
+    }
+
+    /// Reading a missing entry works on any usable backend, so this tells whether one exists.
+    fn is_available() -> bool {
+        match keyring::Entry::new(SERVICE, "availability-probe") {
+            Ok(entry) => matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl SecretStore for OsKeyring {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match Self::entry(account)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Keyring error: {}", e)),
+        }
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<()> {
+        Self::entry(account)?.set_password(value).map_err(|e| anyhow!("Keyring error: {}", e))
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match Self::entry(account)?.delete_credential() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Keyring error: {}", e)),
+        }
+    }
+}
+
+/// In-memory store for headless runs and tests.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        Ok(self.entries.lock().map_err(|_| anyhow!("Secret store poisoned"))?.get(account).cloned())
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<()> {
+        self.entries.lock().map_err(|_| anyhow!("Secret store poisoned"))?.insert(account.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        self.entries.lock().map_err(|_| anyhow!("Secret store poisoned"))?.remove(account);
+        Ok(())
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
+
+// None until detected; then the store, or None when there is no usable keyring
+type StoreSlot = RwLock<Option<Option<Arc<dyn SecretStore>>>>;
+
+fn slot() -> &'static StoreSlot {
+    static STORE: OnceLock<StoreSlot> = OnceLock::new();
+    STORE.get_or_init(|| RwLock::new(None))
+}
+
+fn detect() -> Option<Arc<dyn SecretStore>> {
+    match std::env::var(KEYRING_ENV).unwrap_or_default().to_lowercase().as_str() {
+        "off" => None,
+        "mock" => Some(Arc::new(MemoryStore::default())),
+        #[cfg(not(target_os = "android"))]
+        _ if OsKeyring::is_available() => Some(Arc::new(OsKeyring)),
+        _ => None,
+    }
+}
+
+/// The secret store in use, or None when no keyring is available (profile falls back to encryption).
+/// Detected once per process.
+pub fn store() -> Option<Arc<dyn SecretStore>> {
+    if let Some(cached) = slot().read().ok().and_then(|s| s.clone()) {
+        return cached;
+    }
+    let detected = detect();
+    if let Ok(mut s) = slot().write() {
+        *s = Some(detected.clone());
+    }
+    detected
+}
+
+/// The secret store when it keeps values across restarts, i.e. one that `keyring:` references
+/// may be saved for. None under `TAPI_KEYRING=mock`.
+pub fn persistent_store() -> Option<Arc<dyn SecretStore>> {
+    store().filter(|s| s.is_persistent())
+}
+
+/// Replaces the detected store, e.g. with a `MemoryStore` in tests.
+pub fn set_store(store: Option<Arc<dyn SecretStore>>) {
+    if let Ok(mut s) = slot().write() {
+        *s = Some(store);
+    }
+}
+
+pub fn account(profile_id: &str, field: &str) -> String {
+    format!("{}/{}", profile_id, field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::profile::Profile;
+    use std::fs;
+
+    // Stands in for the OS keyring: in memory, but reported as persistent
+    #[derive(Default)]
+    struct PersistentMemory(MemoryStore);
+
+    impl SecretStore for PersistentMemory {
+        fn get(&self, account: &str) -> Result<Option<String>> {
+            self.0.get(account)
+        }
+
+        fn set(&self, account: &str, value: &str) -> Result<()> {
+            self.0.set(account, value)
+        }
+
+        fn delete(&self, account: &str) -> Result<()> {
+            self.0.delete(account)
+        }
+    }
+
+    #[test]
+    fn memory_store_round_trips() {
+        let store = MemoryStore::default();
+        let account = account("profile", "api_key");
+        assert_eq!(store.get(&account).unwrap(), None);
+
+        store.set(&account, "first").unwrap();
+        store.set(&account, "second").unwrap();
+        assert_eq!(store.get(&account).unwrap().as_deref(), Some("second"));
+
+        store.delete(&account).unwrap();
+        assert_eq!(store.get(&account).unwrap(), None);
+        // Deleting a missing entry is not an error
+        store.delete(&account).unwrap();
+        assert!(!store.is_persistent());
+    }
+
+    // The only test that swaps the process-wide store
+    #[test]
+    fn profiles_store_keyring_references_only_in_persistent_stores() {
+        let path = std::env::temp_dir().join(format!("tapi-keystore-{}.json", std::process::id()));
+        let mut profile = Profile::default();
+        profile.use_keyring = true;
+        profile.api_key = Some("sk-test-key".to_string());
+
+        set_store(Some(Arc::new(MemoryStore::default())));
+        profile.save(&path).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains(KEYRING_PREFIX));
+        assert_eq!(Profile::load(&path).unwrap().api_key.as_deref(), Some("sk-test-key"));
+
+        let keyring = Arc::new(PersistentMemory::default());
+        set_store(Some(keyring.clone()));
+        profile.save(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains(KEYRING_PREFIX));
+        assert_eq!(keyring.get(&account(&profile.keyring_id, "api_key")).unwrap().as_deref(), Some("sk-test-key"));
+        assert_eq!(Profile::load(&path).unwrap().api_key.as_deref(), Some("sk-test-key"));
+
+        set_store(None);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,43 +1,193 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use std::fs;
 use anyhow::Result;
+use serde_json::Value;
+
+/// Language every other one falls back to, key by key
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// Folder (next to the executable, in the data dir or the working dir) searched for
+/// `<code>.ini` / `<code>.json` files that add or override languages
+pub const LANG_DIR: &str = "lang";
+
+// Shipped with the binary, so messages work without a lang folder
+const BUNDLED: &[(&str, &str)] = &[
+    ("en", include_str!("../../../lang/en.ini")),
+    ("tr", include_str!("../../../lang/tr.ini")),
+];
+
+static DIRS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+static CURRENT: RwLock<Option<Arc<Language>>> = RwLock::new(None);
 
 #[derive(Clone, Debug, Default)]
 pub struct Language {
-    #[allow(dead_code)]
+    pub code: String,
     pub strings: HashMap<String, String>,
 }
 
 impl Language {
     pub fn load(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)?;
+        let code = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let strings = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => parse_json(&content)?,
+            _ => parse_ini(&content),
+        };
+        Ok(Language { code, strings })
+    }
+
+    /// English, overlaid with the bundled strings of `code`, then with any `<code>.ini` or
+    /// `<code>.json` found in `dirs` (first dir wins). Unknown codes end up English.
+    pub fn for_code(code: &str, dirs: &[PathBuf]) -> Self {
+        let code = code.trim().to_lowercase();
         let mut strings = HashMap::new();
-        let mut current_section = String::new();
+        for (bundled, content) in BUNDLED {
+            if *bundled == DEFAULT_LANGUAGE {
+                strings.extend(parse_ini(content));
+            }
+        }
+        if code != DEFAULT_LANGUAGE {
+            if let Some((_, content)) = BUNDLED.iter().find(|(c, _)| *c == code) {
+                strings.extend(parse_ini(content));
+            }
+        }
+        for dir in dirs.iter().rev() {
+            for ext in ["json", "ini"] {
+                let path = dir.join(format!("{}.{}", code, ext));
+                if !path.is_file() {
+                    continue;
+                }
+                match Language::load(&path) {
+                    Ok(lang) => strings.extend(lang.strings),
+                    Err(e) => tracing::warn!("Could not read {}: {}", path.display(), e),
+                }
+            }
+        }
+        Language { code, strings }
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.strings.get(key).cloned().unwrap_or_else(|| key.to_string())
+    }
+
+    /// The message for `key` with its `{name}` placeholders filled from `args`.
+    /// `{name:.2f}` style placeholders (from the Python-era files) round numbers.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        interpolate(&self.get(key), args)
+    }
+}
+
+fn parse_ini(content: &str) -> HashMap<String, String> {
+    let mut strings = HashMap::new();
+    let mut current_section = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            current_section = line[1..line.len()-1].to_string();
+            continue;
+        }
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
-                continue;
+        if current_section == "strings" {
+            if let Some((k, v)) = line.split_once('=') {
+                strings.insert(k.trim().to_string(), v.trim().to_string());
             }
+        }
+    }
+    strings
+}
 
-            if line.starts_with('[') && line.ends_with(']') {
-                current_section = line[1..line.len()-1].to_string();
-                continue;
+// Flat `{"KEY": "text"}`, or the UI's nested translations with the backend's keys under
+// `"backend"`; other nested keys are kept as `section.key`
+fn parse_json(content: &str) -> Result<HashMap<String, String>> {
+    fn flatten(prefix: &str, value: &Value, out: &mut HashMap<String, String>) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    let key = match prefix {
+                        "" | "backend" => k.clone(),
+                        p => format!("{}.{}", p, k),
+                    };
+                    flatten(&key, v, out);
+                }
+            }
+            Value::String(s) => {
+                out.insert(prefix.to_string(), s.clone());
             }
+            _ => {}
+        }
+    }
+    let value: Value = serde_json::from_str(content)?;
+    let mut strings = HashMap::new();
+    flatten("", &value, &mut strings);
+    Ok(strings)
+}
 
-            if current_section == "strings" {
-                if let Some((k, v)) = line.split_once('=') {
-                    strings.insert(k.trim().to_string(), v.trim().to_string());
+fn interpolate(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let inner = &rest[start + 1..start + len];
+        let (name, spec) = inner.split_once(':').unwrap_or((inner, ""));
+        match args.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => {
+                let value = value.to_string();
+                let precision = spec.strip_prefix('.').and_then(|s| s.trim_end_matches('f').parse::<usize>().ok());
+                match (precision, value.parse::<f64>()) {
+                    (Some(p), Ok(number)) => out.push_str(&format!("{:.*}", p, number)),
+                    _ => out.push_str(&value),
                 }
             }
+            None => out.push_str(&rest[start..start + len + 1]),
         }
-        
-        Ok(Language { strings })
+        rest = &rest[start + len + 1..];
     }
+    out.push_str(rest);
+    out
+}
 
-    #[allow(dead_code)]
-    pub fn get(&self, key: &str) -> String {
-        self.strings.get(key).cloned().unwrap_or_else(|| key.to_string())
+/// Where `lang/` folders are looked for: the data dir, next to the executable, then the
+/// working dir. Called once at startup; `use_language` reads from these.
+pub fn set_dirs(data_dir: &Path) {
+    let mut dirs = vec![data_dir.join(LANG_DIR)];
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|p| p.parent().map(Path::to_path_buf)) {
+        dirs.push(exe_dir.join(LANG_DIR));
+    }
+    dirs.push(PathBuf::from(LANG_DIR));
+    if let Ok(mut current) = DIRS.write() {
+        *current = dirs;
+    }
+}
+
+/// Switches backend messages to `code` (a profile's `message_language`) and returns the loaded strings.
+pub fn use_language(code: &str) -> Arc<Language> {
+    let dirs = DIRS.read().map(|d| d.clone()).unwrap_or_default();
+    let language = Arc::new(Language::for_code(code, &dirs));
+    if let Ok(mut current) = CURRENT.write() {
+        *current = Some(language.clone());
     }
+    language
+}
+
+pub fn current() -> Arc<Language> {
+    if let Some(language) = CURRENT.read().ok().and_then(|c| c.clone()) {
+        return language;
+    }
+    use_language(DEFAULT_LANGUAGE)
+}
+
+/// A backend message in the current language, e.g. `t("INFO_FILES_TO_PROCESS", &[("count", &n)])`.
+pub fn t(key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    current().format(key, args)
 }
@@ -1,2 +1,8 @@
 pub mod language;
 pub mod profile;
+pub mod profiles;
+pub mod folder_config;
+pub mod runtime;
+pub mod secrets;
+pub mod keystore;
+pub mod autosave;
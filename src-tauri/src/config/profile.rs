@@ -1,18 +1,170 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use anyhow::Result;
-use base64::{Engine as _, engine::general_purpose};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use crate::config::keystore::{self, KEYRING_PREFIX};
+use crate::config::secrets::{self, KdfParams, SecretKey, SecretKind};
+use tracing::warn;
+
+/// Bumped whenever a stored field changes meaning; see `MIGRATIONS`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseMode {
+    #[default]
+    Off,
+    Local,
+    Remote,
+}
+
+impl std::str::FromStr for DatabaseMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(DatabaseMode::Off),
+            "local" => Ok(DatabaseMode::Local),
+            "remote" => Ok(DatabaseMode::Remote),
+            other => Err(format!("unknown database mode '{}' (expected off, local or remote)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for DatabaseMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DatabaseMode::Off => "off",
+            DatabaseMode::Local => "local",
+            DatabaseMode::Remote => "remote",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// How the processor picks a key from `Profile::api_keys` for each page.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStrategy {
+    /// Rotate through the keys page by page
+    #[default]
+    RoundRobin,
+    /// Stay on the first key until it is rejected (401/402/403), then move to the next
+    Failover,
+    /// Use the key with the most daily budget left; keys without a budget come last
+    DailyBudget,
+}
+
+impl std::str::FromStr for KeyStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "round_robin" => Ok(KeyStrategy::RoundRobin),
+            "failover" => Ok(KeyStrategy::Failover),
+            "daily_budget" => Ok(KeyStrategy::DailyBudget),
+            other => Err(format!("unknown key strategy '{}' (expected round_robin, failover or daily_budget)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyStrategy::RoundRobin => "round_robin",
+            KeyStrategy::Failover => "failover",
+            KeyStrategy::DailyBudget => "daily_budget",
+        })
+    }
+}
+
+/// One key of a shared pool. The label identifies it in reports and usage records.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ApiKeyEntry {
+    pub label: String,
+    #[serde(default)]
+    pub key: String,
+    /// Credits this key may spend per day (UTC), None for no limit
+    #[serde(default)]
+    pub daily_budget: Option<u64>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// A settings problem tied to one field, returned to the UI as-is.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// Why settings could not be saved: bad input (per field) or a storage failure.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsError {
+    Invalid { errors: Vec<FieldError> },
+    Failed { message: String },
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Invalid { errors } => {
+                let parts: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+                write!(f, "Invalid settings: {}", parts.join("; "))
+            }
+            SettingsError::Failed { message } => f.write_str(message),
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Profile {
+    #[serde(default)]
+    pub schema_version: u32,
     pub api_key: Option<String>,
+    // Labelled key pool; when non-empty it is used instead of `api_key`
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyEntry>,
+    #[serde(default)]
+    pub key_strategy: KeyStrategy,
     pub language: String,
+    // Locale of the app's interface, which backend messages follow too (empty: `language`)
+    #[serde(default)]
+    pub interface_language: String,
     pub model: Option<String>,
     pub font: Option<String>,
-    pub theme: String,
+    #[serde(default)]
+    pub theme: Theme,
     #[serde(default)]
     pub total_credits_used: u64,
+    // Balance the provider last reported, and when
+    #[serde(default)]
+    pub credit_balance: Option<u64>,
+    #[serde(default)]
+    pub credit_balance_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Credits per image by model, on top of the built-in pricing table
+    #[serde(default)]
+    pub model_costs: BTreeMap<String, u64>,
+    // Hard caps; a run stops before the next page would exceed them (None = no limit)
+    #[serde(default)]
+    pub max_credits_per_run: Option<u64>,
+    #[serde(default)]
+    pub max_credits_per_day: Option<u64>,
     // Custom API Endpoints
     #[serde(default = "default_storage_url")]
     pub storage_url: String,
@@ -20,9 +172,12 @@ pub struct Profile {
     pub ocr_url: String,
     #[serde(default = "default_translate_url")]
     pub translate_url: String,
-    // Storage parameters
-    #[serde(default)]
-    pub storage_urls: String,
+    // Endpoint listing models, fonts and prices; empty uses the bundled list
+    #[serde(default = "default_catalog_url")]
+    pub catalog_url: String,
+    // Image URLs for the Storage API workflow (a comma/newline separated string is accepted too)
+    #[serde(default, deserialize_with = "string_or_list")]
+    pub storage_urls: Vec<String>,
     
     // Custom Headers & Debugging
     #[serde(default)]
@@ -33,8 +188,8 @@ pub struct Profile {
     pub save_debug_json: bool,
 
     // Database Settings
-    #[serde(default = "default_db_mode")]
-    pub database_mode: String, // "off", "local", "remote"
+    #[serde(default)]
+    pub database_mode: DatabaseMode,
     #[serde(default)]
     pub remote_db_url: String,
     #[serde(default)]
@@ -43,10 +198,51 @@ pub struct Profile {
     pub remote_db_user: String,
     #[serde(default)]
     pub remote_db_pass: String,
+    // Periodic two-way sync with the remote, in minutes (0 = disabled)
+    #[serde(default)]
+    pub auto_sync_minutes: u64,
+    // Rows per request when pushing/pulling the remote database
+    #[serde(default = "default_sync_batch_size")]
+    pub sync_batch_size: usize,
+    // Keep writing per-folder .f_history files while database mode is on
+    #[serde(default = "default_true")]
+    pub write_history_file: bool,
+
+    // Keep api_key and remote DB credentials in the OS keyring; the file holds `keyring:` references
+    #[serde(default)]
+    pub use_keyring: bool,
+    // Namespaces this profile's keyring entries
+    #[serde(default = "new_keyring_id")]
+    pub keyring_id: String,
+
+    // Argon2id parameters when secrets are protected by a master passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_kdf: Option<KdfParams>,
+
+    // File this profile was loaded from, used when the processor persists credit usage
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,
+    #[serde(skip)]
+    passphrase_key: Option<SecretKey>,
+    // Passphrase ciphertext by field name, until `unlock`
+    #[serde(skip)]
+    locked_secrets: HashMap<String, String>,
+    // Stored value (ciphertext or keyring reference) of secrets that could not be read here;
+    // saved back as is until the secret is entered again
+    #[serde(skip)]
+    unreadable_secrets: HashMap<String, String>,
+}
+
+pub fn new_keyring_id() -> String {
+    hex::encode(rand::random::<[u8; 8]>())
+}
+
+fn default_true() -> bool {
+    true
 }
 
-fn default_db_mode() -> String {
-    "off".to_string()
+fn default_sync_batch_size() -> usize {
+    500
 }
 
 fn default_storage_url() -> String {
@@ -61,270 +257,546 @@ fn default_translate_url() -> String {
     "https://api.toriitranslate.com/api/upload".to_string()
 }
 
+// Empty: no catalog endpoint is documented, so the bundled list is used unless one is set
+fn default_catalog_url() -> String {
+    String::new()
+}
+
 impl Default for Profile {
     fn default() -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             api_key: None,
+            api_keys: Vec::new(),
+            key_strategy: KeyStrategy::RoundRobin,
             language: "en".to_string(),
+            interface_language: String::new(),
             model: None,
             font: Some("wildwords".to_string()),
-            theme: "dark".to_string(),
+            theme: Theme::Dark,
             total_credits_used: 0,
+            credit_balance: None,
+            credit_balance_at: None,
+            model_costs: BTreeMap::new(),
+            max_credits_per_run: None,
+            max_credits_per_day: None,
             storage_url: default_storage_url(),
             ocr_url: default_ocr_url(),
             translate_url: default_translate_url(),
-            storage_urls: String::new(),
+            catalog_url: default_catalog_url(),
+            storage_urls: Vec::new(),
             storage_headers: String::new(),
             ocr_headers: String::new(),
             save_debug_json: false,
-            database_mode: default_db_mode(),
+            database_mode: DatabaseMode::Off,
             remote_db_url: String::new(),
             remote_db_token: String::new(),
             remote_db_user: String::new(),
             remote_db_pass: String::new(),
+            auto_sync_minutes: 0,
+            sync_batch_size: default_sync_batch_size(),
+            write_history_file: true,
+            use_keyring: false,
+            keyring_id: new_keyring_id(),
+            secret_kdf: None,
+            source_path: None,
+            passphrase_key: None,
+            locked_secrets: HashMap::new(),
+            unreadable_secrets: HashMap::new(),
+        }
+    }
+}
+
+// Each step upgrades the raw JSON of a profile from version `index` to `index + 1`
+const MIGRATIONS: [fn(&mut serde_json::Value); SCHEMA_VERSION as usize] = [migrate_v0_to_v1, migrate_v1_to_v2];
+
+// v0 (unversioned): free-form mode strings and header JSON that was only checked when used
+fn migrate_v0_to_v1(value: &mut serde_json::Value) {
+    let Some(obj) = value.as_object_mut() else { return };
+
+    let mode = obj.get("database_mode").and_then(|v| v.as_str()).unwrap_or("off");
+    let mode = mode.parse::<DatabaseMode>().unwrap_or_else(|_| {
+        warn!("Unknown database_mode '{}' in profile, using off", mode);
+        DatabaseMode::Off
+    });
+    obj.insert("database_mode".into(), mode.to_string().into());
+
+    let theme = if obj.get("theme").and_then(|v| v.as_str()) == Some("light") { "light" } else { "dark" };
+    obj.insert("theme".into(), theme.into());
+
+    for field in ["storage_headers", "ocr_headers"] {
+        let text = obj.get(field).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        if let Err(e) = validate_headers(&text) {
+            warn!("Dropping invalid {} from profile: {}", field, e);
+            obj.insert(field.into(), "".into());
         }
     }
+
+    if obj.get("sync_batch_size").and_then(|v| v.as_u64()) == Some(0) {
+        obj.insert("sync_batch_size".into(), default_sync_batch_size().into());
+    }
 }
 
-use chacha20poly1305::{
-    aead::{Aead, KeyInit},
-    XChaCha20Poly1305, XNonce
-};
+// v1: storage_urls was one free-form string
+fn migrate_v1_to_v2(value: &mut serde_json::Value) {
+    let Some(obj) = value.as_object_mut() else { return };
+    if let Some(text) = obj.get("storage_urls").and_then(|v| v.as_str()) {
+        let urls = split_list(text);
+        obj.insert("storage_urls".into(), urls.into());
+    }
+}
+
+// Comma, whitespace or newline separated entries
+fn split_list(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Older clients still send the settings textarea as one string
+fn string_or_list<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        Text(String),
+        List(Vec<String>),
+    }
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::Text(text) => split_list(&text),
+        StringOrList::List(list) => list.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+    })
+}
+
+/// Runs the migration chain on a stored profile. Newer files than this build are refused.
+pub fn migrate(value: &mut serde_json::Value) -> Result<()> {
+    let version = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!("Profile schema version {} is newer than supported ({}), please update the app", version, SCHEMA_VERSION));
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        step(value);
+    }
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("schema_version".into(), SCHEMA_VERSION.into());
+    }
+    Ok(())
+}
+
+// Header editors store a JSON object of string values; empty means none
+fn validate_headers(text: &str) -> std::result::Result<(), String> {
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| format!("not valid JSON: {}", e))?;
+    let obj = value.as_object().ok_or("must be a JSON object")?;
+    for (name, value) in obj {
+        let value = value.as_str().ok_or_else(|| format!("value of '{}' must be a string", name))?;
+        reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("'{}' is not a valid header name", name))?;
+        reqwest::header::HeaderValue::from_str(value).map_err(|_| format!("value of '{}' is not a valid header value", name))?;
+    }
+    Ok(())
+}
+
+fn validate_url(url: &str, schemes: &[&str]) -> std::result::Result<(), String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("not a valid URL: {}", e))?;
+    if !schemes.contains(&parsed.scheme()) {
+        return Err(format!("scheme must be one of {}", schemes.join(", ")));
+    }
+    Ok(())
+}
+
+// Secrets that move to the OS keyring when `use_keyring` is on (the URL stays encrypted in the file)
+const KEYRING_FIELDS: [&str; 4] = ["api_key", "remote_db_token", "remote_db_user", "remote_db_pass"];
+// Pool keys are stored under `api_keys.<label>`, and always go to the keyring when it is on
+const POOL_KEY_PREFIX: &str = "api_keys.";
+
+fn is_keyring_field(name: &str) -> bool {
+    KEYRING_FIELDS.contains(&name) || name.starts_with(POOL_KEY_PREFIX)
+}
+
+// Profile field a secret name belongs to; pool keys all belong to `api_keys`
+fn locked_field(name: &str) -> &str {
+    if name.starts_with(POOL_KEY_PREFIX) { "api_keys" } else { name }
+}
+
+// Labels end up in keyring account names and reports, so keep them to a safe subset
+fn validate_key_label(label: &str) -> std::result::Result<(), String> {
+    let valid = !label.trim().is_empty()
+        && label.len() <= 64
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid label '{}' (use letters, digits, spaces, '.', '-' or '_')", label))
+    }
+}
 
 impl Profile {
-    // Helper: Retrieves a unique identifier for the machine.
-    // 1. Tries to get the Hardware Machine ID (Best).
-    // 2. Falls back to Hostname (Soft Binding).
-    // 3. Last resort is a generic string (but unlikely to reach here).
-    fn get_device_fingerprint() -> String {
-        #[cfg(target_os = "android")]
-        {
-            // Android doesn't support machine-uid crate.
-            // Using hostname as a soft-bind fallback for mobile.
-            sysinfo::System::host_name().unwrap_or_else(|| "android-device-soft-id".to_string())
-        }
-
-        #[cfg(not(target_os = "android"))]
-        {
-            machine_uid::get().unwrap_or_else(|_| {
-                sysinfo::System::host_name().unwrap_or_else(|| "generic-device".to_string())
-            })
-        }
-    }
-
-    // Dynamic prefix generation based on Machine UID.
-    // The file will not have a static prefix anymore.
-    // Generates a 16-character (8 byte) random-looking hex tag derived from the machine UID.
-    fn get_prefix() -> String {
-        let uid = Self::get_device_fingerprint();
-        // Hash the UID with a domain tag for the prefix
-        let hash = blake3::hash(format!("prefix-tag|{}", uid).as_bytes());
-        // Use the first 16 characters (8 bytes) of the hex hash as the prefix
-        let hex_tag = hex::encode(&hash.as_bytes()[0..8]).to_uppercase();
-        format!("{}::", hex_tag)
+    /// Parses settings sent by the UI or the server API, reporting every problem by field.
+    pub fn from_settings_json(value: serde_json::Value) -> std::result::Result<Self, SettingsError> {
+        let profile: Profile = serde_path_to_error::deserialize(value).map_err(|e| SettingsError::Invalid {
+            errors: vec![FieldError::new(&e.path().to_string(), e.inner().to_string())],
+        })?;
+        profile.validate().map_err(|errors| SettingsError::Invalid { errors })?;
+        Ok(profile)
     }
 
-    pub fn load(path: &Path) -> Result<Self> {
-        if !path.exists() {
-            return Ok(Self::default());
+    /// The language backend messages are shown in: the interface's, else `language`.
+    pub fn message_language(&self) -> &str {
+        match self.interface_language.trim() {
+            "" => &self.language,
+            code => code,
         }
-        let content = fs::read_to_string(path)?;
-        let mut profile: Profile = serde_json::from_str(&content)?;
-        
-        // Decrypt API key if encrypted
-        if let Some(key) = &profile.api_key {
-            let prefix = Self::get_prefix();
-            if key.starts_with(&prefix) {
-                profile.api_key = Some(Self::decrypt(key));
+    }
+
+    /// Field-level checks that serde cannot express.
+    pub fn validate(&self) -> std::result::Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let http = ["http", "https"];
+
+        for (field, url) in [("storage_url", &self.storage_url), ("ocr_url", &self.ocr_url), ("translate_url", &self.translate_url), ("catalog_url", &self.catalog_url)] {
+            if field == "catalog_url" && url.trim().is_empty() {
+                continue;
+            }
+            if let Err(e) = validate_url(url, &http) {
+                errors.push(FieldError::new(field, e));
+            }
+        }
+        for (field, text) in [("storage_headers", &self.storage_headers), ("ocr_headers", &self.ocr_headers)] {
+            if let Err(e) = validate_headers(text) {
+                errors.push(FieldError::new(field, e));
             }
         }
 
-        // Decrypt Remote DB URL
-        if !profile.remote_db_url.is_empty() {
-            let prefix = Self::get_prefix();
-            if profile.remote_db_url.starts_with(&prefix) {
-                profile.remote_db_url = Self::decrypt(&profile.remote_db_url);
+        if self.remote_db_url.trim().is_empty() {
+            if self.database_mode == DatabaseMode::Remote {
+                errors.push(FieldError::new("remote_db_url", "required when database_mode is remote"));
             }
+        } else if let Err(e) = validate_url(&self.remote_db_url, &["http", "https", "ws", "wss"]) {
+            errors.push(FieldError::new("remote_db_url", e));
         }
 
-        // Decrypt Remote DB Token
-        if !profile.remote_db_token.is_empty() {
-            let prefix = Self::get_prefix();
-            if profile.remote_db_token.starts_with(&prefix) {
-                profile.remote_db_token = Self::decrypt(&profile.remote_db_token);
+        let mut labels = std::collections::HashSet::new();
+        for (i, entry) in self.api_keys.iter().enumerate() {
+            if let Err(e) = validate_key_label(&entry.label) {
+                errors.push(FieldError::new(&format!("api_keys[{}].label", i), e));
+            } else if !labels.insert(entry.label.as_str()) {
+                errors.push(FieldError::new(&format!("api_keys[{}].label", i), format!("duplicate label '{}'", entry.label)));
+            }
+            if entry.daily_budget == Some(0) {
+                errors.push(FieldError::new(&format!("api_keys[{}].daily_budget", i), "must be at least 1 (leave empty for no limit)"));
             }
         }
 
-        // Decrypt Remote DB User
-        if !profile.remote_db_user.is_empty() {
-            let prefix = Self::get_prefix();
-            if profile.remote_db_user.starts_with(&prefix) {
-                profile.remote_db_user = Self::decrypt(&profile.remote_db_user);
+        for (field, limit) in [("max_credits_per_run", self.max_credits_per_run), ("max_credits_per_day", self.max_credits_per_day)] {
+            if limit == Some(0) {
+                errors.push(FieldError::new(field, "must be at least 1 (leave empty for no limit)"));
             }
         }
+        // Spending from earlier runs is only known from the usage table
+        if self.max_credits_per_day.is_some() && self.database_mode == DatabaseMode::Off {
+            errors.push(FieldError::new("max_credits_per_day", "needs database mode local or remote"));
+        }
+        if self.model_costs.keys().any(|m| m.trim().is_empty()) {
+            errors.push(FieldError::new("model_costs", "model names must not be empty"));
+        }
+
+        if self.sync_batch_size == 0 || self.sync_batch_size > 10_000 {
+            errors.push(FieldError::new("sync_batch_size", "must be between 1 and 10000"));
+        }
+        if self.language.trim().is_empty() {
+            errors.push(FieldError::new("language", "must not be empty"));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
 
-        // Decrypt Remote DB Pass
-        if !profile.remote_db_pass.is_empty() {
-            let prefix = Self::get_prefix();
-            if profile.remote_db_pass.starts_with(&prefix) {
-                profile.remote_db_pass = Self::decrypt(&profile.remote_db_pass);
+    // Every field that is stored encrypted, by name
+    fn secret_fields_mut(&mut self) -> Vec<(String, &mut String)> {
+        let mut fields = Vec::with_capacity(5 + self.api_keys.len());
+        if let Some(key) = self.api_key.as_mut() {
+            fields.push(("api_key".to_string(), key));
+        }
+        for entry in self.api_keys.iter_mut() {
+            fields.push((format!("{}{}", POOL_KEY_PREFIX, entry.label), &mut entry.key));
+        }
+        fields.push(("remote_db_url".to_string(), &mut self.remote_db_url));
+        fields.push(("remote_db_token".to_string(), &mut self.remote_db_token));
+        fields.push(("remote_db_user".to_string(), &mut self.remote_db_user));
+        fields.push(("remote_db_pass".to_string(), &mut self.remote_db_pass));
+        fields
+    }
+
+    // Keyring account names this profile may own
+    fn keyring_field_names(&self) -> Vec<String> {
+        KEYRING_FIELDS.iter().map(|f| f.to_string())
+            .chain(self.api_keys.iter().map(|e| format!("{}{}", POOL_KEY_PREFIX, e.label)))
+            .collect()
+    }
+
+    /// True when there is a key to translate with, either in the pool or `api_key`.
+    pub fn has_api_key(&self) -> bool {
+        self.api_keys.iter().any(|e| e.enabled && !e.key.trim().is_empty())
+            || self.api_key.as_deref().is_some_and(|k| !k.trim().is_empty())
+    }
+
+    /// Loads a profile. A secret that cannot be read here (machine-bound ciphertext from another
+    /// machine, a missing keyring entry) is left empty with a warning and kept as stored on save;
+    /// passphrase-protected secrets stay locked until `unlock` is called.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self { source_path: Some(path.to_path_buf()), ..Self::default() });
+        }
+        let content = fs::read_to_string(path)?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)?;
+        migrate(&mut value)?;
+        let mut profile: Profile = serde_json::from_value(value)?;
+        profile.source_path = Some(path.to_path_buf());
+
+        let mut locked = HashMap::new();
+        let mut unreadable = HashMap::new();
+        for (name, value) in profile.secret_fields_mut() {
+            let read = if let Some(account) = value.strip_prefix(KEYRING_PREFIX) {
+                match keystore::store() {
+                    Some(store) => store.get(account)
+                        .and_then(|v| v.ok_or_else(|| anyhow!("missing from the OS keyring (entry {})", account))),
+                    None => Err(anyhow!("kept in the OS keyring, but no keyring is available")),
+                }
+            } else {
+                match secrets::kind(value) {
+                    SecretKind::Plain => continue,
+                    SecretKind::Machine => secrets::decrypt_machine(value),
+                    SecretKind::Passphrase => {
+                        // Keep the ciphertext aside so it is never used as a credential
+                        locked.insert(name, std::mem::take(value));
+                        continue;
+                    }
+                }
+            };
+            match read {
+                Ok(plain) => *value = plain,
+                Err(e) => {
+                    warn!(
+                        "Cannot read {} in {}: {}. Re-enter it, or export the profile with a passphrase on the original machine and import it here",
+                        name, path.display(), e
+                    );
+                    unreadable.insert(name, std::mem::take(value));
+                }
             }
         }
-        
+        if locked.contains_key("api_key") || unreadable.contains_key("api_key") {
+            profile.api_key = None;
+        }
+        profile.locked_secrets = locked;
+        profile.unreadable_secrets = unreadable;
         Ok(profile)
     }
 
     pub fn save(&self, path: &Path) -> Result<()> {
         let mut to_save = self.clone();
-        
-        // Encrypt API key (with error handling)
-        if let Some(key) = &to_save.api_key {
-            if !key.is_empty() {
-                let prefix = Self::get_prefix();
-                if !key.starts_with(&prefix) {
-                    to_save.api_key = Some(Self::encrypt(key));
-                } else {
-                    let plain = Self::decrypt(key);
-                    to_save.api_key = Some(Self::encrypt(&plain));
+        to_save.schema_version = SCHEMA_VERSION;
+        let key = self.passphrase_key.clone();
+        let locked = self.locked_secrets.clone();
+        let unreadable = self.unreadable_secrets.clone();
+        let passphrase_mode = self.secret_kdf.is_some();
+        if to_save.api_key.is_none() && (locked.contains_key("api_key") || unreadable.contains_key("api_key")) {
+            to_save.api_key = Some(String::new());
+        }
+
+        // Without a usable keyring the secrets stay encrypted in the file
+        let keyring = if self.use_keyring { keystore::persistent_store() } else { None };
+        if self.use_keyring && keyring.is_none() {
+            warn!("No OS keyring available, keeping secrets encrypted in {}", path.display());
+        }
+        let keyring_id = self.keyring_id.clone();
+
+        for (name, value) in to_save.secret_fields_mut() {
+            if value.is_empty() {
+                // Still locked or unreadable: write the stored value back untouched
+                if let Some(cipher) = locked.get(&name).or_else(|| unreadable.get(&name)) {
+                    *value = cipher.clone();
+                } else if let (Some(store), true) = (&keyring, is_keyring_field(&name)) {
+                    store.delete(&keystore::account(&keyring_id, &name))?;
                 }
+                continue;
+            }
+            if let (Some(store), true) = (&keyring, is_keyring_field(&name)) {
+                let account = keystore::account(&keyring_id, &name);
+                store.set(&account, value)?;
+                *value = format!("{}{}", KEYRING_PREFIX, account);
+                continue;
             }
+            *value = match (passphrase_mode, &key) {
+                (true, Some(key)) => secrets::encrypt_with(key, value)?,
+                (true, None) => return Err(anyhow!("Profile is locked, unlock it with the master passphrase before changing {}", name)),
+                (false, _) => secrets::encrypt_machine(value)?,
+            };
         }
 
-        // Encrypt Remote DB URL
-        if !to_save.remote_db_url.is_empty() {
-            let prefix = Self::get_prefix();
-            if !to_save.remote_db_url.starts_with(&prefix) {
-                to_save.remote_db_url = Self::encrypt(&to_save.remote_db_url);
-            } else {
-                let plain = Self::decrypt(&to_save.remote_db_url);
-                to_save.remote_db_url = Self::encrypt(&plain);
+        let content = serde_json::to_string_pretty(&to_save)?;
+
+        // Atomic write: write to temp file first, then rename
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Removes this profile's entries from the OS keyring (used when the profile is deleted
+    /// or stops using the keyring; the values must already be saved elsewhere).
+    pub fn forget_keyring(&self) -> Result<()> {
+        if let Some(store) = keystore::store() {
+            for name in self.keyring_field_names() {
+                store.delete(&keystore::account(&self.keyring_id, &name))?;
             }
         }
+        Ok(())
+    }
 
-        // Encrypt Remote DB Token
-        if !to_save.remote_db_token.is_empty() {
-            let prefix = Self::get_prefix();
-            if !to_save.remote_db_token.starts_with(&prefix) {
-                to_save.remote_db_token = Self::encrypt(&to_save.remote_db_token);
-            } else {
-                let plain = Self::decrypt(&to_save.remote_db_token);
-                to_save.remote_db_token = Self::encrypt(&plain);
+    /// Removes keyring entries of pool keys that `current` no longer has (called after saving
+    /// `current` over this profile).
+    pub fn forget_dropped_keys(&self, current: &Profile) -> Result<()> {
+        if !self.use_keyring {
+            return Ok(());
+        }
+        let Some(store) = keystore::store() else { return Ok(()) };
+        for entry in &self.api_keys {
+            if !current.api_keys.iter().any(|e| e.label == entry.label) {
+                store.delete(&keystore::account(&self.keyring_id, &format!("{}{}", POOL_KEY_PREFIX, entry.label)))?;
             }
         }
+        Ok(())
+    }
 
-        // Encrypt Remote DB User
-        if !to_save.remote_db_user.is_empty() {
-            let prefix = Self::get_prefix();
-            if !to_save.remote_db_user.starts_with(&prefix) {
-                to_save.remote_db_user = Self::encrypt(&to_save.remote_db_user);
-            } else {
-                let plain = Self::decrypt(&to_save.remote_db_user);
-                to_save.remote_db_user = Self::encrypt(&plain);
-            }
+    /// True while passphrase-protected secrets have not been unlocked.
+    pub fn is_locked(&self) -> bool {
+        !self.locked_secrets.is_empty()
+    }
+
+    pub fn ensure_unlocked(&self) -> Result<()> {
+        if self.is_locked() {
+            return Err(anyhow!("Profile is locked, unlock it with the master passphrase first"));
         }
+        Ok(())
+    }
 
-        // Encrypt Remote DB Pass
-        if !to_save.remote_db_pass.is_empty() {
-            let prefix = Self::get_prefix();
-            if !to_save.remote_db_pass.starts_with(&prefix) {
-                to_save.remote_db_pass = Self::encrypt(&to_save.remote_db_pass);
-            } else {
-                let plain = Self::decrypt(&to_save.remote_db_pass);
-                to_save.remote_db_pass = Self::encrypt(&plain);
+    /// Decrypts the passphrase-protected secrets. Fails on a wrong passphrase.
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        let kdf = self.secret_kdf.clone().ok_or_else(|| anyhow!("Profile has no master passphrase"))?;
+        let key = kdf.unlock(passphrase)?;
+        self.unlock_with(key)
+    }
+
+    /// Unlocks this copy of a profile with the key `unlocked` was unlocked with, when both
+    /// were encrypted under the same passphrase. Does nothing otherwise.
+    pub fn unlock_like(&mut self, unlocked: &Profile) -> Result<()> {
+        match (&unlocked.passphrase_key, &self.secret_kdf, &unlocked.secret_kdf) {
+            (Some(key), Some(mine), Some(theirs)) if self.is_locked() && mine == theirs => self.unlock_with(key.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    fn unlock_with(&mut self, key: SecretKey) -> Result<()> {
+        let mut locked = std::mem::take(&mut self.locked_secrets);
+        for (name, value) in self.secret_fields_mut() {
+            if let Some(cipher) = locked.remove(&name) {
+                *value = secrets::decrypt_with(&key, &cipher)
+                    .map_err(|e| anyhow!("Cannot decrypt {}: {}", name, e))?;
             }
         }
+        // api_key is optional, so it may not have had a slot above
+        if let Some(cipher) = locked.remove("api_key") {
+            self.api_key = Some(secrets::decrypt_with(&key, &cipher)?);
+        }
+        self.passphrase_key = Some(key);
+        Ok(())
+    }
 
-        let content = serde_json::to_string_pretty(&to_save)?;
-        
-        // Atomic write: write to temp file first, then rename
-        let temp_path = path.with_extension("tmp");
-        fs::write(&temp_path, content)?;
-        fs::rename(&temp_path, path)?;
-        
+    /// Turns master-passphrase mode on (Some) or back to machine-bound encryption (None).
+    /// Takes effect on the next `save`.
+    pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> Result<()> {
+        self.ensure_unlocked()?;
+        match passphrase {
+            Some(p) => {
+                let (kdf, key) = KdfParams::generate(p)?;
+                self.secret_kdf = Some(kdf);
+                self.passphrase_key = Some(key);
+            }
+            None => {
+                self.secret_kdf = None;
+                self.passphrase_key = None;
+            }
+        }
         Ok(())
     }
 
-    // Derives a hardware-bound 32-byte key using Blake3 + XChaCha20Poly1305.
-    // This creates a key derived from: HardwareID -> Blake3 -> XChaCha20 -> Blake3 -> FinalKey
-    fn get_machine_key() -> chacha20poly1305::Key {
-        let uid = Self::get_device_fingerprint();
-        
-        // 1. Initial Seed from Hardware (Blake3)
-        let seed_hash = blake3::hash(uid.as_bytes());
-        let seed_key = chacha20poly1305::Key::from_slice(seed_hash.as_bytes());
-        
-        // 2. Use XChaCha20 to mix the seed into a new derived key.
-        let cipher = XChaCha20Poly1305::new(seed_key);
-        // Deterministic nonce for key derivation (Safe here because key is unique per device)
-        let nonce = XNonce::from_slice(&[0u8; 24]); 
-        
-        // Encrypt the machine UID itself to generate cryptographic derived bytes.
-        // This ensures NO static strings are used in the key generation process.
-        // The key is derived from: Encrypt(Key=Hash(UID), Plaintext=UID).
-        let derived = cipher.encrypt(nonce, uid.as_bytes()).unwrap_or_else(|_| seed_hash.as_bytes().to_vec());
-        
-        // 3. Finalize to 32 bytes
-        let final_hash = blake3::hash(&derived);
-        *chacha20poly1305::Key::from_slice(final_hash.as_bytes())
-    }
-
-    fn encrypt(text: &str) -> String {
-        // Try to encrypt, but fallback gracefully on any error
-        match Self::try_encrypt(text) {
-            Ok(encrypted) => encrypted,
-            Err(e) => {
-                eprintln!("Encryption failed: {}, storing plaintext", e);
-                text.to_string()
+    /// Forgets the locked ciphertext of `field` (`api_keys` stands for every pool key), when a
+    /// runtime override supplies the value instead.
+    pub fn drop_locked(&mut self, field: &str) {
+        self.locked_secrets.retain(|name, _| locked_field(name) != field);
+    }
+
+    /// Takes the locked ciphertext of `fields` over from `stored`, after those fields were
+    /// reset to its values, so saving writes the stored secret back instead of an empty one.
+    pub fn keep_locked(&mut self, stored: &Profile, fields: &[&str]) {
+        for (name, cipher) in &stored.locked_secrets {
+            if fields.contains(&locked_field(name)) {
+                self.locked_secrets.insert(name.clone(), cipher.clone());
+            }
+        }
+        for (name, value) in &stored.unreadable_secrets {
+            if fields.contains(&locked_field(name)) {
+                self.unreadable_secrets.insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Carries the state that never goes through serde (file location, passphrase key,
+    /// locked secrets) over from the profile being replaced, e.g. on save_settings.
+    pub fn keep_runtime_state(&mut self, previous: &Profile) {
+        self.source_path = previous.source_path.clone();
+        self.secret_kdf = previous.secret_kdf.clone();
+        self.passphrase_key = previous.passphrase_key.clone();
+        self.locked_secrets = previous.locked_secrets.clone();
+        self.unreadable_secrets = previous.unreadable_secrets.clone();
+    }
+
+    /// Serializes the profile for another machine, secrets encrypted with `passphrase`.
+    pub fn export_portable(&self, passphrase: &str) -> Result<String> {
+        self.ensure_unlocked()?;
+        if passphrase.is_empty() {
+            return Err(anyhow!("A passphrase is required to export a profile"));
+        }
+        let mut export = self.clone();
+        let (kdf, key) = KdfParams::generate(passphrase)?;
+        for (_, value) in export.secret_fields_mut() {
+            if !value.is_empty() {
+                *value = secrets::encrypt_with(&key, value)?;
             }
         }
+        export.secret_kdf = Some(kdf);
+        Ok(serde_json::to_string_pretty(&export)?)
     }
 
-    fn try_encrypt(text: &str) -> Result<String> {
-        let key = Self::get_machine_key();
-        let cipher = XChaCha20Poly1305::new(&key);
-
-        // Generate a random 24-byte nonce using time + process info
-        let time_seed = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| anyhow::anyhow!("Time error: {}", e))?
-            .as_nanos();
-            
-        let nonce_hash = blake3::hash(&time_seed.to_le_bytes());
-        let nonce_bytes = &nonce_hash.as_bytes()[0..24];
-        let nonce = XNonce::from_slice(nonce_bytes);
-
-        let ciphertext = cipher.encrypt(nonce, text.as_bytes())
-            .map_err(|e| anyhow::anyhow!("Cipher error: {}", e))?;
-        
-        let mut final_payload = Vec::with_capacity(24 + ciphertext.len());
-        final_payload.extend_from_slice(nonce_bytes);
-        final_payload.extend_from_slice(&ciphertext);
-
-        Ok(format!("{}{}", Self::get_prefix(), general_purpose::STANDARD.encode(final_payload)))
-    }
-
-    fn decrypt(text: &str) -> String {
-        let prefix = Self::get_prefix();
-        if !text.starts_with(&prefix) { return text.to_string(); }
-        
-        let encoded_data = &text[prefix.len()..];
-        
-        if let Ok(data) = general_purpose::STANDARD.decode(encoded_data) {
-            // Check length: Nonce (24) + Tag (16) = 40 bytes minimum overhead
-            if data.len() < 24 + 16 { return text.to_string(); } 
-            
-            let (nonce_bytes, ciphertext) = data.split_at(24);
-            let nonce = XNonce::from_slice(nonce_bytes);
-            
-            let key = Self::get_machine_key();
-            let cipher = XChaCha20Poly1305::new(&key);
-            
-            if let Ok(decrypted_bytes) = cipher.decrypt(nonce, ciphertext) {
-                return String::from_utf8(decrypted_bytes).unwrap_or(text.to_string());
+    /// Reads an `export_portable` file. The result holds plain secrets and no passphrase,
+    /// so the next `save` binds them to this machine.
+    pub fn import_portable(content: &str, passphrase: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(content)?;
+        migrate(&mut value)?;
+        let mut profile: Profile = serde_json::from_value(value)?;
+        let kdf = profile.secret_kdf.take().ok_or_else(|| anyhow!("Not a passphrase-protected profile export"))?;
+        // Never share keyring entries with the profile it was exported from
+        profile.keyring_id = new_keyring_id();
+        let key = kdf.unlock(passphrase)?;
+        for (name, value) in profile.secret_fields_mut() {
+            if !value.is_empty() {
+                *value = secrets::decrypt_with(&key, value)
+                    .map_err(|e| anyhow!("Cannot decrypt {}: {}", name, e))?;
             }
         }
-        
-        // Decryption failed (wrong key/machine or tampered data)
-        text.to_string()
+        Ok(profile)
     }
 }
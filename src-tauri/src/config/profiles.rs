@@ -0,0 +1,179 @@
+use crate::config::profile::{new_keyring_id, Profile};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const ACTIVE_FILE: &str = "active";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+}
+
+/// Named profiles, one `<name>.json` per profile under `<config_dir>/profiles`.
+/// The name of the active profile lives in `profiles/active`.
+pub struct ProfileStore {
+    config_dir: PathBuf,
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            config_dir: config_dir.to_path_buf(),
+            dir: config_dir.join("profiles"),
+        }
+    }
+
+    // Names end up as file names, so keep them to a safe subset
+    fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name != ACTIVE_FILE
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ');
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid profile name '{}' (use letters, digits, spaces, '-' or '_')", name))
+        }
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Creates the profiles directory on first use, moving a legacy single `profile.json`
+    /// into the `default` profile.
+    fn ensure_dir(&self) -> Result<()> {
+        if self.dir.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        let legacy = self.config_dir.join("profile.json");
+        if legacy.exists() {
+            fs::copy(&legacy, self.path_for(DEFAULT_PROFILE))?;
+        }
+        fs::write(self.dir.join(ACTIVE_FILE), DEFAULT_PROFILE)?;
+        Ok(())
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path_for(name).exists()
+    }
+
+    pub fn active_name(&self) -> Result<String> {
+        self.ensure_dir()?;
+        let name = fs::read_to_string(self.dir.join(ACTIVE_FILE)).unwrap_or_default();
+        let name = name.trim();
+        if name.is_empty() || Self::validate_name(name).is_err() {
+            return Ok(DEFAULT_PROFILE.to_string());
+        }
+        Ok(name.to_string())
+    }
+
+    pub fn list(&self) -> Result<Vec<ProfileInfo>> {
+        self.ensure_dir()?;
+        let active = self.active_name()?;
+
+        let mut names: Vec<String> = fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+            .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .collect();
+        // The active profile may not have been saved yet
+        if !names.contains(&active) {
+            names.push(active.clone());
+        }
+        names.sort();
+
+        Ok(names.into_iter().map(|name| ProfileInfo { active: name == active, name }).collect())
+    }
+
+    /// Loads a profile by name. A missing file yields the defaults.
+    pub fn load(&self, name: &str) -> Result<Profile> {
+        Self::validate_name(name)?;
+        self.ensure_dir()?;
+        Profile::load(&self.path_for(name))
+    }
+
+    pub fn load_active(&self) -> Result<(String, Profile)> {
+        let name = self.active_name()?;
+        let profile = self.load(&name)?;
+        Ok((name, profile))
+    }
+
+    pub fn save(&self, name: &str, profile: &Profile) -> Result<()> {
+        Self::validate_name(name)?;
+        self.ensure_dir()?;
+        profile.save(&self.path_for(name))
+    }
+
+    /// Saves `profile` as the active profile and points its `source_path` there.
+    pub fn save_active(&self, profile: &mut Profile) -> Result<()> {
+        let name = self.active_name()?;
+        self.save(&name, profile)?;
+        profile.source_path = Some(self.path_for(&name));
+        Ok(())
+    }
+
+    /// Creates a new profile from the defaults.
+    pub fn create(&self, name: &str) -> Result<Profile> {
+        Self::validate_name(name)?;
+        self.ensure_dir()?;
+        if self.exists(name) {
+            return Err(anyhow!("Profile '{}' already exists", name));
+        }
+        let profile = Profile::default();
+        self.save(name, &profile)?;
+        Ok(profile)
+    }
+
+    /// Copies every setting (secrets included) of `from` into a new profile `to`.
+    pub fn clone_profile(&self, from: &str, to: &str) -> Result<Profile> {
+        Self::validate_name(to)?;
+        if self.exists(to) {
+            return Err(anyhow!("Profile '{}' already exists", to));
+        }
+        if !self.exists(from) && from != self.active_name()? {
+            return Err(anyhow!("Profile '{}' not found", from));
+        }
+        let mut profile = self.load(from)?;
+        // The copy gets its own keyring entries
+        profile.keyring_id = new_keyring_id();
+        self.save(to, &profile)?;
+        Ok(profile)
+    }
+
+    /// Makes `name` the active profile and returns its settings.
+    pub fn switch(&self, name: &str) -> Result<Profile> {
+        Self::validate_name(name)?;
+        self.ensure_dir()?;
+        if !self.exists(name) {
+            return Err(anyhow!("Profile '{}' not found", name));
+        }
+        let profile = self.load(name)?;
+        fs::write(self.dir.join(ACTIVE_FILE), name)?;
+        Ok(profile)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        Self::validate_name(name)?;
+        if name == self.active_name()? {
+            return Err(anyhow!("Cannot delete the active profile, switch to another one first"));
+        }
+        if !self.exists(name) {
+            return Err(anyhow!("Profile '{}' not found", name));
+        }
+        // Best effort: a profile that no longer loads still gets deleted
+        if let Ok(profile) = self.load(name) {
+            profile.forget_keyring()?;
+        }
+        fs::remove_file(self.path_for(name))?;
+        Ok(())
+    }
+}
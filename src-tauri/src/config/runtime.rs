@@ -0,0 +1,374 @@
+use crate::config::keystore;
+use crate::config::profile::{DatabaseMode, KeyStrategy, Profile};
+use crate::utils::logger::{LogConfig, LogFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+const ENV_PREFIX: &str = "TAPI_";
+
+/// One layer of headless configuration. Every field is optional so layers can be stacked:
+/// built-in defaults, then the `--config` file, then `TAPI_*` variables, then CLI flags.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub api_key: Option<String>,
+    /// How keys of the profile's key pool are picked
+    pub key_strategy: Option<KeyStrategy>,
+    /// Credits per image by model, merged into the profile's pricing table (file only)
+    pub model_costs: Option<BTreeMap<String, u64>>,
+    pub max_credits_per_run: Option<u64>,
+    pub max_credits_per_day: Option<u64>,
+    pub storage_url: Option<String>,
+    pub ocr_url: Option<String>,
+    pub translate_url: Option<String>,
+    pub catalog_url: Option<String>,
+    pub database_mode: Option<DatabaseMode>,
+    pub remote_db_url: Option<String>,
+    pub remote_db_token: Option<String>,
+    pub remote_db_user: Option<String>,
+    pub remote_db_pass: Option<String>,
+    /// Directory for the database and other runtime data
+    pub data_dir: Option<PathBuf>,
+    /// Directory holding `profiles/`
+    pub profile_dir: Option<PathBuf>,
+    /// Database location, defaults to `<data_dir>/tapi.db`
+    pub db_path: Option<PathBuf>,
+    /// Named profile to use instead of the active one
+    pub profile: Option<String>,
+    /// Master passphrase for profiles that use one (env or file only, never a flag)
+    pub passphrase: Option<String>,
+    /// Tracing filter, e.g. `debug` or `info,tapi_lib::core::api=debug` (`RUST_LOG` wins)
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    /// `daily`, `never` or a size such as `10mb`
+    pub log_rotation: Option<String>,
+    /// Log file directory, defaults to `<data_dir>/logs`
+    pub log_dir: Option<PathBuf>,
+}
+
+impl ConfigLayer {
+    /// Reads a TOML config file (or JSON when the extension is `.json`).
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config {}: {}", path.display(), e))?;
+        let is_json = path.extension().map(|e| e.eq_ignore_ascii_case("json")).unwrap_or(false);
+        let layer = if is_json {
+            serde_json::from_str(&content).map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?
+        } else {
+            toml::from_str(&content).map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?
+        };
+        Ok(layer)
+    }
+
+    /// Reads `TAPI_API_KEY`, `TAPI_DATA_DIR`, ... (field name upper-cased). Empty values are ignored.
+    pub fn from_env() -> Result<Self> {
+        let var = |key: &str| {
+            std::env::var(format!("{}{}", ENV_PREFIX, key.to_uppercase()))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+        let database_mode = match var("database_mode") {
+            Some(v) => Some(v.parse().map_err(|e| anyhow!("{}DATABASE_MODE: {}", ENV_PREFIX, e))?),
+            None => None,
+        };
+        let key_strategy = match var("key_strategy") {
+            Some(v) => Some(v.parse().map_err(|e| anyhow!("{}KEY_STRATEGY: {}", ENV_PREFIX, e))?),
+            None => None,
+        };
+        let log_format = match var("log_format") {
+            Some(v) => Some(v.parse().map_err(|e| anyhow!("{}LOG_FORMAT: {}", ENV_PREFIX, e))?),
+            None => None,
+        };
+        let number = |key: &str| -> Result<Option<u64>> {
+            match var(key) {
+                Some(v) => v.trim().parse().map(Some)
+                    .map_err(|_| anyhow!("{}{}: '{}' is not a number", ENV_PREFIX, key.to_uppercase(), v)),
+                None => Ok(None),
+            }
+        };
+        Ok(Self {
+            api_key: var("api_key"),
+            key_strategy,
+            model_costs: None,
+            max_credits_per_run: number("max_credits_per_run")?,
+            max_credits_per_day: number("max_credits_per_day")?,
+            storage_url: var("storage_url"),
+            ocr_url: var("ocr_url"),
+            translate_url: var("translate_url"),
+            catalog_url: var("catalog_url"),
+            database_mode,
+            remote_db_url: var("remote_db_url"),
+            remote_db_token: var("remote_db_token"),
+            remote_db_user: var("remote_db_user"),
+            remote_db_pass: var("remote_db_pass"),
+            data_dir: var("data_dir").map(PathBuf::from),
+            profile_dir: var("profile_dir").map(PathBuf::from),
+            db_path: var("db_path").map(PathBuf::from),
+            profile: var("profile"),
+            passphrase: var("passphrase"),
+            log_level: var("log_level"),
+            log_format,
+            log_rotation: var("log_rotation"),
+            log_dir: var("log_dir").map(PathBuf::from),
+        })
+    }
+
+    /// Logging settings of this layer; files go to `default_dir` unless `log_dir` is set.
+    pub fn log_config(&self, default_dir: PathBuf) -> Result<LogConfig> {
+        Ok(LogConfig {
+            level: self.log_level.clone().unwrap_or_else(|| "info".to_string()),
+            format: self.log_format.unwrap_or_default(),
+            rotation: match self.log_rotation {
+                Some(ref v) => v.parse().map_err(|e| anyhow!("log_rotation: {}", e))?,
+                None => Default::default(),
+            },
+            dir: Some(self.log_dir.clone().unwrap_or(default_dir)),
+        })
+    }
+
+    /// Values set in `upper` win over the ones in `self`.
+    pub fn merge(self, upper: ConfigLayer) -> Self {
+        Self {
+            api_key: upper.api_key.or(self.api_key),
+            key_strategy: upper.key_strategy.or(self.key_strategy),
+            model_costs: match (self.model_costs, upper.model_costs) {
+                (Some(mut lower), Some(upper)) => {
+                    lower.extend(upper);
+                    Some(lower)
+                }
+                (lower, upper) => upper.or(lower),
+            },
+            max_credits_per_run: upper.max_credits_per_run.or(self.max_credits_per_run),
+            max_credits_per_day: upper.max_credits_per_day.or(self.max_credits_per_day),
+            storage_url: upper.storage_url.or(self.storage_url),
+            ocr_url: upper.ocr_url.or(self.ocr_url),
+            translate_url: upper.translate_url.or(self.translate_url),
+            catalog_url: upper.catalog_url.or(self.catalog_url),
+            database_mode: upper.database_mode.or(self.database_mode),
+            remote_db_url: upper.remote_db_url.or(self.remote_db_url),
+            remote_db_token: upper.remote_db_token.or(self.remote_db_token),
+            remote_db_user: upper.remote_db_user.or(self.remote_db_user),
+            remote_db_pass: upper.remote_db_pass.or(self.remote_db_pass),
+            data_dir: upper.data_dir.or(self.data_dir),
+            profile_dir: upper.profile_dir.or(self.profile_dir),
+            db_path: upper.db_path.or(self.db_path),
+            profile: upper.profile.or(self.profile),
+            passphrase: upper.passphrase.or(self.passphrase),
+            log_level: upper.log_level.or(self.log_level),
+            log_format: upper.log_format.or(self.log_format),
+            log_rotation: upper.log_rotation.or(self.log_rotation),
+            log_dir: upper.log_dir.or(self.log_dir),
+        }
+    }
+}
+
+/// Effective configuration for CLI and server mode. Profile settings are the base;
+/// anything set in `overrides` replaces them for this process only.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    pub overrides: ConfigLayer,
+    pub data_dir: PathBuf,
+    pub profile_dir: PathBuf,
+    pub db_path: PathBuf,
+    pub profile: Option<String>,
+    pub log: LogConfig,
+}
+
+impl RuntimeConfig {
+    /// Stacks the config file (if any), the environment and the CLI flags.
+    pub fn resolve(config_file: Option<&Path>, cli: ConfigLayer) -> Result<Self> {
+        let mut layer = ConfigLayer::default();
+        if let Some(path) = config_file {
+            layer = layer.merge(ConfigLayer::from_file(path)?);
+        }
+        let layer = layer.merge(ConfigLayer::from_env()?).merge(cli);
+
+        // Same locations as before: everything in the working directory unless told otherwise
+        let data_dir = layer.data_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let profile_dir = layer.profile_dir.clone().unwrap_or_else(|| data_dir.clone());
+        let db_path = layer.db_path.clone().unwrap_or_else(|| data_dir.join("tapi.db"));
+        let log = layer.log_config(data_dir.join("logs"))?;
+
+        Ok(Self {
+            profile: layer.profile.clone(),
+            overrides: layer,
+            data_dir,
+            profile_dir,
+            db_path,
+            log,
+        })
+    }
+
+    /// Unlocks a passphrase-protected profile when a passphrase was configured.
+    pub fn unlock(&self, profile: &mut Profile) -> Result<()> {
+        if let (true, Some(passphrase)) = (profile.is_locked(), &self.overrides.passphrase) {
+            profile.unlock(passphrase)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the overridden values into `profile`.
+    pub fn apply(&self, profile: &mut Profile) {
+        let o = &self.overrides;
+        if let Some(ref v) = o.api_key {
+            // An explicit key wins over the profile's key pool
+            profile.api_key = Some(v.clone());
+            profile.api_keys.clear();
+            profile.drop_locked("api_key");
+            profile.drop_locked("api_keys");
+        }
+        if let Some(v) = o.key_strategy { profile.key_strategy = v; }
+        if let Some(ref v) = o.model_costs { profile.model_costs.extend(v.clone()); }
+        if let Some(v) = o.max_credits_per_run { profile.max_credits_per_run = Some(v); }
+        if let Some(v) = o.max_credits_per_day { profile.max_credits_per_day = Some(v); }
+        if let Some(ref v) = o.storage_url { profile.storage_url = v.clone(); }
+        if let Some(ref v) = o.ocr_url { profile.ocr_url = v.clone(); }
+        if let Some(ref v) = o.translate_url { profile.translate_url = v.clone(); }
+        if let Some(ref v) = o.catalog_url { profile.catalog_url = v.clone(); }
+        if let Some(v) = o.database_mode { profile.database_mode = v; }
+        if let Some(ref v) = o.remote_db_url { profile.remote_db_url = v.clone(); profile.drop_locked("remote_db_url"); }
+        if let Some(ref v) = o.remote_db_token { profile.remote_db_token = v.clone(); profile.drop_locked("remote_db_token"); }
+        if let Some(ref v) = o.remote_db_user { profile.remote_db_user = v.clone(); profile.drop_locked("remote_db_user"); }
+        if let Some(ref v) = o.remote_db_pass { profile.remote_db_pass = v.clone(); profile.drop_locked("remote_db_pass"); }
+    }
+
+    /// Undoes `apply` on a profile that is about to be saved over `stored`: every overridden
+    /// field still at its overridden value gets the stored value back, so env, flag and config
+    /// file values never end up in the profile file. Fields the user changed are kept.
+    pub fn restore(&self, edited: &mut Profile, stored: &Profile) {
+        let mut effective = stored.clone();
+        self.apply(&mut effective);
+        let mut restored = Vec::new();
+
+        macro_rules! keep_stored {
+            ($($field:ident),*) => {$(
+                if edited.$field != stored.$field && edited.$field == effective.$field {
+                    edited.$field = stored.$field.clone();
+                    restored.push(stringify!($field));
+                }
+            )*};
+        }
+        keep_stored!(key_strategy, max_credits_per_run, max_credits_per_day, storage_url, ocr_url,
+            translate_url, catalog_url, database_mode, remote_db_url, remote_db_token, remote_db_user, remote_db_pass);
+
+        // The key override also emptied the pool, so both come back together
+        if self.overrides.api_key.is_some() && edited.api_key == effective.api_key && edited.api_keys.is_empty() {
+            edited.api_key = stored.api_key.clone();
+            edited.api_keys = stored.api_keys.clone();
+            restored.extend(["api_key", "api_keys"]);
+        }
+        if let Some(ref costs) = self.overrides.model_costs {
+            for (model, cost) in costs {
+                if edited.model_costs.get(model) == Some(cost) {
+                    match stored.model_costs.get(model) {
+                        Some(c) => edited.model_costs.insert(model.clone(), *c),
+                        None => edited.model_costs.remove(model),
+                    };
+                }
+            }
+        }
+        edited.keep_locked(stored, &restored);
+    }
+
+    /// Effective values after `apply`, with secrets masked. Used by `--print-config`.
+    pub fn describe(&self, profile: &Profile, profile_name: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("profile", profile_name.to_string()),
+            ("profile_dir", self.profile_dir.display().to_string()),
+            ("data_dir", self.data_dir.display().to_string()),
+            ("db_path", self.db_path.display().to_string()),
+            ("log_level", self.log.level.clone()),
+            ("log_format", self.log.format.to_string()),
+            ("log_rotation", self.log.rotation.to_string()),
+            ("log_dir", self.log.dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default()),
+            ("secret_storage", match (profile.secret_kdf.is_some(), profile.is_locked()) {
+                _ if profile.use_keyring && keystore::persistent_store().is_some() => "keyring".to_string(),
+                (true, true) => "passphrase (locked)".to_string(),
+                (true, false) => "passphrase".to_string(),
+                (false, _) => "machine".to_string(),
+            }),
+            ("api_key", mask_secret(profile.api_key.as_deref().unwrap_or(""))),
+            ("api_keys", profile.api_keys.iter()
+                .map(|e| format!("{}={}{}", e.label, mask_secret(&e.key), if e.enabled { "" } else { " (disabled)" }))
+                .collect::<Vec<_>>()
+                .join(", ")),
+            ("key_strategy", profile.key_strategy.to_string()),
+            ("model_costs", profile.model_costs.iter().map(|(m, c)| format!("{}={}", m, c)).collect::<Vec<_>>().join(", ")),
+            ("max_credits_per_run", profile.max_credits_per_run.map(|v| v.to_string()).unwrap_or_default()),
+            ("max_credits_per_day", profile.max_credits_per_day.map(|v| v.to_string()).unwrap_or_default()),
+            ("storage_url", profile.storage_url.clone()),
+            ("ocr_url", profile.ocr_url.clone()),
+            ("translate_url", profile.translate_url.clone()),
+            ("catalog_url", profile.catalog_url.clone()),
+            ("database_mode", profile.database_mode.to_string()),
+            ("remote_db_url", mask_url(&profile.remote_db_url)),
+            ("remote_db_token", mask_secret(&profile.remote_db_token)),
+            ("remote_db_user", profile.remote_db_user.clone()),
+            ("remote_db_pass", mask_secret(&profile.remote_db_pass)),
+        ]
+    }
+}
+
+// Keeps the last 4 characters of long secrets so keys can still be told apart
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        n if n <= 8 => "****".to_string(),
+        n => format!("****{}", chars[n - 4..].iter().collect::<String>()),
+    }
+}
+
+// Hides `user:pass@` credentials embedded in a URL
+fn mask_url(url: &str) -> String {
+    match (url.find("://"), url.rfind('@')) {
+        (Some(scheme_end), Some(at)) if at > scheme_end => {
+            format!("{}****{}", &url[..scheme_end + 3], &url[at..])
+        }
+        _ => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(overrides: ConfigLayer) -> RuntimeConfig {
+        RuntimeConfig {
+            overrides,
+            data_dir: PathBuf::from("."),
+            profile_dir: PathBuf::from("."),
+            db_path: PathBuf::from("tapi.db"),
+            profile: None,
+            log: LogConfig::default(),
+        }
+    }
+
+    #[test]
+    fn restore_keeps_overrides_out_of_the_saved_profile() {
+        let config = config(ConfigLayer {
+            api_key: Some("env-key".into()),
+            storage_url: Some("https://override.example".into()),
+            max_credits_per_day: Some(50),
+            ..Default::default()
+        });
+        let mut stored = Profile::default();
+        stored.api_key = Some("stored-key".into());
+        stored.storage_url = "https://stored.example".into();
+
+        let mut edited = stored.clone();
+        config.apply(&mut edited);
+        // The user only changed the OCR endpoint and the daily cap
+        edited.ocr_url = "https://ocr.example".into();
+        edited.max_credits_per_day = Some(80);
+        config.restore(&mut edited, &stored);
+
+        assert_eq!(edited.api_key.as_deref(), Some("stored-key"));
+        assert_eq!(edited.storage_url, "https://stored.example");
+        assert_eq!(edited.ocr_url, "https://ocr.example");
+        assert_eq!(edited.max_credits_per_day, Some(80));
+    }
+}
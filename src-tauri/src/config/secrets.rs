@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+    XChaCha20Poly1305, XNonce
+};
+use serde::{Deserialize, Serialize};
+
+/// Marks values encrypted with the master passphrase (format version 1).
+pub const PASSPHRASE_PREFIX: &str = "tapi-pp1:";
+// Known plaintext encrypted into `KdfParams::check` so a wrong passphrase is reported as such
+const CHECK_PLAINTEXT: &str = "tapi-passphrase-check";
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+// Argon2id defaults (OWASP: 19 MiB, 2 passes, 1 lane)
+const ARGON2_M_COST: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
+/// A derived 32-byte key. Never printed.
+#[derive(Clone)]
+pub struct SecretKey([u8; 32]);
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// Argon2id parameters stored next to passphrase-encrypted secrets.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KdfParams {
+    /// Base64 salt
+    pub salt: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    /// `CHECK_PLAINTEXT` encrypted with the derived key
+    pub check: String,
+}
+
+impl KdfParams {
+    /// Fresh salt and default cost, returning the params and the key they derive.
+    pub fn generate(passphrase: &str) -> Result<(Self, SecretKey)> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("The master passphrase cannot be empty"));
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        let mut params = Self {
+            salt: general_purpose::STANDARD.encode(salt),
+            m_cost: ARGON2_M_COST,
+            t_cost: ARGON2_T_COST,
+            p_cost: ARGON2_P_COST,
+            check: String::new(),
+        };
+        let key = params.derive(passphrase)?;
+        params.check = encrypt_with(&key, CHECK_PLAINTEXT)?;
+        Ok((params, key))
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<SecretKey> {
+        let salt = general_purpose::STANDARD.decode(&self.salt)
+            .map_err(|e| anyhow!("Corrupt passphrase salt: {}", e))?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        Ok(SecretKey(key))
+    }
+
+    /// Derives the key and checks it against the stored verifier.
+    pub fn unlock(&self, passphrase: &str) -> Result<SecretKey> {
+        let key = self.derive(passphrase)?;
+        match decrypt_with(&key, &self.check) {
+            Ok(text) if text == CHECK_PLAINTEXT => Ok(key),
+            _ => Err(anyhow!("Wrong master passphrase")),
+        }
+    }
+}
+
+/// How a stored value is protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    Plain,
+    Machine,
+    Passphrase,
+}
+
+pub fn kind(value: &str) -> SecretKind {
+    if value.starts_with(PASSPHRASE_PREFIX) {
+        SecretKind::Passphrase
+    } else if looks_machine_encrypted(value) {
+        SecretKind::Machine
+    } else {
+        SecretKind::Plain
+    }
+}
+
+// Helper: Retrieves a unique identifier for the machine.
+// 1. Tries to get the Hardware Machine ID (Best).
+// 2. Falls back to Hostname (Soft Binding).
+// 3. Last resort is a generic string (but unlikely to reach here).
+fn device_fingerprint() -> String {
+    #[cfg(target_os = "android")]
+    {
+        // Android doesn't support machine-uid crate.
+        // Using hostname as a soft-bind fallback for mobile.
+        sysinfo::System::host_name().unwrap_or_else(|| "android-device-soft-id".to_string())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        machine_uid::get().unwrap_or_else(|_| {
+            sysinfo::System::host_name().unwrap_or_else(|| "generic-device".to_string())
+        })
+    }
+}
+
+// Dynamic prefix generation based on Machine UID.
+// Generates a 16-character (8 byte) random-looking hex tag derived from the machine UID.
+fn machine_prefix() -> String {
+    let uid = device_fingerprint();
+    // Hash the UID with a domain tag for the prefix
+    let hash = blake3::hash(format!("prefix-tag|{}", uid).as_bytes());
+    let hex_tag = hex::encode(&hash.as_bytes()[0..8]).to_uppercase();
+    format!("{}::", hex_tag)
+}
+
+// Derives a hardware-bound 32-byte key using Blake3 + XChaCha20Poly1305.
+// This creates a key derived from: HardwareID -> Blake3 -> XChaCha20 -> Blake3 -> FinalKey
+fn machine_key() -> SecretKey {
+    let uid = device_fingerprint();
+
+    // 1. Initial Seed from Hardware (Blake3)
+    let seed_hash = blake3::hash(uid.as_bytes());
+    let seed_key = chacha20poly1305::Key::from_slice(seed_hash.as_bytes());
+
+    // 2. Use XChaCha20 to mix the seed into a new derived key.
+    let cipher = XChaCha20Poly1305::new(seed_key);
+    // Deterministic nonce for key derivation only (the key is unique per device); data nonces are random
+    let nonce = XNonce::from_slice(&[0u8; 24]);
+    let derived = cipher.encrypt(nonce, uid.as_bytes()).unwrap_or_else(|_| seed_hash.as_bytes().to_vec());
+
+    // 3. Finalize to 32 bytes
+    SecretKey(*blake3::hash(&derived).as_bytes())
+}
+
+fn seal(key: &SecretKey, text: &str) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key.0));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, text.as_bytes())
+        .map_err(|e| anyhow!("Cipher error: {}", e))?;
+
+    let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+fn open(key: &SecretKey, encoded: &str) -> Result<String> {
+    let data = general_purpose::STANDARD.decode(encoded)
+        .map_err(|_| anyhow!("not valid base64"))?;
+    if data.len() < NONCE_LEN + TAG_LEN {
+        return Err(anyhow!("ciphertext too short"));
+    }
+
+    let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key.0));
+    let plain = cipher.decrypt(XNonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| anyhow!("authentication failed"))?;
+    String::from_utf8(plain).map_err(|_| anyhow!("not valid UTF-8"))
+}
+
+/// Encrypts with the key bound to this machine.
+pub fn encrypt_machine(text: &str) -> Result<String> {
+    Ok(format!("{}{}", machine_prefix(), seal(&machine_key(), text)?))
+}
+
+/// Decrypts a machine-bound value. Values from another machine carry a different prefix,
+/// so they are reported instead of being passed through as if they were plaintext.
+pub fn decrypt_machine(text: &str) -> Result<String> {
+    let prefix = machine_prefix();
+    let encoded = text.strip_prefix(&prefix)
+        .ok_or_else(|| anyhow!("encrypted on another machine"))?;
+    open(&machine_key(), encoded)
+        .map_err(|e| anyhow!("cannot decrypt with this machine's key ({})", e))
+}
+
+pub fn encrypt_with(key: &SecretKey, text: &str) -> Result<String> {
+    Ok(format!("{}{}", PASSPHRASE_PREFIX, seal(key, text)?))
+}
+
+pub fn decrypt_with(key: &SecretKey, text: &str) -> Result<String> {
+    let encoded = text.strip_prefix(PASSPHRASE_PREFIX)
+        .ok_or_else(|| anyhow!("not encrypted with the master passphrase"))?;
+    open(key, encoded)
+}
+
+// Machine-bound values look like `<16 hex>::<base64>`, whichever machine wrote them
+fn looks_machine_encrypted(value: &str) -> bool {
+    match value.split_once("::") {
+        Some((tag, rest)) => tag.len() == 16 && tag.chars().all(|c| c.is_ascii_hexdigit()) && !rest.is_empty(),
+        None => false,
+    }
+}
@@ -2,10 +2,16 @@ use reqwest::{Client, multipart};
 use std::path::Path;
 use anyhow::Result;
 use tokio::fs::{File};
-use tokio::io::AsyncWriteExt;
 use tokio_util::codec::{BytesCodec, FramedRead};
-use crate::utils::logger::log_debug;
-use reqwest::header::{HeaderName, HeaderValue};
+use tracing::{debug, info_span, Instrument};
+use crate::utils::debug::Exchange;
+use crate::core::catalog::Catalog;
+use crate::core::storage::StorageBatch;
+use crate::core::ocr::OcrPage;
+use crate::core::script::Bubble;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
 #[derive(Clone)]
@@ -15,7 +21,7 @@ pub struct ApiEndpoints {
     pub ocr: String,
     pub ocr_headers: Option<String>,
     pub translate: String,
-    pub save_debug_json: bool,
+    pub catalog: String,
 }
 
 impl Default for ApiEndpoints {
@@ -26,11 +32,106 @@ impl Default for ApiEndpoints {
             ocr: "https://api.toriitranslate.com/api/ocr".to_string(),
             ocr_headers: None,
             translate: "https://api.toriitranslate.com/api/upload".to_string(),
-            save_debug_json: false,
+            // The provider documents no catalog endpoint; one has to be configured
+            catalog: String::new(),
         }
     }
 }
 
+/// The key itself was refused (invalid, out of credits or over quota). Another key may
+/// still work, so the processor fails over instead of giving up on the page.
+#[derive(Debug, thiserror::Error)]
+#[error("API key rejected ({status}): {message}")]
+pub struct KeyRejected {
+    pub status: u16,
+    pub message: String,
+}
+
+// Error codes the provider sends for a key that is unusable, whatever the status
+const KEY_ERROR_CODES: [&str; 4] = ["invalid_api_key", "insufficient_credits", "quota_exceeded", "key_disabled"];
+// A 429 is waited out this many times before it counts as a failed request
+const RATE_LIMIT_RETRIES: u32 = 4;
+const RATE_LIMIT_BASE_SECS: u64 = 2;
+const RATE_LIMIT_MAX_SECS: u64 = 60;
+
+// 401/402/403, or one of KEY_ERROR_CODES as `code` / `error.code` of a JSON body. Other
+// statuses are never judged by their wording, and rate limits (429) are retried instead.
+fn is_key_rejection(status: StatusCode, body: &str) -> bool {
+    if matches!(status.as_u16(), 401..=403) {
+        return true;
+    }
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(body) else { return false };
+    json.get("code").or_else(|| json.pointer("/error/code"))
+        .and_then(|c| c.as_str())
+        .is_some_and(|c| KEY_ERROR_CODES.contains(&c))
+}
+
+// Wait before resending a rate-limited request: the provider's Retry-After seconds when
+// given, otherwise an exponential backoff
+fn rate_limit_delay(headers: &HeaderMap, attempt: u32) -> std::time::Duration {
+    let secs = headers.get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(RATE_LIMIT_BASE_SECS << attempt.min(5));
+    std::time::Duration::from_secs(secs.min(RATE_LIMIT_MAX_SECS))
+}
+
+// Names the provider has used for the charge and the balance, in headers and JSON bodies
+const CHARGED_FIELDS: [&str; 4] = ["credits_used", "x-credits-used", "credits_charged", "x-credits-charged"];
+const REMAINING_FIELDS: [&str; 5] = ["credits_remaining", "x-credits-remaining", "remaining_credits", "credits_left", "balance"];
+
+/// Credits as reported by the provider. Either value is None when it was not sent.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CreditInfo {
+    pub charged: Option<u64>,
+    pub remaining: Option<u64>,
+}
+
+impl CreditInfo {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let find = |names: &[&str]| names.iter()
+            .filter_map(|n| headers.get(*n))
+            .find_map(|v| v.to_str().ok()?.trim().parse::<f64>().ok())
+            .map(|v| v.max(0.0).round() as u64);
+        Self { charged: find(&CHARGED_FIELDS), remaining: find(&REMAINING_FIELDS) }
+    }
+
+    /// Looks at the top level of a JSON response (e.g. the storage endpoint's).
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        let find = |names: &[&str]| names.iter()
+            .filter_map(|n| value.get(*n))
+            .find_map(|v| v.as_f64().or_else(|| v.as_str()?.trim().parse().ok()))
+            .map(|v| v.max(0.0).round() as u64);
+        Self { charged: find(&CHARGED_FIELDS), remaining: find(&REMAINING_FIELDS) }
+    }
+}
+
+/// A translated page and what the provider said it cost.
+pub struct Translated {
+    pub image: Vec<u8>,
+    pub credits: CreditInfo,
+}
+
+/// An OCR result and what the provider said it cost.
+pub struct Recognized {
+    pub page: OcrPage,
+    pub credits: CreditInfo,
+}
+
+// A response read in full
+struct Reply {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+#[derive(Clone)]
 pub struct ApiClient {
     client: Client,
     api_key: String,
@@ -55,23 +156,81 @@ impl ApiClient {
         }
     }
 
-    pub async fn translate_file(&self, file_path: &Path, model: &str, target_lang: &str, font: &str, text_align: &str, stroke_disabled: bool, inpaint_only: bool, min_font_size: u32) -> Result<Vec<u8>> {
+    /// Same client and endpoints, different key (cheap: the HTTP client is shared).
+    pub fn with_api_key(&self, api_key: &str) -> Self {
+        Self {
+            client: self.client.clone(),
+            api_key: api_key.trim().to_string(),
+            endpoints: self.endpoints.clone(),
+        }
+    }
+
+    /// Sends the request and reads the whole response; when the run has a debug bundle the
+    /// exchange (redacted request, headers, body, timing) is recorded there as `kind`.
+    async fn send(&self, kind: &str, request_builder: reqwest::RequestBuilder) -> Result<Reply> {
+        let request = request_builder.build()?;
+        let exchange = Exchange::start(kind, &request);
+        let span = info_span!("api_request", kind, method = %request.method(), url = %request.url());
+        let started = std::time::Instant::now();
+        let result = async {
+            let response = self.client.execute(request).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?.to_vec();
+            debug!(status = status.as_u16(), bytes = body.len(), elapsed_ms = started.elapsed().as_millis() as u64, "response");
+            Ok::<_, reqwest::Error>(Reply { status, headers, body })
+        }.instrument(span).await;
+
+        match (exchange, &result) {
+            (Some(exchange), Ok(reply)) => exchange.finish(reply.status.as_u16(), &reply.headers, &reply.body),
+            (Some(exchange), Err(e)) => exchange.fail(&e.to_string()),
+            _ => {}
+        }
+        Ok(result?)
+    }
+
+    // Sends the request `build` makes, building and sending it again while the provider
+    // answers 429 (up to RATE_LIMIT_RETRIES times)
+    async fn send_limited<F, Fut>(&self, kind: &str, build: F) -> Result<Reply>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<reqwest::RequestBuilder>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let reply = self.send(kind, build().await?).await?;
+            if reply.status != StatusCode::TOO_MANY_REQUESTS || attempt >= RATE_LIMIT_RETRIES {
+                return Ok(reply);
+            }
+            let delay = rate_limit_delay(&reply.headers, attempt);
+            debug!("API: Rate limited, retrying {} in {:?}", kind, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// `context` (e.g. a series glossary) is sent as an extra form part, for models that take it.
+    pub async fn translate_file(&self, file_path: &Path, model: &str, target_lang: &str, font: &str, text_align: &str, stroke_disabled: bool, inpaint_only: bool, min_font_size: u32, context: Option<&str>) -> Result<Translated> {
         let mut retries = 0;
         let max_retries = 3;
+        let mut rate_limited = 0;
         
         loop {
-            log_debug(&format!("API: Opening file {:?}", file_path));
+            debug!("API: Opening file {:?}", file_path);
             let file = File::open(file_path).await?;
             let stream = FramedRead::new(file, BytesCodec::new());
             let file_body = reqwest::Body::wrap_stream(stream);
             
             let filename = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
-            log_debug(&format!("API: Preparing multipart for {}", filename));
+            debug!("API: Preparing multipart for {}", filename);
 
-            let form = multipart::Form::new()
+            let mut form = multipart::Form::new()
                 .part("file", multipart::Part::stream(file_body).file_name(filename));
+            if let Some(context) = context {
+                form = form.text("context", context.to_string());
+            }
 
-            log_debug("API: Constructing request and headers...");
+            debug!("API: Constructing request and headers...");
             
             let safe_header = |v: &str| -> String {
                 v.chars().filter(|c| !c.is_control() && (*c as u32) < 127).collect()
@@ -80,7 +239,7 @@ impl ApiClient {
             let mut request_builder = self.client.post(&self.endpoints.translate)
                 .multipart(form);
 
-            log_debug("API: Adding headers...");
+            debug!("API: Adding headers...");
             request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
             request_builder = request_builder.header("target_lang", safe_header(target_lang));
             request_builder = request_builder.header("translator", safe_header(model));
@@ -90,29 +249,43 @@ impl ApiClient {
             request_builder = request_builder.header("inpaint_only", inpaint_only.to_string());
             request_builder = request_builder.header("min_font_size", min_font_size.to_string());
 
-            log_debug("API: Sending request...");
-            let result = request_builder.send().await;
+            debug!("API: Sending request...");
+            let result = self.send("translate", request_builder).await;
 
             match result {
-                Ok(response) => {
-                    let status = response.status();
-                    log_debug(&format!("API: Response status: {}", status));
+                Ok(reply) => {
+                    let status = reply.status;
+                    debug!("API: Response status: {}", status);
+
+                    // Rate limits are waited out without using up the normal retries
+                    if status == StatusCode::TOO_MANY_REQUESTS && rate_limited < RATE_LIMIT_RETRIES {
+                        let delay = rate_limit_delay(&reply.headers, rate_limited);
+                        debug!("API: Rate limited, retrying in {:?}", delay);
+                        rate_limited += 1;
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
                     
                     // Check success header
-                    let success_header = response.headers()
+                    let success_header = reply.headers
                         .get("success")
                         .and_then(|v| v.to_str().ok())
                         .unwrap_or("false");
                     
-                    log_debug(&format!("API: Success header: {}", success_header));
+                    debug!("API: Success header: {}", success_header);
                     
                     if success_header == "true" && status.is_success() {
-                        let bytes = response.bytes().await?;
-                        log_debug(&format!("API: Success! Bytes received: {}", bytes.len()));
-                        return Ok(bytes.to_vec());
+                        let credits = CreditInfo::from_headers(&reply.headers);
+                        debug!("API: Success! Bytes received: {}, credits: {:?}", reply.body.len(), credits);
+                        return Ok(Translated { image: reply.body, credits });
                     } else {
-                        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                        log_debug(&format!("API: Error response: {}", error_text));
+                        let error_text = reply.text();
+                        debug!("API: Error response: {}", error_text);
+
+                        // Retrying with the same key will not help
+                        if is_key_rejection(status, &error_text) {
+                            return Err(KeyRejected { status: status.as_u16(), message: error_text }.into());
+                        }
                         
                         if retries >= max_retries {
                             return Err(anyhow::anyhow!("API Error ({}): {}", status, error_text));
@@ -120,7 +293,7 @@ impl ApiClient {
                     }
                 },
                 Err(e) => {
-                    log_debug(&format!("API: Network error: {}", e));
+                    debug!("API: Network error: {}", e);
                     if retries >= max_retries {
                         return Err(anyhow::anyhow!("Network error after {} retries: {}", max_retries, e));
                     }
@@ -128,11 +301,75 @@ impl ApiClient {
             }
             
             retries += 1;
-            log_debug(&format!("API: Retrying {}/{}", retries, max_retries));
+            debug!("API: Retrying {}/{}", retries, max_retries);
             tokio::time::sleep(std::time::Duration::from_secs(2)).await;
         }
     }
 
+    /// Renders given text into a page instead of translating it: the translate endpoint gets the
+    /// image plus a `translations` part (`[{"bbox": {...}, "text": "..."}]`) and `render_only: true`,
+    /// so it only cleans the listed bubbles and typesets the text. Used to apply an edited script.
+    /// The provider confirms a render with `render_only: true` in the response headers; a reply
+    /// without it (a backend that ignored the flag and translated the page itself) or one that
+    /// is not an image is an error rather than a page with someone else's text.
+    pub async fn render_page(&self, file_path: &Path, bubbles: &[Bubble], target_lang: &str, font: &str, text_align: &str, stroke_disabled: bool, min_font_size: u32) -> Result<Translated> {
+        debug!("API: Rendering {} bubble(s) into {:?}", bubbles.len(), file_path);
+        let filename = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let translations: Vec<serde_json::Value> = bubbles.iter()
+            .map(|b| serde_json::json!({ "bbox": b.bbox, "source": b.source, "text": b.translation }))
+            .collect();
+        let translations = serde_json::to_string(&translations)?;
+
+        let safe_header = |v: &str| -> String {
+            v.chars().filter(|c| !c.is_control() && (*c as u32) < 127).collect()
+        };
+
+        let reply = self.send_limited("render", || async {
+            let file = File::open(file_path).await?;
+            let file_body = reqwest::Body::wrap_stream(FramedRead::new(file, BytesCodec::new()));
+            let form = multipart::Form::new()
+                .part("file", multipart::Part::stream(file_body).file_name(filename.clone()))
+                .text("translations", translations.clone());
+
+            let mut request_builder = self.client.post(&self.endpoints.translate).multipart(form);
+            request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+            request_builder = request_builder.header("render_only", "true");
+            request_builder = request_builder.header("target_lang", safe_header(target_lang));
+            request_builder = request_builder.header("font", safe_header(font));
+            request_builder = request_builder.header("text_align", safe_header(text_align));
+            request_builder = request_builder.header("stroke_disabled", stroke_disabled.to_string());
+            request_builder = request_builder.header("min_font_size", min_font_size.to_string());
+            Ok(request_builder)
+        }).await?;
+        let status = reply.status;
+        let success_header = reply.headers
+            .get("success")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("false");
+
+        if success_header == "true" && status.is_success() {
+            let rendered = reply.headers
+                .get("render_only")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+            if !rendered {
+                return Err(anyhow::anyhow!("Render API Error: the endpoint did not confirm render_only, so the page may not carry the script's text"));
+            }
+            if image::guess_format(&reply.body).is_err() {
+                return Err(anyhow::anyhow!("Render API Error: the response is not an image ({} bytes)", reply.body.len()));
+            }
+            let credits = CreditInfo::from_headers(&reply.headers);
+            return Ok(Translated { image: reply.body, credits });
+        }
+
+        let error_text = reply.text();
+        debug!("API: Render error response: {}", error_text);
+        if is_key_rejection(status, &error_text) {
+            return Err(KeyRejected { status: status.as_u16(), message: error_text }.into());
+        }
+        Err(anyhow::anyhow!("Render API Error ({}): {}", status, error_text))
+    }
+
     fn add_custom_headers(request_builder: reqwest::RequestBuilder, headers_json: &Option<String>) -> reqwest::RequestBuilder {
         let mut builder = request_builder;
         if let Some(json_str) = headers_json {
@@ -153,66 +390,145 @@ impl ApiClient {
         builder
     }
 
-    pub async fn call_storage(&self, storage_urls_arg: &str) -> Result<serde_json::Value> {
+    pub async fn call_ocr(&self, image_path: &Path) -> Result<Recognized> {
+         let url = &self.endpoints.ocr;
+         debug!("API: Calling OCR: {}", url);
+         
+         let filename = image_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+         let reply = self.send_limited("ocr", || async {
+             let file = File::open(image_path).await?;
+             let file_body = reqwest::Body::wrap_stream(FramedRead::new(file, BytesCodec::new()));
+             let form = multipart::Form::new()
+                 .part("file", multipart::Part::stream(file_body).file_name(filename.clone()));
+
+             let mut request_builder = self.client.post(url).multipart(form);
+             request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+             Ok(Self::add_custom_headers(request_builder, &self.endpoints.ocr_headers))
+         }).await?;
+         let status = reply.status;
+         
+         if !status.is_success() {
+             let text = reply.text();
+             if is_key_rejection(status, &text) {
+                 return Err(KeyRejected { status: status.as_u16(), message: text }.into());
+             }
+             return Err(anyhow::anyhow!("OCR API Error: {}", status));
+         }
+
+         let json: serde_json::Value = serde_json::from_slice(&reply.body)?;
+         let mut credits = CreditInfo::from_headers(&reply.headers);
+         let from_body = CreditInfo::from_json(&json);
+         credits.charged = credits.charged.or(from_body.charged);
+         credits.remaining = credits.remaining.or(from_body.remaining);
+         Ok(Recognized { page: OcrPage::from_json(&json)?, credits })
+    }
+
+    /// Asks the storage endpoint about image URLs, sent comma-separated in the `storage_urls`
+    /// header with the translation settings as for `translate_file`. The first request queues
+    /// the URLs on the provider's side; asking again with the same URLs reports their progress.
+    /// Nothing is uploaded.
+    pub async fn call_storage(&self, urls: &[String], model: &str, target_lang: &str, font: &str, text_align: &str, stroke_disabled: bool, inpaint_only: bool, min_font_size: u32) -> Result<StorageBatch> {
         let url = &self.endpoints.storage;
-        log_debug(&format!("API: Calling Storage: {}", url));
+        debug!("API: Calling Storage for {} URL(s): {}", urls.len(), url);
 
-        let mut request_builder = self.client.get(url);
-        request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
-        
-        request_builder = Self::add_custom_headers(request_builder, &self.endpoints.storage_headers);
-        request_builder = request_builder.header("storage_urls", storage_urls_arg);
+        let safe_header = |v: &str| -> String {
+            v.chars().filter(|c| !c.is_control() && (*c as u32) < 127).collect()
+        };
+        let storage_urls = urls.join(",");
 
-        let response = request_builder.send().await?;
-        let status = response.status();
-        let bytes = response.bytes().await?;
-        
-        if self.endpoints.save_debug_json {
-            if let Ok(mut file) =  File::create("debug_storage_response.json").await {
-               let _ = file.write_all(&bytes).await;
+        let reply = self.send_limited("storage", || async {
+            let mut request_builder = self.client.get(url);
+            request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+            request_builder = Self::add_custom_headers(request_builder, &self.endpoints.storage_headers);
+            request_builder = request_builder.header("storage_urls", storage_urls.as_str());
+            request_builder = request_builder.header("target_lang", safe_header(target_lang));
+            request_builder = request_builder.header("translator", safe_header(model));
+            request_builder = request_builder.header("font", safe_header(font));
+            request_builder = request_builder.header("text_align", safe_header(text_align));
+            request_builder = request_builder.header("stroke_disabled", stroke_disabled.to_string());
+            request_builder = request_builder.header("inpaint_only", inpaint_only.to_string());
+            request_builder = request_builder.header("min_font_size", min_font_size.to_string());
+            Ok(request_builder)
+        }).await?;
+        let status = reply.status;
+        let text = reply.text();
+
+        if !status.is_success() {
+            if is_key_rejection(status, &text) {
+                return Err(KeyRejected { status: status.as_u16(), message: text }.into());
             }
+            return Err(anyhow::anyhow!("Storage API Error ({}): {}", status, text));
         }
 
+        let json: serde_json::Value = serde_json::from_str(&text)?;
+        let mut batch = StorageBatch::from_json(&json)?;
+        let from_headers = CreditInfo::from_headers(&reply.headers);
+        batch.credits.charged = batch.credits.charged.or(from_headers.charged);
+        batch.credits.remaining = batch.credits.remaining.or(from_headers.remaining);
+        Ok(batch)
+    }
+
+    /// Fetches a finished storage result.
+    pub async fn download_result(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.client.get(url).send().await?;
+        let status = response.status();
         if !status.is_success() {
-             return Err(anyhow::anyhow!("Storage API Error: {}", status));
+            return Err(anyhow::anyhow!("Result download failed ({}): {}", status, url));
         }
+        Ok(response.bytes().await?.to_vec())
+    }
 
-        let json: serde_json::Value = serde_json::from_slice(&bytes)?;
-        Ok(json)
+    pub fn catalog_url(&self) -> &str {
+        &self.endpoints.catalog
     }
 
-    pub async fn call_ocr(&self, image_path: &Path) -> Result<serde_json::Value> {
-         let url = &self.endpoints.ocr;
-         log_debug(&format!("API: Calling OCR: {}", url));
-         
-         let file = File::open(image_path).await?;
-         let stream = FramedRead::new(file, BytesCodec::new());
-         let file_body = reqwest::Body::wrap_stream(stream);
-         let filename = image_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    /// Models (with their credit cost), and fonts the provider currently offers.
+    pub async fn fetch_catalog(&self) -> Result<Catalog> {
+        let url = &self.endpoints.catalog;
+        debug!("API: Fetching catalog: {}", url);
+
+        let mut request_builder = self.client.get(url).timeout(std::time::Duration::from_secs(15));
+        if !self.api_key.is_empty() {
+            request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
+        }
 
-         let form = multipart::Form::new()
-             .part("file", multipart::Part::stream(file_body).file_name(filename));
+        let reply = self.send("catalog", request_builder).await?;
+        if !reply.status.is_success() {
+            return Err(anyhow::anyhow!("Catalog API Error: {}", reply.status));
+        }
 
-         let mut request_builder = self.client.post(url).multipart(form);
-         request_builder = request_builder.header("Authorization", format!("Bearer {}", self.api_key));
-         
-         request_builder = Self::add_custom_headers(request_builder, &self.endpoints.ocr_headers);
+        let json: serde_json::Value = serde_json::from_slice(&reply.body)?;
+        Catalog::from_response(json)
+    }
+}
 
-         let response = request_builder.send().await?;
-         let status = response.status();
-         let bytes = response.bytes().await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-         if self.endpoints.save_debug_json {
-            if let Ok(mut file) =  File::create("debug_ocr_response.json").await {
-               let _ = file.write_all(&bytes).await;
-            }
-         }
-         
-         if !status.is_success() {
-             return Err(anyhow::anyhow!("OCR API Error: {}", status));
-         }
+    #[test]
+    fn only_auth_statuses_and_key_codes_reject_a_key() {
+        assert!(is_key_rejection(StatusCode::UNAUTHORIZED, ""));
+        assert!(is_key_rejection(StatusCode::PAYMENT_REQUIRED, "whatever"));
+        assert!(is_key_rejection(StatusCode::FORBIDDEN, ""));
+        assert!(is_key_rejection(StatusCode::BAD_REQUEST, r#"{"error":{"code":"insufficient_credits"}}"#));
+
+        // Wording alone never drops a key
+        assert!(!is_key_rejection(StatusCode::BAD_REQUEST, "insufficient contrast in image"));
+        assert!(!is_key_rejection(StatusCode::TOO_MANY_REQUESTS, "quota exceeded, slow down"));
+        assert!(!is_key_rejection(StatusCode::BAD_REQUEST, r#"{"code":"invalid_image"}"#));
+    }
+
+    #[test]
+    fn rate_limit_delay_prefers_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(rate_limit_delay(&headers, 0).as_secs(), RATE_LIMIT_BASE_SECS);
+        assert_eq!(rate_limit_delay(&headers, 2).as_secs(), RATE_LIMIT_BASE_SECS * 4);
 
-         let json: serde_json::Value = serde_json::from_slice(&bytes)?;
-         Ok(json)
+        headers.insert(reqwest::header::RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(rate_limit_delay(&headers, 3).as_secs(), 7);
+        headers.insert(reqwest::header::RETRY_AFTER, HeaderValue::from_static("3600"));
+        assert_eq!(rate_limit_delay(&headers, 0).as_secs(), RATE_LIMIT_MAX_SECS);
     }
 }
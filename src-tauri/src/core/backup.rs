@@ -0,0 +1,212 @@
+use crate::core::database::{DatabaseManager, HashEntry, ImportMode};
+use crate::core::processor::load_history;
+use crate::core::reconcile::hash_folder_images;
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+// Rows per write when loading a dump into the database
+const IMPORT_BATCH_SIZE: usize = 500;
+// Keep the report small when a dump is badly broken
+const MAX_REPORTED_ERRORS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpFormat {
+    /// One `{"table": ..., "data": {...}}` object per line, covers every table
+    Jsonl,
+    /// Spreadsheet-friendly, holds the `file_hashes` table only
+    Csv,
+}
+
+impl DumpFormat {
+    /// Explicit format wins, otherwise guess from the file extension (defaults to JSON-lines).
+    pub fn resolve(format: Option<&str>, path: &Path) -> Result<Self> {
+        match format.map(|f| f.to_lowercase()) {
+            Some(f) if f == "jsonl" || f == "json" => Ok(DumpFormat::Jsonl),
+            Some(f) if f == "csv" => Ok(DumpFormat::Csv),
+            Some(f) => Err(anyhow!("Unknown dump format '{}' (expected jsonl or csv)", f)),
+            None => {
+                let is_csv = path.extension()
+                    .map(|e| e.to_string_lossy().eq_ignore_ascii_case("csv"))
+                    .unwrap_or(false);
+                Ok(if is_csv { DumpFormat::Csv } else { DumpFormat::Jsonl })
+            }
+        }
+    }
+}
+
+/// A single dumped row, tagged with its table. New tables get a new variant here.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "table", content = "data", rename_all = "snake_case")]
+pub enum DumpRecord {
+    FileHashes(HashEntry),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    pub errors: Vec<String>,
+}
+
+impl ImportReport {
+    fn reject(&mut self, line: usize, reason: String) {
+        self.invalid += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(format!("Row {}: {}", line, reason));
+        }
+    }
+}
+
+/// Blake3 hex digest, as produced by `calculate_file_hash`.
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn write_dump<W: Write>(entries: &[HashEntry], format: DumpFormat, writer: W) -> Result<()> {
+    match format {
+        DumpFormat::Jsonl => {
+            let mut writer = BufWriter::new(writer);
+            for entry in entries {
+                let line = serde_json::to_string(&DumpRecord::FileHashes(entry.clone()))?;
+                writeln!(writer, "{}", line)?;
+            }
+            writer.flush()?;
+        }
+        DumpFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for entry in entries {
+                writer.serialize(entry)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses a dump, dropping rows that fail to parse or carry an invalid hash.
+pub fn read_dump<R: Read>(reader: R, format: DumpFormat, report: &mut ImportReport) -> Result<Vec<HashEntry>> {
+    let mut entries = Vec::new();
+
+    match format {
+        DumpFormat::Jsonl => {
+            for (idx, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<DumpRecord>(&line) {
+                    Ok(DumpRecord::FileHashes(entry)) => entries.push((idx + 1, entry)),
+                    Err(e) => report.reject(idx + 1, e.to_string()),
+                }
+            }
+        }
+        DumpFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(reader);
+            for (idx, row) in reader.deserialize::<HashEntry>().enumerate() {
+                // +2: header line and 1-based numbering
+                match row {
+                    Ok(entry) => entries.push((idx + 2, entry)),
+                    Err(e) => report.reject(idx + 2, e.to_string()),
+                }
+            }
+        }
+    }
+
+    let mut valid = Vec::with_capacity(entries.len());
+    for (line, mut entry) in entries {
+        entry.hash = entry.hash.trim().to_lowercase();
+        if is_valid_hash(&entry.hash) {
+            valid.push(entry);
+        } else {
+            report.reject(line, format!("invalid hash '{}'", entry.hash));
+        }
+    }
+    Ok(valid)
+}
+
+pub async fn export_to_file(db: &DatabaseManager, path: &Path, format: DumpFormat) -> Result<usize> {
+    let entries = db.export_entries().await?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Same temp-then-rename approach as the profile, so a failed export never leaves half a file
+    let temp_path = path.with_extension("tmp");
+    write_dump(&entries, format, File::create(&temp_path)?)?;
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(entries.len())
+}
+
+pub async fn import_from_reader<R: Read>(db: &DatabaseManager, reader: R, format: DumpFormat, mode: ImportMode) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let entries = read_dump(reader, format, &mut report)?;
+
+    let (written, skipped) = db.import_entries(entries, mode, IMPORT_BATCH_SIZE).await?;
+    report.imported = written;
+    report.skipped = skipped;
+    Ok(report)
+}
+
+pub async fn import_from_file(db: &DatabaseManager, path: &Path, format: DumpFormat, mode: ImportMode) -> Result<ImportReport> {
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+    import_from_reader(db, file, format, mode).await
+}
+
+/// Loads a folder's `.f_history` into the database. File names are recovered by hashing the
+/// images still in the folder; hashes with no matching file keep an empty name.
+pub async fn import_history_folder(logger: &impl ProgressLogger, db: &DatabaseManager, folder: &Path) -> Result<ImportReport> {
+    let history_path = folder.join(".f_history");
+    if !history_path.exists() {
+        return Err(anyhow!("No .f_history found in {}", folder.display()));
+    }
+    let history = load_history(&history_path);
+
+    let mut names = hash_folder_images(logger, folder).await;
+    names.retain(|hash, _| history.contains(hash));
+
+    let folder_name = folder.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Root".to_string());
+    let now = chrono::Utc::now();
+
+    let mut report = ImportReport::default();
+    // Never overwrite names that are already in the database with guessed ones
+    let existing = db.existing_hashes(history.iter().cloned().collect()).await?;
+    let mut entries = Vec::with_capacity(history.len());
+    for (idx, hash) in history.into_iter().enumerate() {
+        if !is_valid_hash(&hash) {
+            report.reject(idx + 1, format!("invalid hash '{}'", hash));
+            continue;
+        }
+        if existing.contains(&hash) {
+            report.skipped += 1;
+            continue;
+        }
+        let (name, folder) = names.remove(&hash).unwrap_or_else(|| (String::new(), folder_name.clone()));
+        entries.push(HashEntry {
+            hash,
+            name,
+            folder,
+            created_at: Some(now),
+            updated_at: Some(now),
+            deleted: false,
+            model: String::new(),
+            target_lang: String::new(),
+            synced_at: None,
+        });
+    }
+
+    let (written, skipped) = db.import_entries(entries, ImportMode::Merge, IMPORT_BATCH_SIZE).await?;
+    report.imported = written;
+    report.skipped += skipped;
+    logger.log(t("INFO_HISTORY_IMPORTED", &[("count", &written), ("path", &history_path.display()), ("known", &report.skipped)]));
+    Ok(report)
+}
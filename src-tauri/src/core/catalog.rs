@@ -0,0 +1,234 @@
+use crate::core::api::ApiClient;
+use crate::core::database::DatabaseManager;
+use crate::core::pricing::PricingTable;
+use crate::config::profile::Profile;
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use tracing::warn;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+
+/// How long a fetched catalog is trusted before it is fetched again.
+pub const CATALOG_TTL_HOURS: i64 = 24;
+/// Model used when neither the command line nor the profile names one.
+pub const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+
+// Shipped with the app for offline use: only models whose price is known (1 credit per image)
+const BUNDLED_MODELS: [(&str, &str, u64); 4] = [
+    ("gemini-2.5-flash", "Gemini 2.5 Flash", 1),
+    ("gemini-3-flash", "Gemini 3.0 Flash", 1),
+    ("deepseek", "DeepSeek", 1),
+    ("grok-4-fast", "Grok 4 Fast", 1),
+];
+
+const BUNDLED_FONTS: [(&str, &str); 11] = [
+    ("wildwords", "Wild Words"),
+    ("badcomic", "Bad Comic"),
+    ("komika", "Komika"),
+    ("bangers", "Bangers"),
+    ("edo", "Edo"),
+    ("ridi", "Ridi"),
+    ("bushidoo", "Bushidoo"),
+    ("hayah", "Hayah"),
+    ("itim", "Itim"),
+    ("mogul", "Mogul"),
+    ("noto", "Noto Sans"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelInfo {
+    pub name: String,
+    #[serde(default)]
+    pub label: String,
+    /// Credits per image
+    #[serde(default = "default_cost")]
+    pub cost: u64,
+    /// Takes extra context (e.g. a glossary) with each page
+    #[serde(default, alias = "supports_context")]
+    pub context: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FontInfo {
+    pub name: String,
+    #[serde(default)]
+    pub label: String,
+}
+
+fn default_cost() -> u64 {
+    1
+}
+
+/// Where the catalog in use came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogSource {
+    Provider,
+    Cache,
+    Bundled,
+}
+
+/// Models, fonts and per-model costs offered by the provider.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Catalog {
+    pub models: Vec<ModelInfo>,
+    pub fonts: Vec<FontInfo>,
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub source: CatalogSource,
+}
+
+// Providers may list bare names or objects
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Listed<T> {
+    Name(String),
+    Full(T),
+}
+
+#[derive(Deserialize)]
+struct CatalogResponse {
+    #[serde(default)]
+    models: Vec<Listed<ModelInfo>>,
+    #[serde(default)]
+    fonts: Vec<Listed<FontInfo>>,
+}
+
+impl Catalog {
+    pub fn bundled() -> Self {
+        Self {
+            models: BUNDLED_MODELS.iter()
+                .map(|(name, label, cost)| ModelInfo { name: name.to_string(), label: label.to_string(), cost: *cost, context: false })
+                .collect(),
+            fonts: BUNDLED_FONTS.iter()
+                .map(|(name, label)| FontInfo { name: name.to_string(), label: label.to_string() })
+                .collect(),
+            fetched_at: None,
+            source: CatalogSource::Bundled,
+        }
+    }
+
+    /// Parses the provider's response: `{"models": [...], "fonts": [...]}`.
+    pub fn from_response(value: serde_json::Value) -> Result<Self> {
+        let response: CatalogResponse = serde_json::from_value(value)
+            .map_err(|e| anyhow!("Unexpected catalog format: {}", e))?;
+        let models: Vec<ModelInfo> = response.models.into_iter().map(|m| match m {
+            Listed::Name(name) => ModelInfo { label: name.clone(), name, cost: default_cost(), context: false },
+            Listed::Full(info) => info,
+        }).collect();
+        let fonts: Vec<FontInfo> = response.fonts.into_iter().map(|f| match f {
+            Listed::Name(name) => FontInfo { label: name.clone(), name },
+            Listed::Full(info) => info,
+        }).collect();
+        if models.is_empty() {
+            return Err(anyhow!("The catalog lists no models"));
+        }
+        Ok(Self { models, fonts, fetched_at: Some(Utc::now()), source: CatalogSource::Provider })
+    }
+
+    pub fn model(&self, name: &str) -> Option<&ModelInfo> {
+        self.models.iter().find(|m| m.name == name)
+    }
+
+    pub fn has_font(&self, name: &str) -> bool {
+        self.fonts.iter().any(|f| f.name == name)
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.fetched_at.is_some_and(|at| Utc::now() - at < chrono::Duration::hours(CATALOG_TTL_HOURS))
+    }
+
+    /// Checks a run's model and font. Unknown names are errors against the provider's list,
+    /// but only warnings against the bundled one, which may be out of date.
+    pub fn validate(&self, model: &str, font: &str) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        if self.model(model).is_none() {
+            let names: Vec<&str> = self.models.iter().map(|m| m.name.as_str()).collect();
+            problems.push(format!("Unknown model '{}' (available: {})", model, names.join(", ")));
+        }
+        // An empty font list means the provider does not restrict fonts
+        if !self.fonts.is_empty() && !self.has_font(font) {
+            let names: Vec<&str> = self.fonts.iter().map(|f| f.name.as_str()).collect();
+            problems.push(format!("Unknown font '{}' (available: {})", font, names.join(", ")));
+        }
+        if !problems.is_empty() && self.source != CatalogSource::Bundled {
+            return Err(UnknownCatalogEntry(problems.join("; ")).into());
+        }
+        Ok(problems)
+    }
+}
+
+/// The catalog to use: the cached copy while it is fresh, otherwise a new fetch (cached
+/// again), falling back to a stale cache and finally to the bundled list when offline.
+/// Without a configured `catalog_url` the bundled list is used as is.
+pub async fn resolve_catalog(logger: &impl ProgressLogger, client: &ApiClient, db: Option<&DatabaseManager>, refresh: bool) -> Catalog {
+    let url = client.catalog_url().to_string();
+    if url.trim().is_empty() {
+        return Catalog::bundled();
+    }
+    let cached = match db {
+        Some(db) => db.load_catalog(&url).await.unwrap_or_else(|e| {
+            warn!("CATALOG CACHE READ ERROR: {}", e);
+            None
+        }),
+        None => None,
+    };
+
+    if let Some(ref catalog) = cached {
+        if !refresh && catalog.is_fresh() {
+            return Catalog { source: CatalogSource::Cache, ..catalog.clone() };
+        }
+    }
+
+    match client.fetch_catalog().await {
+        Ok(catalog) => {
+            if let Some(db) = db {
+                if let Err(e) = db.save_catalog(&url, &catalog).await {
+                    warn!("CATALOG CACHE WRITE ERROR: {}", e);
+                }
+            }
+            catalog
+        }
+        Err(e) => match cached {
+            Some(catalog) => {
+                let date = catalog.fetched_at.map(|at| at.to_rfc3339()).unwrap_or_default();
+                logger.log(t("WARN_CATALOG_CACHED", &[("error", &e), ("date", &date)]));
+                Catalog { source: CatalogSource::Cache, ..catalog }
+            }
+            None => {
+                logger.log(t("WARN_CATALOG_BUNDLED", &[("error", &e)]));
+                Catalog::bundled()
+            }
+        },
+    }
+}
+
+/// The run's model or font is not in the provider's catalog: a setting to fix, not a
+/// failure of the run itself.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct UnknownCatalogEntry(pub String);
+
+/// What a run learns from the catalog before it starts.
+pub struct Preflight {
+    /// The catalog's prices with the profile's overrides on top
+    pub pricing: PricingTable,
+    /// Whether the model takes context (glossary) with each page
+    pub context: bool,
+}
+
+/// Checks a run's model and font against the catalog before anything is sent.
+pub async fn preflight(logger: &impl ProgressLogger, client: &ApiClient, db: Option<&DatabaseManager>, profile: Option<&Profile>, model: &str, font: &str) -> Result<Preflight> {
+    let catalog = resolve_catalog(logger, client, db, false).await;
+    for warning in catalog.validate(model, font)? {
+        logger.log(t("WARN_CATALOG_CHECK", &[("warning", &warning)]));
+    }
+    let pricing = PricingTable::from_catalog(&catalog);
+    Ok(Preflight {
+        pricing: match profile {
+            Some(profile) => pricing.with_overrides(profile),
+            None => pricing,
+        },
+        context: catalog.model(model).map(|m| m.context).unwrap_or(false),
+    })
+}
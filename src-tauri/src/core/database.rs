@@ -4,13 +4,55 @@ use surrealdb::engine::remote::http::Http;
 use surrealdb::engine::remote::ws::Ws;
 use surrealdb::opt::auth::Root;
 use surrealdb::Surreal;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, RwLock};
+use crate::config::profile::{DatabaseMode, Profile};
+use crate::core::catalog::Catalog;
+use crate::core::glossary::GlossaryTerm;
+use crate::utils::logger::{ProgressLogger, ConsoleLogger};
+use crate::config::language::t;
+use tracing::{error, info};
 
+// Applies per connection attempt and per sync batch, not to the whole sync
 const REMOTE_TIMEOUT_SECS: u64 = 15;
 const LOCAL_TIMEOUT_SECS: u64 = 5;
+const SYNC_MAX_RETRIES: u32 = 3;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+// Sort key for listings; rows imported without a date sort last
+const SORT_EXPR: &str = "<datetime> (created_at ?? '1970-01-01T00:00:00Z')";
+// How often the auto-sync loop re-checks the profile while auto-sync is disabled
+const AUTO_SYNC_IDLE_SECS: u64 = 60;
+// Sync cursor key: when the row was written to this database (older rows fall back to their dates)
+const SYNC_EXPR: &str = "<datetime> (synced_at ?? updated_at ?? created_at ?? '1970-01-01T00:00:00Z')";
+// Pulls leave rows the remote wrote in the last few seconds for the next sync: a batch that is
+// still committing may carry an earlier `synced_at` than rows that are already visible.
+const SYNC_SETTLE_SECS: u64 = 2 * REMOTE_TIMEOUT_SECS;
+
+// Upserts each entry under its hash as record id, so both sides agree on identity. The database
+// stamps `synced_at` itself, so sync cursors never depend on the writer's clock.
+const UPSERT_ENTRIES_QUERY: &str = "BEGIN TRANSACTION; \
+    FOR $e IN $entries { \
+        UPSERT type::thing('file_hashes', $e.hash) CONTENT $e; \
+        UPDATE type::thing('file_hashes', $e.hash) SET synced_at = time::now(); \
+    }; \
+    COMMIT TRANSACTION;";
+// Replace import: every live row missing from the dump becomes a tombstone, so the replace
+// also reaches the remote, and the dump is written; all of it or nothing.
+const REPLACE_ENTRIES_QUERY: &str = "BEGIN TRANSACTION; \
+    UPDATE file_hashes SET deleted = true, updated_at = $now, synced_at = time::now() \
+        WHERE deleted != true AND hash NOTINSIDE $hashes; \
+    FOR $e IN $entries { \
+        UPSERT type::thing('file_hashes', $e.hash) CONTENT $e; \
+        UPDATE type::thing('file_hashes', $e.hash) SET synced_at = time::now(); \
+    }; \
+    COMMIT TRANSACTION;";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HashEntry {
@@ -18,7 +60,36 @@ pub struct HashEntry {
     pub name: String,
     #[serde(default)]
     pub folder: String,
-    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    // Last local modification; older rows without it fall back to created_at
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    // Tombstone flag: deletes are kept as rows so they can propagate to the remote
+    #[serde(default)]
+    pub deleted: bool,
+    // Translation settings the file was processed with (empty for manual/legacy rows)
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub target_lang: String,
+    // Set by the database on every write; never exported or copied to the other side
+    #[serde(default, skip_serializing)]
+    pub synced_at: Option<DateTime<Utc>>,
+}
+
+impl HashEntry {
+    pub fn modified_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at.or(self.created_at)
+    }
+
+    // Position of the row in its database's change feed, matching SYNC_EXPR
+    fn sync_mark(&self) -> DateTime<Utc> {
+        self.synced_at.or(self.modified_at()).unwrap_or(DateTime::UNIX_EPOCH)
+    }
+
+    fn same_content(&self, other: &HashEntry) -> bool {
+        self.name == other.name && self.folder == other.folder && self.deleted == other.deleted
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +99,116 @@ pub struct HashEntryOutput {
     #[serde(default)]
     pub folder: String,
     pub created_at: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub target_lang: String,
+}
+
+impl From<HashEntry> for HashEntryOutput {
+    fn from(e: HashEntry) -> Self {
+        Self {
+            hash: e.hash,
+            name: e.name,
+            folder: e.folder,
+            created_at: e.created_at.map(|c| c.to_rfc3339()).unwrap_or_default(),
+            model: e.model,
+            target_lang: e.target_lang,
+        }
+    }
+}
+
+/// Filters for listing the hash table. All fields are optional and combined with AND.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HashQuery {
+    /// Case-insensitive substring of the name, folder or hash
+    pub search: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub model: Option<String>,
+    pub target_lang: Option<String>,
+    /// Opaque cursor from a previous page's `next_cursor`
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HashPage {
+    pub entries: Vec<HashEntryOutput>,
+    /// Rows matching the filters, across all pages
+    pub total: usize,
+    pub next_cursor: Option<String>,
+}
+
+/// Credits spent with one API key (by label) on one UTC day, stored in `key_usage`.
+/// Local bookkeeping only, never synced to the remote.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KeyUsage {
+    pub label: String,
+    /// `YYYY-MM-DD`
+    pub day: String,
+    #[serde(default)]
+    pub credits: u64,
+    #[serde(default)]
+    pub pages: u64,
+}
+
+/// Summary of a sync run, returned to the UI / API callers.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncDirection {
+    Push,
+    Pull,
+    Both,
+}
+
+// Per-remote high-water marks, stored locally in the `sync_state` table.
+// The (synced_at, hash) pair is a cursor into the source database's own write order: it is
+// saved after every completed batch, so an interrupted sync resumes where it stopped.
+// `last_push` is a local `synced_at`, `last_pull` a remote one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct SyncState {
+    remote: String,
+    last_push: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_push_hash: String,
+    last_pull: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_pull_hash: String,
+}
+
+/// How imported rows are combined with the existing table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep existing rows; imported rows only win when they are newer
+    Merge,
+    /// Tombstone the existing rows the import lacks and write the imported ones, in one
+    /// transaction, so a sync carries the deletions to the other side
+    Replace,
+}
+
+impl std::str::FromStr for ImportMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            other => Err(anyhow::anyhow!("Unknown import mode '{}' (expected merge or replace)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CountRow {
+    count: usize,
 }
 
 #[derive(Clone)]
@@ -47,19 +228,36 @@ impl DatabaseManager {
     }
 
     pub async fn save_hash(&self, hash: String, name: String, folder: String) -> Result<()> {
+        self.upsert_entry(hash, name, folder, None, None).await
+    }
+
+    /// Records a translated file together with the model and language it was processed with.
+    pub async fn save_translation(&self, hash: String, name: String, folder: String, model: String, target_lang: String) -> Result<()> {
+        self.upsert_entry(hash, name, folder, Some(model), Some(target_lang)).await
+    }
+
+    async fn upsert_entry(&self, hash: String, name: String, folder: String, model: Option<String>, target_lang: Option<String>) -> Result<()> {
         let db = self.db.clone();
         
         tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
-            let _: Option<HashEntry> = db
-                .upsert(("file_hashes", &hash))
-                .content(HashEntry {
-                    hash,
-                    name,
-                    folder,
-                    created_at: Some(chrono::Utc::now()),
-                })
-                .await?;
-            Ok::<(), anyhow::Error>(())
+            // Keep the original created_at (and translation settings) when re-saving an entry
+            let existing: Option<HashEntry> = db.select(("file_hashes", &hash)).await?;
+            let now = Utc::now();
+            let created_at = existing.as_ref().and_then(|e| e.created_at).unwrap_or(now);
+            let model = model.or_else(|| existing.as_ref().map(|e| e.model.clone())).unwrap_or_default();
+            let target_lang = target_lang.or_else(|| existing.as_ref().map(|e| e.target_lang.clone())).unwrap_or_default();
+
+            Self::write_entries(&db, vec![HashEntry {
+                hash,
+                name,
+                folder,
+                created_at: Some(created_at),
+                updated_at: Some(now),
+                deleted: false,
+                model,
+                target_lang,
+                synced_at: None,
+            }]).await
         }).await.map_err(|_| anyhow::anyhow!("Save timeout"))??;
         
         Ok(())
@@ -71,40 +269,106 @@ impl DatabaseManager {
         
         let result = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
             let entry: Option<HashEntry> = db.select(("file_hashes", &hash)).await?;
-            Ok::<Option<String>, anyhow::Error>(entry.map(|e| e.name))
+            Ok::<Option<String>, anyhow::Error>(entry.filter(|e| !e.deleted).map(|e| e.name))
         }).await.map_err(|_| anyhow::anyhow!("Get timeout"))??;
         
         Ok(result)
     }
 
-    pub async fn list_all(&self) -> Result<Vec<HashEntryOutput>> {
+    /// Point lookup used for dedup: true when a live row exists for the hash.
+    pub async fn has_hash(&self, hash: &str) -> Result<bool> {
+        Ok(self.get_name(hash).await?.is_some())
+    }
+
+    /// Filtered, cursor-paginated listing, newest first.
+    pub async fn query_entries(&self, query: &HashQuery) -> Result<HashPage> {
         let db = self.db.clone();
-        
-        let result = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
-            let mut entries: Vec<HashEntry> = db.select("file_hashes").await?;
-            
-            // Sort by date descending
-            entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-            
-            let output: Vec<HashEntryOutput> = entries.into_iter().map(|e| HashEntryOutput {
-                hash: e.hash,
-                name: e.name,
-                folder: e.folder,
-                created_at: e.created_at.map(|c| c.to_rfc3339()).unwrap_or_default(),
-            }).collect();
-            
-            Ok::<Vec<HashEntryOutput>, anyhow::Error>(output)
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        let mut conditions = vec!["deleted != true".to_string()];
+        if query.search.as_deref().is_some_and(|s| !s.trim().is_empty()) {
+            conditions.push("(string::lowercase(name) CONTAINS $search OR string::lowercase(folder) CONTAINS $search OR hash CONTAINS $search)".to_string());
+        }
+        if query.from.is_some() {
+            conditions.push(format!("{} >= <datetime> $from", SORT_EXPR));
+        }
+        if query.to.is_some() {
+            conditions.push(format!("{} <= <datetime> $to", SORT_EXPR));
+        }
+        if query.model.as_deref().is_some_and(|m| !m.is_empty()) {
+            conditions.push("model = $model".to_string());
+        }
+        if query.target_lang.as_deref().is_some_and(|l| !l.is_empty()) {
+            conditions.push("target_lang = $target_lang".to_string());
+        }
+        let filter = conditions.join(" AND ");
+
+        // Cursor = "<created_at>|<hash>" of the last row on the previous page
+        let cursor = match query.cursor.as_deref() {
+            Some(c) if !c.is_empty() => {
+                let (at, hash) = c.split_once('|').ok_or_else(|| anyhow::anyhow!("Invalid cursor"))?;
+                let at = DateTime::parse_from_rfc3339(at).map_err(|_| anyhow::anyhow!("Invalid cursor"))?.with_timezone(&Utc);
+                Some((at, hash.to_string()))
+            }
+            _ => None,
+        };
+        let page_filter = if cursor.is_some() {
+            format!("{} AND ({expr} < <datetime> $cursor_at OR ({expr} = <datetime> $cursor_at AND hash < $cursor_hash))", filter, expr = SORT_EXPR)
+        } else {
+            filter.clone()
+        };
+
+        let (cursor_at, cursor_hash) = cursor.unzip();
+        let search = query.search.as_ref().map(|s| s.trim().to_lowercase());
+        let (from, to) = (query.from, query.to);
+        let (model, target_lang) = (query.model.clone(), query.target_lang.clone());
+
+        let (total, entries) = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let mut response = db
+                .query(format!("SELECT count() FROM file_hashes WHERE {} GROUP ALL", filter))
+                .query(format!("SELECT *, {} AS sort_at FROM file_hashes WHERE {} ORDER BY sort_at DESC, hash DESC LIMIT $limit", SORT_EXPR, page_filter))
+                .bind(("search", search))
+                .bind(("from", from))
+                .bind(("to", to))
+                .bind(("model", model))
+                .bind(("target_lang", target_lang))
+                .bind(("cursor_at", cursor_at))
+                .bind(("cursor_hash", cursor_hash))
+                .bind(("limit", limit))
+                .await?;
+            let count: Option<CountRow> = response.take(0)?;
+            let entries: Vec<HashEntry> = response.take(1)?;
+            Ok::<_, anyhow::Error>((count.map(|c| c.count).unwrap_or(0), entries))
         }).await.map_err(|_| anyhow::anyhow!("List timeout"))??;
-        
-        Ok(result)
+
+        let next_cursor = if entries.len() == limit {
+            entries.last().map(|e| {
+                let at = e.created_at.unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+                format!("{}|{}", at.to_rfc3339(), e.hash)
+            })
+        } else {
+            None
+        };
+
+        Ok(HashPage {
+            entries: entries.into_iter().map(HashEntryOutput::from).collect(),
+            total,
+            next_cursor,
+        })
     }
 
     pub async fn delete_hash(&self, hash: &str) -> Result<()> {
         let db = self.db.clone();
         let hash = hash.to_string();
         
+        // Tombstone instead of removing, so the delete reaches the remote on next sync
         tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
-            let _: Option<HashEntry> = db.delete(("file_hashes", &hash)).await?;
+            let _ = db
+                .query("UPDATE type::thing('file_hashes', $hash) SET deleted = true, updated_at = $now, synced_at = time::now()")
+                .bind(("hash", hash))
+                .bind(("now", Utc::now()))
+                .await?
+                .check()?;
             Ok::<(), anyhow::Error>(())
         }).await.map_err(|_| anyhow::anyhow!("Delete timeout"))??;
         
@@ -115,13 +379,247 @@ impl DatabaseManager {
         let db = self.db.clone();
         
         tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
-            let _: Vec<HashEntry> = db.delete("file_hashes").await?;
+            let _ = db
+                .query("UPDATE file_hashes SET deleted = true, updated_at = $now, synced_at = time::now() WHERE deleted != true")
+                .bind(("now", Utc::now()))
+                .await?
+                .check()?;
             Ok::<(), anyhow::Error>(())
         }).await.map_err(|_| anyhow::anyhow!("Clear timeout"))??;
         
         Ok(())
     }
 
+    // --- API Key Usage ---
+
+    /// Adds one translated page and its credits to the key's row for `day`.
+    pub async fn record_key_usage(&self, label: &str, day: &str, credits: u64) -> Result<()> {
+        let db = self.db.clone();
+        let (label, day) = (label.to_string(), day.to_string());
+
+        tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let _ = db
+                .query("UPSERT type::thing('key_usage', [$label, $day]) SET label = $label, day = $day, credits = (credits ?? 0) + $credits, pages = (pages ?? 0) + 1")
+                .bind(("label", label))
+                .bind(("day", day))
+                .bind(("credits", credits))
+                .await?
+                .check()?;
+            Ok::<(), anyhow::Error>(())
+        }).await.map_err(|_| anyhow::anyhow!("Save timeout"))??;
+
+        Ok(())
+    }
+
+    /// Usage rows from `since` (inclusive, `YYYY-MM-DD`) on, newest day first.
+    pub async fn list_key_usage(&self, since: Option<String>) -> Result<Vec<KeyUsage>> {
+        let db = self.db.clone();
+
+        let rows = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let rows: Vec<KeyUsage> = db
+                .query("SELECT label, day, credits, pages FROM key_usage WHERE day >= $since ORDER BY day DESC, label ASC")
+                .bind(("since", since.unwrap_or_default()))
+                .await?
+                .take(0)?;
+            Ok::<_, anyhow::Error>(rows)
+        }).await.map_err(|_| anyhow::anyhow!("List timeout"))??;
+
+        Ok(rows)
+    }
+
+    // --- Model Catalog Cache ---
+
+    /// The catalog last fetched from `url`, however old.
+    pub async fn load_catalog(&self, url: &str) -> Result<Option<Catalog>> {
+        #[derive(Deserialize)]
+        struct Row {
+            catalog: Catalog,
+        }
+
+        let db = self.db.clone();
+        let url = url.to_string();
+
+        let row = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let row: Option<Row> = db
+                .query("SELECT catalog FROM ONLY type::thing('catalog_cache', $url)")
+                .bind(("url", url))
+                .await?
+                .take(0)?;
+            Ok::<_, anyhow::Error>(row)
+        }).await.map_err(|_| anyhow::anyhow!("Load timeout"))??;
+
+        Ok(row.map(|r| r.catalog))
+    }
+
+    pub async fn save_catalog(&self, url: &str, catalog: &Catalog) -> Result<()> {
+        let db = self.db.clone();
+        let url = url.to_string();
+        let catalog = catalog.clone();
+
+        tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let _ = db
+                .query("UPSERT type::thing('catalog_cache', $url) SET url = $url, catalog = $catalog")
+                .bind(("url", url))
+                .bind(("catalog", catalog))
+                .await?
+                .check()?;
+            Ok::<(), anyhow::Error>(())
+        }).await.map_err(|_| anyhow::anyhow!("Save timeout"))??;
+
+        Ok(())
+    }
+
+    // --- Glossary ---
+
+    /// Terms of one series (by `glossary::series_key`), alphabetical.
+    pub async fn list_glossary(&self, series: &str) -> Result<Vec<GlossaryTerm>> {
+        let db = self.db.clone();
+        let series = series.to_string();
+
+        let rows = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let rows: Vec<GlossaryTerm> = db
+                .query("SELECT series, source, target, notes FROM glossary WHERE series = $series ORDER BY source ASC")
+                .bind(("series", series))
+                .await?
+                .take(0)?;
+            Ok::<_, anyhow::Error>(rows)
+        }).await.map_err(|_| anyhow::anyhow!("List timeout"))??;
+
+        Ok(rows)
+    }
+
+    /// Series that have at least one term.
+    pub async fn list_glossary_series(&self) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Row {
+            series: String,
+        }
+
+        let db = self.db.clone();
+
+        let rows = tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let rows: Vec<Row> = db
+                .query("SELECT series FROM glossary GROUP BY series ORDER BY series ASC")
+                .await?
+                .take(0)?;
+            Ok::<_, anyhow::Error>(rows)
+        }).await.map_err(|_| anyhow::anyhow!("List timeout"))??;
+
+        Ok(rows.into_iter().map(|r| r.series).collect())
+    }
+
+    /// Adds or updates a term; a series has one entry per source term, whatever its case.
+    pub async fn save_glossary_term(&self, term: &GlossaryTerm) -> Result<()> {
+        let db = self.db.clone();
+        let term = term.clone();
+
+        tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let _ = db
+                .query("UPSERT type::thing('glossary', [$series, string::lowercase($source)]) SET series = $series, source = $source, target = $target, notes = $notes")
+                .bind(("series", term.series))
+                .bind(("source", term.source))
+                .bind(("target", term.target))
+                .bind(("notes", term.notes))
+                .await?
+                .check()?;
+            Ok::<(), anyhow::Error>(())
+        }).await.map_err(|_| anyhow::anyhow!("Save timeout"))??;
+
+        Ok(())
+    }
+
+    pub async fn delete_glossary_term(&self, series: &str, source: &str) -> Result<()> {
+        let db = self.db.clone();
+        let (series, source) = (series.to_string(), source.to_string());
+
+        tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let _ = db
+                .query("DELETE type::thing('glossary', [$series, string::lowercase($source)])")
+                .bind(("series", series))
+                .bind(("source", source))
+                .await?
+                .check()?;
+            Ok::<(), anyhow::Error>(())
+        }).await.map_err(|_| anyhow::anyhow!("Delete timeout"))??;
+
+        Ok(())
+    }
+
+    pub async fn clear_glossary(&self, series: &str) -> Result<()> {
+        let db = self.db.clone();
+        let series = series.to_string();
+
+        tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), async move {
+            let _ = db
+                .query("DELETE glossary WHERE series = $series")
+                .bind(("series", series))
+                .await?
+                .check()?;
+            Ok::<(), anyhow::Error>(())
+        }).await.map_err(|_| anyhow::anyhow!("Clear timeout"))??;
+
+        Ok(())
+    }
+
+    // --- Bulk Access (Export / Import) ---
+
+    /// Every row of `file_hashes`, tombstones included, ordered by hash.
+    pub async fn export_entries(&self) -> Result<Vec<HashEntry>> {
+        let entries: Vec<HashEntry> = self.db
+            .query("SELECT * FROM file_hashes ORDER BY hash")
+            .await?
+            .take(0)?;
+        Ok(entries)
+    }
+
+    /// Which of the given hashes already have a live (non-deleted) row.
+    pub async fn existing_hashes(&self, hashes: Vec<String>) -> Result<HashSet<String>> {
+        let found = Self::entries_by_hash(&self.db, hashes).await?;
+        Ok(found.into_values().filter(|e| !e.deleted).map(|e| e.hash).collect())
+    }
+
+    /// Writes imported rows. Returns (written, skipped); in merge mode rows are written in
+    /// batches and a row is skipped when the local copy is the same age or newer. Replace mode
+    /// is a single transaction that tombstones every row the dump does not contain.
+    pub async fn import_entries(&self, entries: Vec<HashEntry>, mode: ImportMode, batch_size: usize) -> Result<(usize, usize)> {
+        let db = self.db.clone();
+
+        if mode == ImportMode::Replace {
+            let written = entries.len();
+            let hashes: Vec<String> = entries.iter().map(|e| e.hash.clone()).collect();
+            db.query(REPLACE_ENTRIES_QUERY)
+                .bind(("now", Utc::now()))
+                .bind(("hashes", hashes))
+                .bind(("entries", entries))
+                .await?
+                .check()?;
+            return Ok((written, 0));
+        }
+
+        let mut written = 0;
+        let mut skipped = 0;
+
+        for chunk in entries.chunks(batch_size.max(1)) {
+            let to_write: Vec<HashEntry> = if mode == ImportMode::Merge {
+                let current = Self::entries_by_hash(&db, chunk.iter().map(|e| e.hash.clone()).collect()).await?;
+                chunk.iter()
+                    .filter(|e| current.get(&e.hash).is_none_or(|l| e.modified_at() > l.modified_at()))
+                    .cloned()
+                    .collect()
+            } else {
+                chunk.to_vec()
+            };
+
+            skipped += chunk.len() - to_write.len();
+            written += to_write.len();
+
+            tokio::time::timeout(Duration::from_secs(LOCAL_TIMEOUT_SECS), Self::write_entries(&db, to_write))
+                .await.map_err(|_| anyhow::anyhow!("Import timeout"))??;
+        }
+
+        Ok((written, skipped))
+    }
+
     // --- Auth Helper ---
     async fn auth_remote<C: surrealdb::Connection>(remote_db: &Surreal<C>, token: &str, user: &str, pass: &str) -> Result<()> {
         if !token.is_empty() {
@@ -156,65 +654,372 @@ impl DatabaseManager {
 
     // --- Dynamic Protocol Support (HTTP/WS) with Timeout ---
 
-    pub async fn push_to_remote(&self, url: &str, token: &str, user: &str, pass: &str) -> Result<()> {
-        let db = self.db.clone();
-        let entries: Vec<HashEntry> = db.select("file_hashes").await?;
-        if entries.is_empty() { return Ok(()); }
+    pub async fn push_to_remote(&self, url: &str, token: &str, user: &str, pass: &str, batch_size: usize, logger: &impl ProgressLogger) -> Result<SyncReport> {
+        self.sync_remote(url, token, user, pass, SyncDirection::Push, batch_size, logger).await
+    }
 
-        let timeout = Duration::from_secs(REMOTE_TIMEOUT_SECS);
-        
-        tokio::time::timeout(timeout, async {
-            if url.starts_with("ws") {
-                let remote_db = Surreal::new::<Ws>(url.trim_start_matches("ws://").trim_start_matches("wss://")).await?;
-                Self::sync_push(&remote_db, token, user, pass, entries).await?;
-            } else {
-                let remote_db = Surreal::new::<Http>(url.trim_start_matches("http://").trim_start_matches("https://")).await?;
-                Self::sync_push(&remote_db, token, user, pass, entries).await?;
-            }
-            Ok::<(), anyhow::Error>(())
-        }).await.map_err(|_| anyhow::anyhow!("Push timeout ({}s)", REMOTE_TIMEOUT_SECS))??;
-        
-        Ok(())
+    pub async fn pull_from_remote(&self, url: &str, token: &str, user: &str, pass: &str, batch_size: usize, logger: &impl ProgressLogger) -> Result<SyncReport> {
+        self.sync_remote(url, token, user, pass, SyncDirection::Pull, batch_size, logger).await
     }
 
-    pub async fn pull_from_remote(&self, url: &str, token: &str, user: &str, pass: &str) -> Result<()> {
+    /// Incremental two-way sync. Only rows changed since the last sync with this remote are
+    /// exchanged; when both sides changed the same hash, the newer `updated_at` wins.
+    pub async fn sync_with_remote(&self, url: &str, token: &str, user: &str, pass: &str, batch_size: usize, logger: &impl ProgressLogger) -> Result<SyncReport> {
+        self.sync_remote(url, token, user, pass, SyncDirection::Both, batch_size, logger).await
+    }
+
+    async fn sync_remote(&self, url: &str, token: &str, user: &str, pass: &str, direction: SyncDirection, batch_size: usize, logger: &impl ProgressLogger) -> Result<SyncReport> {
         let db = self.db.clone();
+        let remote_key = Self::remote_key(url);
+        let state: Option<SyncState> = db.select(("sync_state", remote_key.as_str())).await?;
+        let state = state.unwrap_or_else(|| SyncState { remote: url.to_string(), ..Default::default() });
+        let batch_size = batch_size.max(1);
+
         let timeout = Duration::from_secs(REMOTE_TIMEOUT_SECS);
-        
-        tokio::time::timeout(timeout, async {
-            if url.starts_with("ws") {
+
+        if url.starts_with("ws") {
+            let remote_db = tokio::time::timeout(timeout, async {
                 let remote_db = Surreal::new::<Ws>(url.trim_start_matches("ws://").trim_start_matches("wss://")).await?;
-                Self::sync_pull(&db, &remote_db, token, user, pass).await?;
-            } else {
+                Self::auth_remote(&remote_db, token, user, pass).await?;
+                Ok::<_, anyhow::Error>(remote_db)
+            }).await.map_err(|_| anyhow::anyhow!("Connection timeout ({}s)", REMOTE_TIMEOUT_SECS))??;
+            Self::sync_both(&db, &remote_db, &remote_key, state, direction, batch_size, logger).await
+        } else {
+            let remote_db = tokio::time::timeout(timeout, async {
                 let remote_db = Surreal::new::<Http>(url.trim_start_matches("http://").trim_start_matches("https://")).await?;
-                Self::sync_pull(&db, &remote_db, token, user, pass).await?;
+                Self::auth_remote(&remote_db, token, user, pass).await?;
+                Ok::<_, anyhow::Error>(remote_db)
+            }).await.map_err(|_| anyhow::anyhow!("Connection timeout ({}s)", REMOTE_TIMEOUT_SECS))??;
+            Self::sync_both(&db, &remote_db, &remote_key, state, direction, batch_size, logger).await
+        }
+    }
+
+    // Stable id for a remote, so each configured server keeps its own high-water marks
+    fn remote_key(url: &str) -> String {
+        let normalized = url.trim().trim_end_matches('/').to_lowercase();
+        hex::encode(&blake3::hash(normalized.as_bytes()).as_bytes()[0..8])
+    }
+
+    // Runs one batch operation with a timeout, retrying with a short backoff
+    async fn with_retry<T, F, Fut>(what: &str, logger: &impl ProgressLogger, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            let err = match tokio::time::timeout(Duration::from_secs(REMOTE_TIMEOUT_SECS), op()).await {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(e)) => e,
+                Err(_) => anyhow::anyhow!("timeout ({}s)", REMOTE_TIMEOUT_SECS),
+            };
+
+            if retries >= SYNC_MAX_RETRIES {
+                return Err(anyhow::anyhow!("{} failed after {} retries: {}", what, SYNC_MAX_RETRIES, err));
             }
-            Ok::<(), anyhow::Error>(())
-        }).await.map_err(|_| anyhow::anyhow!("Pull timeout ({}s)", REMOTE_TIMEOUT_SECS))??;
-        
-        Ok(())
+            retries += 1;
+            logger.log(t("WARN_SYNC_RETRY", &[("what", &what), ("error", &err), ("retry", &retries), ("max", &SYNC_MAX_RETRIES)]));
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
     }
 
-    async fn sync_push<C: surrealdb::Connection>(remote_db: &Surreal<C>, token: &str, user: &str, pass: &str, entries: Vec<HashEntry>) -> Result<()> {
-        Self::auth_remote(remote_db, token, user, pass).await?;
-        
-        let _ = remote_db
-            .query("INSERT INTO file_hashes $entries ON DUPLICATE KEY UPDATE name = $after.name, folder = $after.folder, created_at = $after.created_at")
+    // Rows after the (since, after) cursor in write order; `settle` skips the most recent writes
+    fn changes_condition(since: &Option<DateTime<Utc>>, settle: bool) -> String {
+        let mut conditions = Vec::new();
+        if since.is_some() {
+            conditions.push(format!("({expr} > <datetime> $since OR ({expr} = <datetime> $since AND hash > $after))", expr = SYNC_EXPR));
+        }
+        if settle {
+            conditions.push(format!("{} <= time::now() - {}s", SYNC_EXPR, SYNC_SETTLE_SECS));
+        }
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        }
+    }
+
+    async fn count_changes<C: surrealdb::Connection>(db: &Surreal<C>, since: Option<DateTime<Utc>>, after: String, settle: bool) -> Result<usize> {
+        let query = format!("SELECT count() FROM file_hashes {} GROUP ALL", Self::changes_condition(&since, settle));
+        let row: Option<CountRow> = db
+            .query(query)
+            .bind(("since", since))
+            .bind(("after", after))
+            .await?
+            .take(0)?;
+        Ok(row.map(|r| r.count).unwrap_or(0))
+    }
+
+    // One page of changed rows, ordered by (synced, hash) so the cursor is stable
+    async fn changes_page<C: surrealdb::Connection>(db: &Surreal<C>, since: Option<DateTime<Utc>>, after: String, limit: usize, settle: bool) -> Result<Vec<HashEntry>> {
+        let query = format!(
+            "SELECT *, {} AS synced FROM file_hashes {} ORDER BY synced, hash LIMIT $limit",
+            SYNC_EXPR,
+            Self::changes_condition(&since, settle)
+        );
+        let entries: Vec<HashEntry> = db
+            .query(query)
+            .bind(("since", since))
+            .bind(("after", after))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
+        Ok(entries)
+    }
+
+    async fn entries_by_hash<C: surrealdb::Connection>(db: &Surreal<C>, hashes: Vec<String>) -> Result<HashMap<String, HashEntry>> {
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let entries: Vec<HashEntry> = db
+            .query("SELECT * FROM file_hashes WHERE hash IN $hashes")
+            .bind(("hashes", hashes))
+            .await?
+            .take(0)?;
+        Ok(entries.into_iter().map(|e| (e.hash.clone(), e)).collect())
+    }
+
+    async fn write_entries<C: surrealdb::Connection>(db: &Surreal<C>, entries: Vec<HashEntry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        db.query(UPSERT_ENTRIES_QUERY)
             .bind(("entries", entries))
-            .await?;
+            .await?
+            .check()?;
         Ok(())
     }
 
-    async fn sync_pull<C: surrealdb::Connection>(local_db: &Surreal<Db>, remote_db: &Surreal<C>, token: &str, user: &str, pass: &str) -> Result<()> {
-        Self::auth_remote(remote_db, token, user, pass).await?;
-        
-        let remote_entries: Vec<HashEntry> = remote_db.select("file_hashes").await?;
-        if !remote_entries.is_empty() {
-            let _ = local_db
-                .query("INSERT INTO file_hashes $entries ON DUPLICATE KEY UPDATE name = $after.name, folder = $after.folder, created_at = $after.created_at")
-                .bind(("entries", remote_entries))
-                .await?;
-        }
+    async fn save_sync_state(local_db: &Surreal<Db>, remote_key: &str, state: &SyncState) -> Result<()> {
+        let _: Option<SyncState> = local_db.upsert(("sync_state", remote_key)).content(state.clone()).await?;
         Ok(())
     }
+
+    // Copies one page from `source` to `target`, skipping rows the target already has at the
+    // same or a newer version. This also stops our own pushes from echoing back on pull.
+    // `conflict_since` is the target's previous high-water mark: a target row written after it
+    // with different content means both sides changed the hash since the last sync.
+    async fn transfer_batch<S: surrealdb::Connection, T: surrealdb::Connection>(source: &Surreal<S>, target: &Surreal<T>, since: Option<DateTime<Utc>>, after: String, limit: usize, settle: bool, conflict_since: Option<DateTime<Utc>>) -> Result<(Vec<HashEntry>, usize, usize)> {
+        let page = Self::changes_page(source, since, after, limit, settle).await?;
+        let current = Self::entries_by_hash(target, page.iter().map(|e| e.hash.clone()).collect()).await?;
+
+        let mut conflicts = 0;
+        let mut to_write = Vec::new();
+        for entry in &page {
+            match current.get(&entry.hash) {
+                Some(existing) => {
+                    let target_changed = conflict_since.is_none_or(|s| existing.sync_mark() > s);
+                    if target_changed && !entry.same_content(existing) {
+                        conflicts += 1;
+                    }
+                    if entry.modified_at() > existing.modified_at() {
+                        to_write.push(entry.clone());
+                    }
+                }
+                None => to_write.push(entry.clone()),
+            }
+        }
+
+        let written = to_write.len();
+        Self::write_entries(target, to_write).await?;
+        Ok((page, written, conflicts))
+    }
+
+    async fn sync_both<C: surrealdb::Connection>(local_db: &Surreal<Db>, remote_db: &Surreal<C>, remote_key: &str, mut state: SyncState, direction: SyncDirection, batch_size: usize, logger: &impl ProgressLogger) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
+        // Conflicts are judged against the marks as they were before this run
+        let pull_mark = state.last_pull;
+        let push_mark = state.last_push;
+
+        if direction != SyncDirection::Pull {
+            let total = Self::with_retry("Counting local changes", logger, || {
+                Self::count_changes(local_db, state.last_push, state.last_push_hash.clone(), false)
+            }).await?;
+            let mut done = 0;
+
+            while done < total {
+                let (page, written, conflicts) = Self::with_retry("Push batch", logger, || {
+                    Self::transfer_batch(local_db, remote_db, state.last_push, state.last_push_hash.clone(), batch_size, false, pull_mark)
+                }).await?;
+                let Some(last) = page.last() else { break };
+
+                report.pushed += written;
+                report.conflicts += conflicts;
+                done += page.len();
+                state.last_push = Some(last.sync_mark());
+                state.last_push_hash = last.hash.clone();
+                Self::save_sync_state(local_db, remote_key, &state).await?;
+
+                logger.progress(done.min(total), total, t("INFO_SYNC_PUSHED", &[("done", &done.min(total)), ("total", &total)]));
+            }
+        }
+
+        if direction != SyncDirection::Push {
+            let total = Self::with_retry("Counting remote changes", logger, || {
+                Self::count_changes(remote_db, state.last_pull, state.last_pull_hash.clone(), true)
+            }).await?;
+            let mut done = 0;
+
+            while done < total {
+                let (page, written, conflicts) = Self::with_retry("Pull batch", logger, || {
+                    Self::transfer_batch(remote_db, local_db, state.last_pull, state.last_pull_hash.clone(), batch_size, true, push_mark)
+                }).await?;
+                let Some(last) = page.last() else { break };
+
+                report.pulled += written;
+                // In a two-way sync both-side changes were already counted while pushing
+                if direction == SyncDirection::Pull {
+                    report.conflicts += conflicts;
+                }
+                done += page.len();
+                state.last_pull = Some(last.sync_mark());
+                state.last_pull_hash = last.hash.clone();
+                Self::save_sync_state(local_db, remote_key, &state).await?;
+
+                logger.progress(done.min(total), total, t("INFO_SYNC_PULLED", &[("done", &done.min(total)), ("total", &total)]));
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Background loop for periodic two-way sync. Re-reads the profile on every tick, and
+/// `changed` cuts the current wait short, so a new `auto_sync_minutes` or remote applies at once.
+pub async fn run_auto_sync(profile: Arc<RwLock<Profile>>, db: Arc<RwLock<Option<DatabaseManager>>>, changed: Arc<Notify>) {
+    loop {
+        let (minutes, url, token, user, pass, batch_size) = {
+            let p = profile.read().await;
+            let enabled = p.database_mode == DatabaseMode::Remote && !p.remote_db_url.is_empty();
+            let minutes = if enabled { p.auto_sync_minutes } else { 0 };
+            (minutes, p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone(), p.sync_batch_size)
+        };
+
+        let wait = if minutes == 0 { AUTO_SYNC_IDLE_SECS } else { minutes * 60 };
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(wait)) => {}
+            _ = changed.notified() => continue,
+        }
+
+        if minutes == 0 {
+            continue;
+        }
+
+        let db_manager = {
+            let db_lock = db.read().await;
+            db_lock.clone()
+        };
+
+        if let Some(db_manager) = db_manager {
+            match db_manager.sync_with_remote(&url, &token, &user, &pass, batch_size, &ConsoleLogger).await {
+                Ok(report) => info!("Auto-sync: {} pushed, {} pulled, {} conflicts", report.pushed, report.pulled, report.conflicts),
+                Err(e) => error!("Auto-sync failed: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn temp_db(name: &str) -> Surreal<Db> {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!("tapi-test-{}-{}-{}", name, std::process::id(), nanos));
+        let db: Surreal<Db> = Surreal::new::<SurrealKv>(path.to_str().unwrap()).await.unwrap();
+        db.use_ns("tapi").use_db("main").await.unwrap();
+        db
+    }
+
+    fn entry(hash: &str, updated_at: DateTime<Utc>) -> HashEntry {
+        HashEntry {
+            hash: hash.to_string(),
+            name: format!("{}.jpg", hash),
+            folder: "series".to_string(),
+            created_at: Some(updated_at),
+            updated_at: Some(updated_at),
+            deleted: false,
+            model: String::new(),
+            target_lang: String::new(),
+            synced_at: None,
+        }
+    }
+
+    async fn page_all(db: &Surreal<Db>, mut since: Option<DateTime<Utc>>, mut after: String, limit: usize) -> (Vec<String>, Option<DateTime<Utc>>, String) {
+        let mut seen = Vec::new();
+        loop {
+            let page = DatabaseManager::changes_page(db, since, after.clone(), limit, false).await.unwrap();
+            let Some(last) = page.last() else { break };
+            since = Some(last.sync_mark());
+            after = last.hash.clone();
+            seen.extend(page.into_iter().map(|e| e.hash));
+        }
+        (seen, since, after)
+    }
+
+    #[tokio::test]
+    async fn paging_visits_every_row_once() {
+        let db = temp_db("paging").await;
+        let now = Utc::now();
+        let entries: Vec<HashEntry> = ["e", "a", "d", "b", "c"].iter().map(|h| entry(h, now)).collect();
+        DatabaseManager::write_entries(&db, entries).await.unwrap();
+
+        let (mut seen, _, _) = page_all(&db, None, String::new(), 2).await;
+        seen.sort();
+        assert_eq!(seen, vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(DatabaseManager::count_changes(&db, None, String::new(), false).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn rows_written_late_with_old_timestamps_are_still_paged() {
+        let db = temp_db("late").await;
+        DatabaseManager::write_entries(&db, vec![entry("new", Utc::now())]).await.unwrap();
+        let (_, since, after) = page_all(&db, None, String::new(), 10).await;
+
+        // Another client pushes a row it translated long ago, after our cursor moved on
+        let old = Utc::now() - chrono::Duration::days(30);
+        DatabaseManager::write_entries(&db, vec![entry("old", old)]).await.unwrap();
+
+        let (seen, _, _) = page_all(&db, since, after, 10).await;
+        assert_eq!(seen, vec!["old"]);
+    }
+
+    #[tokio::test]
+    async fn settle_window_holds_back_fresh_writes() {
+        let db = temp_db("settle").await;
+        DatabaseManager::write_entries(&db, vec![entry("fresh", Utc::now())]).await.unwrap();
+
+        let settled = DatabaseManager::changes_page(&db, None, String::new(), 10, true).await.unwrap();
+        assert!(settled.is_empty());
+        assert_eq!(DatabaseManager::count_changes(&db, None, String::new(), true).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn replace_import_tombstones_missing_rows() {
+        let manager = DatabaseManager { db: temp_db("replace").await };
+        let now = Utc::now();
+        DatabaseManager::write_entries(&manager.db, vec![entry("kept", now), entry("dropped", now)]).await.unwrap();
+
+        let (written, skipped) = manager.import_entries(vec![entry("kept", now), entry("added", now)], ImportMode::Replace, 1).await.unwrap();
+        assert_eq!((written, skipped), (2, 0));
+
+        let rows = DatabaseManager::entries_by_hash(&manager.db, vec!["kept".into(), "dropped".into(), "added".into()]).await.unwrap();
+        assert!(!rows["kept"].deleted);
+        assert!(!rows["added"].deleted);
+        assert!(rows["dropped"].deleted);
+    }
+
+    #[tokio::test]
+    async fn synced_at_is_not_copied_between_databases() {
+        let source = temp_db("source").await;
+        let target = temp_db("target").await;
+        DatabaseManager::write_entries(&source, vec![entry("a", Utc::now())]).await.unwrap();
+        let page = DatabaseManager::changes_page(&source, None, String::new(), 10, false).await.unwrap();
+        let source_mark = page[0].sync_mark();
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        DatabaseManager::write_entries(&target, page).await.unwrap();
+        let copied = DatabaseManager::entries_by_hash(&target, vec!["a".to_string()]).await.unwrap();
+        assert!(copied["a"].sync_mark() > source_mark);
+    }
 }
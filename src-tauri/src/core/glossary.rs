@@ -0,0 +1,244 @@
+use crate::config::folder_config::FolderConfig;
+use crate::core::database::{DatabaseManager, ImportMode};
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+/// Written next to MangaDex downloads (see `commands::mangadex::save_mangadex_history`).
+pub const MANGADEX_INFO_FILE: &str = "mangadex_info.json";
+
+/// One glossary entry: how `source` must come out in translations of the series.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GlossaryTerm {
+    /// Series key, see `series_key`
+    #[serde(default)]
+    pub series: String,
+    pub source: String,
+    pub target: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// "One_Piece", "one piece" and "One Piece!" are the same series: folder names are
+/// sanitized differently by the downloaders than the names the sites report.
+pub fn series_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Deserialize)]
+struct MangadexInfo {
+    series_name: Option<String>,
+}
+
+/// The series a folder belongs to: `series` in `.tapi.toml`, else the series name of the
+/// nearest MangaDex download info (the folder or up to two levels up), else the folder
+/// structure — a folder holding images is a chapter of its parent, anything else is the series.
+pub fn find_series(folder: &Path) -> Option<String> {
+    if let Ok(Some(config)) = FolderConfig::load(folder) {
+        if let Some(series) = config.series.filter(|s| !s.trim().is_empty()) {
+            return Some(series.trim().to_string());
+        }
+    }
+
+    for dir in folder.ancestors().take(3) {
+        let Ok(content) = fs::read_to_string(dir.join(MANGADEX_INFO_FILE)) else { continue };
+        if let Ok(MangadexInfo { series_name: Some(name) }) = serde_json::from_str(&content) {
+            if !name.trim().is_empty() {
+                return Some(name.trim().to_string());
+            }
+        }
+    }
+
+    let has_images = fs::read_dir(folder).ok()?
+        .filter_map(|e| e.ok())
+        .any(|e| e.path().extension()
+            .map(|ext| ["jpg", "jpeg", "png", "webp"].contains(&ext.to_string_lossy().to_lowercase().as_str()))
+            .unwrap_or(false));
+    let dir = if has_images { folder.parent().unwrap_or(folder) } else { folder };
+    dir.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+/// The terms of one series, ready to send or apply.
+#[derive(Debug, Clone, Default)]
+pub struct Glossary {
+    pub series: String,
+    pub terms: Vec<GlossaryTerm>,
+}
+
+impl Glossary {
+    pub async fn load(db: &DatabaseManager, series: &str) -> Result<Self> {
+        let key = series_key(series);
+        let terms = db.list_glossary(&key).await?;
+        Ok(Self { series: key, terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The glossary as the `context` part of a translate request.
+    pub fn context(&self) -> String {
+        let terms: Vec<serde_json::Value> = self.terms.iter()
+            .map(|t| serde_json::json!({ "source": t.source, "target": t.target, "notes": t.notes }))
+            .collect();
+        serde_json::json!({ "series": self.series, "glossary": terms }).to_string()
+    }
+
+    /// Post-translation enforcement for machine translations we get back as text (OCR
+    /// results): every occurrence of a source term is replaced by its target. Case-insensitive,
+    /// whole words only for terms that start/end with a letter or digit. One pass over the
+    /// text, so a target is never rewritten by another term; where terms overlap the longest
+    /// one wins.
+    pub fn apply(&self, text: &str) -> String {
+        let mut terms: Vec<&GlossaryTerm> = self.terms.iter().filter(|t| !t.source.trim().is_empty()).collect();
+        if terms.is_empty() {
+            return text.to_string();
+        }
+        // Alternatives are tried in order, so longer terms have to come first
+        terms.sort_by_key(|t| std::cmp::Reverse(t.source.trim().chars().count()));
+
+        let alternatives: Vec<String> = terms.iter().map(|term| {
+            let source = term.source.trim();
+            let start = if source.starts_with(char::is_alphanumeric) { r"\b" } else { "" };
+            let end = if source.ends_with(char::is_alphanumeric) { r"\b" } else { "" };
+            format!("({}{}{})", start, regex::escape(source), end)
+        }).collect();
+        let Ok(re) = regex::Regex::new(&format!("(?i){}", alternatives.join("|"))) else {
+            return text.to_string();
+        };
+        re.replace_all(text, |caps: &regex::Captures| {
+            // Group i + 1 is terms[i]
+            let i = (1..caps.len()).find(|&g| caps.get(g).is_some()).unwrap_or(1) - 1;
+            terms[i].target.trim().to_string()
+        }).into_owned()
+    }
+}
+
+/// The series' glossary for a run over `folder`, or None when there is no database, no
+/// detectable series or no terms.
+pub async fn load_for_folder(logger: &impl ProgressLogger, db: Option<&DatabaseManager>, folder: &Path) -> Option<Glossary> {
+    let db = db?;
+    let series = find_series(folder)?;
+    match Glossary::load(db, &series).await {
+        Ok(glossary) if !glossary.is_empty() => {
+            logger.log(t("INFO_GLOSSARY_LOADED", &[("series", &series), ("count", &glossary.terms.len())]));
+            Some(glossary)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            logger.log(t("ERROR_GLOSSARY_READ", &[("series", &series), ("error", &e)]));
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CsvTerm {
+    source: String,
+    target: String,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// Reads `source,target,notes` rows (header required, notes optional).
+pub fn read_csv<R: Read>(reader: R, series: &str) -> Result<Vec<GlossaryTerm>> {
+    let key = series_key(series);
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let mut terms = Vec::new();
+    for (line, row) in rdr.deserialize::<CsvTerm>().enumerate() {
+        // +2: header line and 1-based numbering
+        let row = row.map_err(|e| anyhow!("Invalid glossary row {}: {}", line + 2, e))?;
+        if row.source.is_empty() || row.target.is_empty() {
+            continue;
+        }
+        terms.push(GlossaryTerm { series: key.clone(), source: row.source, target: row.target, notes: row.notes.filter(|n| !n.is_empty()) });
+    }
+    Ok(terms)
+}
+
+pub fn write_csv<W: Write>(terms: &[GlossaryTerm], writer: W) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for term in terms {
+        wtr.serialize(CsvTerm { source: term.source.clone(), target: term.target.clone(), notes: term.notes.clone() })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Loads a CSV into the series' glossary. `Replace` drops the existing terms first.
+pub async fn import_from_reader<R: Read>(db: &DatabaseManager, series: &str, reader: R, mode: ImportMode) -> Result<usize> {
+    let terms = read_csv(reader, series)?;
+    if mode == ImportMode::Replace {
+        db.clear_glossary(&series_key(series)).await?;
+    }
+    for term in &terms {
+        db.save_glossary_term(term).await?;
+    }
+    Ok(terms.len())
+}
+
+pub async fn import_from_file(db: &DatabaseManager, series: &str, path: &Path, mode: ImportMode) -> Result<usize> {
+    import_from_reader(db, series, fs::File::open(path)?, mode).await
+}
+
+pub async fn export_to_file(db: &DatabaseManager, series: &str, path: &Path) -> Result<usize> {
+    let terms = db.list_glossary(&series_key(series)).await?;
+    write_csv(&terms, fs::File::create(path)?)?;
+    Ok(terms.len())
+}
+
+/// The glossary to send with a translation run over `folder`. Image-to-image translation can
+/// only honour it through the model, so nothing is sent when the model takes no context.
+pub async fn for_translation_run(logger: &impl ProgressLogger, db: Option<&DatabaseManager>, folder: &Path, model_takes_context: bool) -> Option<std::sync::Arc<Glossary>> {
+    let glossary = load_for_folder(logger, db, folder).await?;
+    if !model_takes_context {
+        logger.log(t("INFO_GLOSSARY_NO_CONTEXT", &[]));
+        return None;
+    }
+    Some(std::sync::Arc::new(glossary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary(terms: &[(&str, &str)]) -> Glossary {
+        Glossary {
+            series: "test".to_string(),
+            terms: terms.iter().map(|(source, target)| GlossaryTerm { source: source.to_string(), target: target.to_string(), ..Default::default() }).collect(),
+        }
+    }
+
+    #[test]
+    fn replaces_whole_words_case_insensitively() {
+        let g = glossary(&[("Luffy", "Rufi")]);
+        assert_eq!(g.apply("luffy and LUFFY, not Luffys"), "Rufi and Rufi, not Luffys");
+    }
+
+    #[test]
+    fn targets_are_not_rewritten_by_other_terms() {
+        // Sequential replacement would turn "Zoro" into "Zorro" and then into "Zorrox"
+        let g = glossary(&[("Zoro", "Zorro"), ("Zorro", "Zorrox")]);
+        assert_eq!(g.apply("Zoro met Zorro"), "Zorro met Zorrox");
+    }
+
+    #[test]
+    fn the_longest_overlapping_term_wins() {
+        let g = glossary(&[("Straw Hat", "Mugiwara"), ("Straw Hat Pirates", "Mugiwara Crew")]);
+        assert_eq!(g.apply("The Straw Hat Pirates and a straw hat"), "The Mugiwara Crew and a Mugiwara");
+    }
+
+    #[test]
+    fn terms_without_word_characters_at_the_edges_match_anywhere() {
+        let g = glossary(&[("-san", "")]);
+        assert_eq!(g.apply("Nami-san!"), "Nami!");
+    }
+}
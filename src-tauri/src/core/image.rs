@@ -5,6 +5,15 @@ use walkdir::{WalkDir, DirEntry};
 use image::{DynamicImage, GenericImageView};
 use anyhow::Result;
 
+/// Whether `path`, found under `root`, is one of `includes` or inside one. The app sends the
+/// absolute paths of the checked items; `--include` takes paths relative to the input folder.
+pub fn is_included(path: &Path, root: &Path, includes: &[String]) -> bool {
+    let relative = path.strip_prefix(root).ok();
+    includes.iter().map(Path::new).any(|inc| {
+        path.starts_with(inc) || relative.is_some_and(|r| r.starts_with(inc))
+    })
+}
+
 pub fn find_all_images(path: &Path) -> Vec<PathBuf> {
     let mut images = Vec::new();
     
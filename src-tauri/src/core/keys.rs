@@ -0,0 +1,163 @@
+use crate::config::profile::{ApiKeyEntry, KeyStrategy, Profile};
+use crate::core::database::DatabaseManager;
+use chrono::Utc;
+use std::sync::Mutex;
+use anyhow::Result;
+
+/// Label of the single `api_key` when a profile has no key pool.
+pub const DEFAULT_KEY_LABEL: &str = "default";
+
+/// Usage is bucketed by UTC day, `YYYY-MM-DD`.
+pub fn usage_day() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// A key handed out for one request.
+#[derive(Debug, Clone)]
+pub struct SelectedKey {
+    pub index: usize,
+    pub label: String,
+    pub key: String,
+}
+
+struct PoolKey {
+    label: String,
+    key: String,
+    daily_budget: Option<u64>,
+    used_today: u64,
+    rejected: bool,
+}
+
+impl PoolKey {
+    fn remaining(&self) -> Option<u64> {
+        self.daily_budget.map(|b| b.saturating_sub(self.used_today))
+    }
+
+    fn usable(&self, cost: u64) -> bool {
+        !self.rejected && self.remaining().is_none_or(|left| left >= cost)
+    }
+}
+
+struct PoolState {
+    keys: Vec<PoolKey>,
+    // Next key to try for round-robin
+    cursor: usize,
+}
+
+/// The keys of one run. Every strategy skips keys that were rejected during the run
+/// and keys whose daily budget would be exceeded by the next page.
+pub struct KeyPool {
+    strategy: KeyStrategy,
+    day: String,
+    state: Mutex<PoolState>,
+}
+
+impl KeyPool {
+    pub fn new(entries: &[ApiKeyEntry], strategy: KeyStrategy) -> Self {
+        let keys = entries.iter()
+            .filter(|e| e.enabled && !e.key.trim().is_empty())
+            .map(|e| PoolKey {
+                label: e.label.clone(),
+                key: e.key.trim().to_string(),
+                daily_budget: e.daily_budget,
+                used_today: 0,
+                rejected: false,
+            })
+            .collect();
+        Self {
+            strategy,
+            day: usage_day(),
+            state: Mutex::new(PoolState { keys, cursor: 0 }),
+        }
+    }
+
+    /// A pool holding just `api_key` (CLI flag, env var or a profile without a key list).
+    pub fn single(api_key: &str) -> Self {
+        let entry = ApiKeyEntry {
+            label: DEFAULT_KEY_LABEL.to_string(),
+            key: api_key.to_string(),
+            daily_budget: None,
+            enabled: true,
+        };
+        Self::new(&[entry], KeyStrategy::RoundRobin)
+    }
+
+    /// The profile's key pool, or `fallback_key` when the profile has none.
+    pub fn from_profile(profile: &Profile, fallback_key: &str) -> Self {
+        let pool = Self::new(&profile.api_keys, profile.key_strategy);
+        if pool.is_empty() {
+            Self::single(fallback_key)
+        } else {
+            pool
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().map(|s| s.keys.is_empty()).unwrap_or(true)
+    }
+
+    /// Seeds today's usage from the database, so budgets count earlier runs too.
+    pub async fn load_usage(&self, db: &DatabaseManager) -> Result<()> {
+        let rows = db.list_key_usage(Some(self.day.clone())).await?;
+        if let Ok(mut state) = self.state.lock() {
+            for key in state.keys.iter_mut() {
+                key.used_today = rows.iter()
+                    .filter(|r| r.day == self.day && r.label == key.label)
+                    .map(|r| r.credits)
+                    .sum();
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the key for a page costing `cost` credits, None when no key can take it.
+    pub fn select(&self, cost: u64) -> Option<SelectedKey> {
+        let mut state = self.state.lock().ok()?;
+        let count = state.keys.len();
+        let index = match self.strategy {
+            KeyStrategy::RoundRobin => {
+                let start = state.cursor;
+                let index = (0..count).map(|i| (start + i) % count).find(|&i| state.keys[i].usable(cost))?;
+                state.cursor = (index + 1) % count;
+                index
+            }
+            KeyStrategy::Failover => (0..count).find(|&i| state.keys[i].usable(cost))?,
+            KeyStrategy::DailyBudget => {
+                let usable: Vec<usize> = (0..count).filter(|&i| state.keys[i].usable(cost)).collect();
+                usable.iter().copied()
+                    .filter(|&i| state.keys[i].daily_budget.is_some())
+                    .max_by_key(|&i| (state.keys[i].remaining(), std::cmp::Reverse(i)))
+                    .or_else(|| usable.first().copied())?
+            }
+        };
+        let key = &state.keys[index];
+        Some(SelectedKey { index, label: key.label.clone(), key: key.key.clone() })
+    }
+
+    /// True while some key can still take a page costing `cost`.
+    pub fn has_usable(&self, cost: u64) -> bool {
+        self.state.lock().map(|s| s.keys.iter().any(|k| k.usable(cost))).unwrap_or(false)
+    }
+
+    /// Counts credits spent with a key in this run.
+    pub fn record(&self, index: usize, credits: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(key) = state.keys.get_mut(index) {
+                key.used_today += credits;
+            }
+        }
+    }
+
+    /// Takes a key out of rotation for the rest of the run.
+    pub fn reject(&self, index: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(key) = state.keys.get_mut(index) {
+                key.rejected = true;
+            }
+        }
+    }
+
+    pub fn day(&self) -> &str {
+        &self.day
+    }
+}
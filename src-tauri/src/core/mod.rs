@@ -3,4 +3,14 @@ pub mod api;
 pub mod archive;
 pub mod processor;
 pub mod pdf;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod backup;
+pub mod reconcile;
+pub mod keys;
+pub mod report;
+pub mod pricing;
+pub mod catalog;
+pub mod storage;
+pub mod ocr;
+pub mod script;
+pub mod glossary;
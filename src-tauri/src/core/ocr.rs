@@ -0,0 +1,368 @@
+use crate::config::autosave::{ProfileAutosave, AUTOSAVE_INTERVAL};
+use crate::core::api::KeyRejected;
+use crate::core::image::{find_all_images, is_included};
+use crate::core::keys::KeyPool;
+use crate::core::pricing::{CostEstimate, OCR_PAGE_COST};
+use crate::core::processor::{record_charge, TranslationOptions};
+use crate::core::glossary::Glossary;
+use crate::core::report::{PageReport, PageStatus, RunReport};
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::{ProgressEvent, ProgressTracker, Stage};
+use tracing::{info_span, warn, Instrument};
+use crate::utils::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+/// Where per-page JSON goes inside the output folder.
+pub const OCR_PAGES_DIR: &str = "pages";
+
+/// Pixel rectangle of a text block, top-left origin.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct BoundingBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// One recognized text region (usually a speech bubble).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TextBlock {
+    pub text: String,
+    pub bbox: Option<BoundingBox>,
+    pub language: Option<String>,
+    /// 0.0 - 1.0 when the provider sends it
+    pub confidence: Option<f32>,
+    /// Machine translation, when the OCR response carries one
+    #[serde(default)]
+    pub translation: Option<String>,
+}
+
+/// OCR result for one image. The OCR endpoint answers with this shape (without `file`):
+/// `{"width"?, "height"?, "language"?, "blocks": [{"text", "bbox"?: {"x", "y", "width", "height"},
+/// "language"?, "confidence"?, "translation"?}]}`, and it is what the per-page JSON files hold.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OcrPage {
+    /// Image path relative to the processed folder; set by the caller
+    #[serde(default)]
+    pub file: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Dominant language of the page
+    pub language: Option<String>,
+    pub blocks: Vec<TextBlock>,
+}
+
+impl OcrPage {
+    /// Parses an OCR response. Blocks without text are dropped.
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let mut page: OcrPage = serde_json::from_value(value.clone())
+            .map_err(|e| anyhow!("Unexpected OCR response ({}): {}", e, value))?;
+        page.file = String::new();
+        page.blocks.retain(|b| !b.text.trim().is_empty());
+        for block in &mut page.blocks {
+            block.text = block.text.trim().to_string();
+            block.translation = block.translation.take().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        // Fall back to the first block's language when the page has none
+        if page.language.is_none() {
+            page.language = page.blocks.iter().find_map(|b| b.language.clone());
+        }
+        Ok(page)
+    }
+
+    pub fn text(&self) -> String {
+        self.blocks.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Layout of the aggregated chapter file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    Plain,
+    Markdown,
+    Tsv,
+}
+
+impl std::str::FromStr for TextFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "plain" | "txt" | "text" => Ok(TextFormat::Plain),
+            "markdown" | "md" => Ok(TextFormat::Markdown),
+            "tsv" => Ok(TextFormat::Tsv),
+            other => Err(anyhow!("Unknown text format '{}' (expected plain, markdown or tsv)", other)),
+        }
+    }
+}
+
+impl TextFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TextFormat::Plain => "txt",
+            TextFormat::Markdown => "md",
+            TextFormat::Tsv => "tsv",
+        }
+    }
+}
+
+// TSV cells cannot hold tabs or line breaks
+fn tsv_cell(s: &str) -> String {
+    s.replace(['\t', '\r'], " ").replace('\n', "\\n")
+}
+
+/// One text file for a chapter's pages, in page order.
+pub fn render_chapter(title: &str, pages: &[OcrPage], format: TextFormat) -> String {
+    let mut out = String::new();
+    let page_name = |p: &OcrPage| Path::new(&p.file).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| p.file.clone());
+    match format {
+        TextFormat::Plain => {
+            for page in pages {
+                out.push_str(&format!("=== {} ===\n", page_name(page)));
+                for block in &page.blocks {
+                    out.push_str(&block.text);
+                    out.push('\n');
+                }
+                out.push('\n');
+            }
+        }
+        TextFormat::Markdown => {
+            out.push_str(&format!("# {}\n\n", title));
+            for page in pages {
+                out.push_str(&format!("## {}\n\n", page_name(page)));
+                if page.blocks.is_empty() {
+                    out.push_str("_No text_\n\n");
+                    continue;
+                }
+                for (i, block) in page.blocks.iter().enumerate() {
+                    // Keep multi-line bubbles inside their list item
+                    out.push_str(&format!("{}. {}\n", i + 1, block.text.replace('\n', "  \n   ")));
+                }
+                out.push('\n');
+            }
+        }
+        TextFormat::Tsv => {
+            out.push_str("page\tblock\tx\ty\twidth\theight\tlanguage\tconfidence\ttext\n");
+            for page in pages {
+                for (i, block) in page.blocks.iter().enumerate() {
+                    let b = block.bbox.unwrap_or_default();
+                    out.push_str(&format!(
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                        tsv_cell(&page_name(page)), i + 1, b.x, b.y, b.width, b.height,
+                        block.language.as_deref().or(page.language.as_deref()).unwrap_or(""),
+                        block.confidence.map(|c| format!("{:.3}", c)).unwrap_or_default(),
+                        tsv_cell(&block.text),
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Runs OCR over every image under `input_dir` without translating anything. Each page's
+/// result is written to `<output_dir>/pages/<relative path>.json` (`01.jpg.json`), and each
+/// chapter (image folder) gets `<output_dir>/<relative folder>/<folder name>.<ext>` with all its
+/// text. Pages that already have their JSON are not sent again but still go into the chapter
+/// file. Caps, key pool and reporting work as for translation runs, at `OCR_PAGE_COST` a page
+/// unless the provider reports the charge.
+pub async fn process_ocr_directory(
+    logger: &impl ProgressLogger,
+    input_dir: &Path,
+    output_dir: &Path,
+    format: TextFormat,
+    glossary: Option<&Glossary>,
+    options: &TranslationOptions,
+) -> Result<RunReport> {
+    let mut images = find_all_images(input_dir);
+    // Never OCR our own output when it sits inside the input folder
+    images.retain(|img| !img.starts_with(output_dir));
+    if let Some(ref includes) = options.included_paths {
+        images.retain(|img| is_included(img, input_dir, includes));
+    }
+    images.sort();
+
+    let mut report = RunReport::default();
+    if images.is_empty() {
+        logger.log(t("ERROR_NO_IMAGES_IN_DIR", &[]));
+        return Ok(report);
+    }
+
+    let page_json = |relative: &Path| {
+        let mut name = relative.as_os_str().to_os_string();
+        name.push(".json");
+        output_dir.join(OCR_PAGES_DIR).join(name)
+    };
+    let cost = OCR_PAGE_COST;
+    let to_send = images.iter()
+        .filter(|img| !page_json(img.strip_prefix(input_dir).unwrap_or(img)).is_file())
+        .count();
+    let estimate = CostEstimate { model: "ocr".to_string(), cost_per_image: cost, images: to_send, credits: cost * to_send as u64, known_price: false };
+    report.estimate = Some(estimate.clone());
+    logger.log(t("INFO_TOTAL_COST", &[("count", &estimate.images), ("cost_per_image", &cost), ("total_cost", &estimate.credits)]));
+
+    let db_manager = match options.db {
+        Some(ref db_rwlock) => db_rwlock.read().await.clone(),
+        None => None,
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = options.budget.load_today(db).await {
+            logger.log(t("WARN_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+
+    if options.dry_run {
+        logger.log(t("INFO_DRY_RUN", &[]));
+        return Ok(report);
+    }
+    fs::create_dir_all(output_dir)?;
+
+    let client = options.client();
+    let keys = match &options.profile {
+        Some(profile_rwlock) => KeyPool::from_profile(&*profile_rwlock.read().await, &options.api_key),
+        None => KeyPool::single(&options.api_key),
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = keys.load_usage(db).await {
+            logger.log(t("WARN_KEY_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+    let mut autosave = options.profile.clone().map(|p| ProfileAutosave::new(p, AUTOSAVE_INTERVAL));
+
+    let total = images.len();
+    let mut tracker = ProgressTracker::new(total);
+    let mut chapters: BTreeMap<PathBuf, Vec<OcrPage>> = BTreeMap::new();
+    for (i, img_path) in images.iter().enumerate() {
+        let relative = img_path.strip_prefix(input_dir).unwrap_or(img_path).to_path_buf();
+        let json_path = page_json(&relative);
+        let chapter = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+        logger.progress(i + 1, total, t("INFO_OCR_PROGRESS", &[("i", &(i + 1)), ("total", &total), ("filename", &relative.display())]));
+
+        if let Some(page) = fs::read_to_string(&json_path).ok().and_then(|s| serde_json::from_str::<OcrPage>(&s).ok()) {
+            report.skipped += 1;
+            chapters.entry(chapter).or_default().push(page);
+            continue;
+        }
+
+        if let Err(reason) = options.budget.check(cost) {
+            logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+            report.stopped = Some(reason);
+            break;
+        }
+
+        let started = std::time::Instant::now();
+        let mut page_report = PageReport::new(img_path);
+        let bytes_sent = fs::metadata(img_path).map(|m| m.len()).unwrap_or(0);
+        logger.event(ProgressEvent::file_stage(Stage::Uploading, img_path));
+        let file_span = info_span!("file", file = %img_path.display());
+        // Rejected keys (401/402/403) are dropped and the page goes to the next one
+        let result = async {
+            loop {
+                let Some(selected) = keys.select(cost) else {
+                    break Err(anyhow!(t("REASON_NO_KEYS", &[])));
+                };
+                page_report.key = Some(selected.label.clone());
+                match client.with_api_key(&selected.key).call_ocr(img_path).await {
+                    Err(e) if e.downcast_ref::<KeyRejected>().is_some() => {
+                        logger.log(t("WARN_KEY_REJECTED", &[("key", &selected.label), ("error", &e)]));
+                        keys.reject(selected.index);
+                    }
+                    result => break result.map(|recognized| (recognized, selected)),
+                }
+            }
+        }.instrument(file_span).await;
+
+        match result {
+            Ok((recognized, selected)) => {
+                record_charge(options, &keys, &mut autosave, &selected, recognized.credits, cost, &mut page_report, &mut report).await;
+                let mut page = recognized.page;
+                page.file = relative.to_string_lossy().replace('\\', "/");
+                // Translations that came with the OCR result follow the series' terms
+                if let Some(glossary) = glossary {
+                    for translation in page.blocks.iter_mut().filter_map(|b| b.translation.as_mut()) {
+                        *translation = glossary.apply(translation);
+                    }
+                }
+                if let Some(parent) = json_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                logger.event(ProgressEvent::file_stage(Stage::Saving, img_path));
+                fs::write(&json_path, serde_json::to_string_pretty(&page)?)?;
+                page_report.status = PageStatus::Translated;
+                chapters.entry(chapter).or_default().push(page);
+            }
+            Err(e) => {
+                warn!("OCR ERROR {:?}: {}", img_path, e);
+                logger.log(t("ERROR_OCR", &[("filename", &relative.display()), ("error", &e)]));
+                page_report.error = Some(e.to_string());
+                if !keys.has_usable(cost) {
+                    debug::record_timing(img_path, started.elapsed(), page_report.error.as_deref().unwrap_or("recognized"));
+                    logger.event(page_report.file_done(img_path, started.elapsed(), bytes_sent, 0));
+                    report.pages.push(page_report);
+                    let reason = t("REASON_NO_KEYS", &[]);
+                    logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+                    report.stopped = Some(reason);
+                    report.keys_exhausted = true;
+                    break;
+                }
+            }
+        }
+        debug::record_timing(img_path, started.elapsed(), page_report.error.as_deref().unwrap_or("recognized"));
+        logger.event(page_report.file_done(img_path, started.elapsed(), bytes_sent, 0));
+        logger.event(tracker.file_done(bytes_sent));
+        report.pages.push(page_report);
+    }
+
+    if let Some(ref mut autosave) = autosave {
+        autosave.flush().await;
+    }
+
+    for (chapter, pages) in &chapters {
+        let title = chapter.file_name()
+            .or_else(|| input_dir.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "chapter".to_string());
+        let dir = output_dir.join(chapter);
+        fs::create_dir_all(&dir)?;
+        let text_path = dir.join(format!("{}.{}", sanitize_filename::sanitize(&title), format.extension()));
+        fs::write(&text_path, render_chapter(&title, pages, format))?;
+        logger.log(t("INFO_CHAPTER_WRITTEN", &[("path", &text_path.display()), ("count", &pages.len())]));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_documented_response() {
+        let json = serde_json::json!({
+            "width": 800,
+            "height": 1200,
+            "blocks": [
+                {"text": " Hello ", "bbox": {"x": 1.0, "y": 2.0, "width": 30.0, "height": 40.0}, "language": "ja", "confidence": 0.9},
+                {"text": "  "}
+            ]
+        });
+        let page = OcrPage::from_json(&json).unwrap();
+        assert_eq!(page.blocks.len(), 1);
+        assert_eq!(page.blocks[0].text, "Hello");
+        assert_eq!(page.blocks[0].bbox, Some(BoundingBox { x: 1.0, y: 2.0, width: 30.0, height: 40.0 }));
+        assert_eq!(page.language.as_deref(), Some("ja"));
+    }
+
+    #[test]
+    fn rejects_other_shapes() {
+        assert!(OcrPage::from_json(&serde_json::json!({"text_blocks": []})).is_err());
+        assert!(OcrPage::from_json(&serde_json::json!([{"text": "Hello"}])).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::language::t;
+use crate::config::profile::Profile;
+use crate::core::catalog::Catalog;
+use crate::core::database::DatabaseManager;
+use crate::core::keys::usage_day;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use anyhow::Result;
+
+/// Credits per image for models the pricing table does not list.
+pub const DEFAULT_MODEL_COST: u64 = 1;
+/// Credits per OCR page assumed for the caps when the provider does not report the charge.
+pub const OCR_PAGE_COST: u64 = 1;
+
+/// Credits per image, by model name.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    costs: BTreeMap<String, u64>,
+}
+
+impl PricingTable {
+    /// Prices of the bundled catalog, for when nothing better is known.
+    pub fn builtin() -> Self {
+        Self::from_catalog(&Catalog::bundled())
+    }
+
+    pub fn from_catalog(catalog: &Catalog) -> Self {
+        Self {
+            costs: catalog.models.iter().map(|m| (m.name.clone(), m.cost)).collect(),
+        }
+    }
+
+    /// The profile's `model_costs` (or the config file) override the catalog's prices.
+    pub fn with_overrides(mut self, profile: &Profile) -> Self {
+        self.costs.extend(profile.model_costs.iter().map(|(m, c)| (m.clone(), *c)));
+        self
+    }
+
+    pub fn cost(&self, model: &str) -> u64 {
+        self.costs.get(model).copied().unwrap_or(DEFAULT_MODEL_COST)
+    }
+
+    pub fn is_known(&self, model: &str) -> bool {
+        self.costs.contains_key(model)
+    }
+}
+
+/// Dry-run result: what a run would send and what it would cost.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CostEstimate {
+    pub model: String,
+    pub cost_per_image: u64,
+    /// Images left after skipping existing outputs and known hashes
+    pub images: usize,
+    pub credits: u64,
+    /// False when the model has no entry in the pricing table and the default cost was assumed
+    pub known_price: bool,
+}
+
+impl CostEstimate {
+    pub fn new(pricing: &PricingTable, model: &str, images: usize) -> Self {
+        let cost_per_image = pricing.cost(model);
+        Self {
+            model: model.to_string(),
+            cost_per_image,
+            images,
+            credits: cost_per_image * images as u64,
+            known_price: pricing.is_known(model),
+        }
+    }
+
+    pub fn merge(&mut self, other: &CostEstimate) {
+        if self.model.is_empty() {
+            self.model = other.model.clone();
+            self.cost_per_image = other.cost_per_image;
+            self.known_price = other.known_price;
+        }
+        self.images += other.images;
+        self.credits += other.credits;
+    }
+}
+
+struct BudgetState {
+    spent_run: u64,
+    // Credits already spent today (all keys), loaded from the database once per run
+    spent_today: Option<u64>,
+}
+
+/// Hard credit caps for one run. The processor asks before every page and stops
+/// once the next page would go over either limit.
+pub struct CreditBudget {
+    pub per_run: Option<u64>,
+    pub per_day: Option<u64>,
+    state: Mutex<BudgetState>,
+}
+
+impl CreditBudget {
+    pub fn new(per_run: Option<u64>, per_day: Option<u64>) -> Self {
+        Self {
+            per_run,
+            per_day,
+            state: Mutex::new(BudgetState { spent_run: 0, spent_today: None }),
+        }
+    }
+
+    pub fn from_profile(profile: &Profile) -> Self {
+        Self::new(profile.max_credits_per_run, profile.max_credits_per_day)
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(None, None)
+    }
+
+    /// Reads today's spending from the per-key usage table (first call only). Until it has
+    /// succeeded, a daily limit refuses every page: spending from earlier runs is unknown.
+    pub async fn load_today(&self, db: &DatabaseManager) -> Result<()> {
+        if self.per_day.is_none() || self.state.lock().map(|s| s.spent_today.is_some()).unwrap_or(true) {
+            return Ok(());
+        }
+        let today = usage_day();
+        let spent: u64 = db.list_key_usage(Some(today.clone())).await?
+            .iter()
+            .filter(|r| r.day == today)
+            .map(|r| r.credits)
+            .sum();
+        if let Ok(mut state) = self.state.lock() {
+            // Usage recorded during this run is already in the table
+            state.spent_today = Some(spent.saturating_sub(state.spent_run));
+        }
+        Ok(())
+    }
+
+    /// Ok when a page costing `cost` fits both limits, otherwise the reason it does not.
+    pub fn check(&self, cost: u64) -> std::result::Result<(), String> {
+        let state = self.state.lock().map_err(|_| "budget state poisoned".to_string())?;
+        if let Some(limit) = self.per_run {
+            if state.spent_run + cost > limit {
+                return Err(t("REASON_RUN_BUDGET", &[("limit", &limit), ("spent", &state.spent_run)]));
+            }
+        }
+        if let Some(limit) = self.per_day {
+            let Some(spent_today) = state.spent_today else {
+                return Err(t("REASON_DAY_BUDGET_NO_DB", &[("limit", &limit)]));
+            };
+            let today = spent_today + state.spent_run;
+            if today + cost > limit {
+                return Err(t("REASON_DAY_BUDGET", &[("limit", &limit), ("spent", &today)]));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn spend(&self, cost: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.spent_run += cost;
+        }
+    }
+
+    /// Credits left under the tighter of the two limits, None when unlimited.
+    pub fn remaining(&self) -> Option<u64> {
+        let state = self.state.lock().ok()?;
+        let run = self.per_run.map(|l| l.saturating_sub(state.spent_run));
+        let day = self.per_day.map(|l| l.saturating_sub(state.spent_today.unwrap_or(0) + state.spent_run));
+        match (run, day) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded(budget: CreditBudget, spent_today: u64) -> CreditBudget {
+        budget.state.lock().unwrap().spent_today = Some(spent_today);
+        budget
+    }
+
+    #[test]
+    fn run_budget_stops_before_the_page_that_would_exceed_it() {
+        let budget = CreditBudget::new(Some(10), None);
+        assert!(budget.check(4).is_ok());
+        budget.spend(4);
+        budget.spend(4);
+        assert_eq!(budget.remaining(), Some(2));
+        assert!(budget.check(2).is_ok());
+        assert!(budget.check(3).is_err());
+    }
+
+    #[test]
+    fn daily_budget_fails_closed_until_todays_spending_is_known() {
+        let budget = CreditBudget::new(None, Some(100));
+        assert!(budget.check(1).is_err());
+
+        let budget = loaded(budget, 90);
+        assert!(budget.check(10).is_ok());
+        budget.spend(5);
+        assert_eq!(budget.remaining(), Some(5));
+        assert!(budget.check(6).is_err());
+    }
+
+    #[test]
+    fn unlimited_budget_never_refuses() {
+        let budget = CreditBudget::unlimited();
+        budget.spend(u32::MAX as u64);
+        assert!(budget.check(1_000).is_ok());
+        assert_eq!(budget.remaining(), None);
+    }
+}
@@ -1,16 +1,27 @@
-use crate::core::image::{find_all_images,  save_image_with_limit};
-use crate::core::api::{ApiClient, ApiEndpoints};
-
-
-use crate::utils::logger::{ProgressLogger, log_debug};
+use crate::core::image::{find_all_images, is_included, save_image_with_limit};
+use crate::core::api::{ApiClient, ApiEndpoints, CreditInfo, KeyRejected};
+use crate::core::keys::{KeyPool, SelectedKey};
+use crate::core::pricing::{CostEstimate, CreditBudget, PricingTable};
+use crate::core::report::{PageReport, PageStatus, RunReport};
+use crate::core::glossary::Glossary;
+use crate::core::catalog::preflight;
+
+
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::{file_name, ProgressEvent, ProgressTracker, Stage};
+use tracing::{debug, info_span, warn, Instrument};
+use crate::utils::debug;
 use std::path::{Path, PathBuf};
 
 use std::fs;
 use std::collections::HashSet;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use crate::config::profile::Profile;
+use crate::config::profile::{DatabaseMode, Profile};
+use crate::config::autosave::{ProfileAutosave, AUTOSAVE_INTERVAL};
+use crate::core::database::DatabaseManager;
 
 pub struct TranslationOptions {
     pub model: String,
@@ -24,37 +35,107 @@ pub struct TranslationOptions {
     pub profile: Option<Arc<RwLock<Profile>>>,
     pub endpoints: Option<ApiEndpoints>,
     pub included_paths: Option<Vec<String>>,
-    pub db: Option<Arc<RwLock<Option<crate::core::database::DatabaseManager>>>>,
+    pub db: Option<Arc<RwLock<Option<DatabaseManager>>>>,
+    /// Stop after filtering and report the cost estimate instead of translating
+    pub dry_run: bool,
+    /// Shared by every directory of a run (e.g. all archives), so caps apply to the whole run
+    pub budget: Arc<CreditBudget>,
+    /// Catalog prices with the profile's overrides
+    pub pricing: PricingTable,
+    /// The series glossary, sent as context with each page (only set when the model takes context)
+    pub glossary: Option<Arc<Glossary>>,
 }
 
-fn get_model_cost(model: &str) -> u64 {
-    match model {
-        "gemini-2.5-flash" | "deepseek" | "grok-4-fast" | "gemini-3-flash" => 1,
-        // Assume others are 1 for now unless specified
-        _ => 1, 
+impl TranslationOptions {
+    /// A run with `api_key` and the profile's endpoints and credit caps (shared by everything
+    /// the run translates). The caller sets the model and rendering settings; the bundled
+    /// prices stand until `preflight`.
+    pub async fn for_profile(api_key: &str, profile: Option<Arc<RwLock<Profile>>>, db: Option<Arc<RwLock<Option<DatabaseManager>>>>) -> Self {
+        let (endpoints, budget) = match profile {
+            Some(ref p) => {
+                let prof = p.read().await;
+                let endpoints = ApiEndpoints {
+                    storage: prof.storage_url.clone(),
+                    storage_headers: Some(prof.storage_headers.clone()),
+                    ocr: prof.ocr_url.clone(),
+                    ocr_headers: Some(prof.ocr_headers.clone()),
+                    translate: prof.translate_url.clone(),
+                    catalog: prof.catalog_url.clone(),
+                };
+                (Some(endpoints), CreditBudget::from_profile(&prof))
+            }
+            None => (None, CreditBudget::unlimited()),
+        };
+
+        Self {
+            model: String::new(),
+            api_key: api_key.to_string(),
+            target_lang: String::new(),
+            font: String::new(),
+            text_align: "auto".to_string(),
+            stroke_disabled: false,
+            inpaint_only: false,
+            min_font_size: 12,
+            profile,
+            endpoints,
+            included_paths: None,
+            db,
+            dry_run: false,
+            budget: Arc::new(budget),
+            pricing: PricingTable::builtin(),
+            glossary: None,
+        }
+    }
+
+    pub fn client(&self) -> ApiClient {
+        match self.endpoints {
+            Some(ref endpoints) => ApiClient::new_with_endpoints(self.api_key.clone(), endpoints.clone()),
+            None => ApiClient::new(self.api_key.clone()),
+        }
+    }
+
+    /// The open database, if any.
+    pub async fn database(&self) -> Option<DatabaseManager> {
+        match self.db {
+            Some(ref d) => d.read().await.clone(),
+            None => None,
+        }
+    }
+
+    /// Checks the model and font against the catalog, so unknown ones fail here rather than
+    /// on every page, and takes its prices. Returns whether the model takes context.
+    pub async fn preflight(&mut self, logger: &impl ProgressLogger) -> Result<bool> {
+        let db_manager = self.database().await;
+        let profile = match self.profile {
+            Some(ref p) => Some(p.read().await.clone()),
+            None => None,
+        };
+        let checked = preflight(logger, &self.client(), db_manager.as_ref(), profile.as_ref(), &self.model, &self.font).await?;
+        self.pricing = checked.pricing;
+        Ok(checked.context)
     }
 }
 
-async fn calculate_file_hash(path: &Path) -> Result<String> {
-    log_debug(&format!("START HASH: {:?}", path));
+pub async fn calculate_file_hash(path: &Path) -> Result<String> {
+    debug!("START HASH: {:?}", path);
     let path = path.to_owned();
     let hash = tokio::task::spawn_blocking(move || {
         // Use a smaller buffer for hashing to reduce per-thread memory footprint? 
         // Default copy is usually efficient (8KB-128KB).
         // The main issue is likely the sheer number of threads spawned previously.
-        log_debug(&format!("BLOCKING HASH START: {:?}", path));
+        debug!("BLOCKING HASH START: {:?}", path);
         let mut file = std::fs::File::open(&path)?;
         let mut hasher = blake3::Hasher::new();
         std::io::copy(&mut file, &mut hasher)?;
         let h = hasher.finalize().to_hex().to_string();
-        log_debug(&format!("BLOCKING HASH END: {:?}", path));
+        debug!("BLOCKING HASH END: {:?}", path);
         Ok::<String, anyhow::Error>(h)
     }).await??;
-    log_debug(&format!("END HASH: {}", hash));
+    debug!("END HASH: {}", hash);
     Ok(hash)
 }
 
-fn load_history(path: &Path) -> HashSet<String> {
+pub fn load_history(path: &Path) -> HashSet<String> {
     if let Ok(content) = fs::read_to_string(path) {
         serde_json::from_str(&content).unwrap_or_default()
     } else {
@@ -62,7 +143,7 @@ fn load_history(path: &Path) -> HashSet<String> {
     }
 }
 
-fn save_history(path: &Path, history: &HashSet<String>) {
+pub fn save_history(path: &Path, history: &HashSet<String>) {
     if let Ok(content) = serde_json::to_string(history) {
         // Use a temporary file to ensure atomic writes (prevents corruption on crash)
         let tmp_path = path.with_extension("tmp");
@@ -72,37 +153,43 @@ fn save_history(path: &Path, history: &HashSet<String>) {
     }
 }
 
-pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, output_dir: &Path, options: &TranslationOptions) -> Result<()> {
+async fn is_known_hash(hash: &str, history: &HashSet<String>, db: &Option<DatabaseManager>) -> Result<bool> {
+    if history.contains(hash) {
+        return Ok(true);
+    }
+    match db {
+        Some(db) => db.has_hash(hash).await,
+        None => Ok(false),
+    }
+}
+
+// A page whose history lookup failed is not sent: it may already be paid for. It is reported
+// as failed so the run does not look complete, and the next run checks it again.
+fn lookup_failed(logger: &impl ProgressLogger, report: &mut RunReport, path: &Path, error: anyhow::Error) {
+    logger.log(t("WARN_HASH_LOOKUP", &[("file", &path.display()), ("error", &error)]));
+    let mut page = PageReport::new(path);
+    page.error = Some(error.to_string());
+    report.pages.push(page);
+}
+
+pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, output_dir: &Path, options: &TranslationOptions) -> Result<RunReport> {
     let mut all_images = find_all_images(input_dir);
     
     // Filter by included_paths if provided
     if let Some(ref includes) = options.included_paths {
-        let include_set: HashSet<String> = includes.iter().cloned().collect();
-        all_images.retain(|img| {
-            // Check if the image itself is in the set OR any of its parent directories are in the set
-            let img_str = img.to_string_lossy().to_string();
-            if include_set.contains(&img_str) {
-                return true;
-            }
-            
-            // Check parent components relative to input_dir? 
-            // Better: Check if any part of the path is in include_set
-            // Actually, the UI usually sends the Absolute Path of the checked items.
-            // Let's check if the path starts with any of the included paths.
-            includes.iter().any(|inc| img_str.starts_with(inc))
-        });
+        all_images.retain(|img| is_included(img, input_dir, includes));
     }
 
     if all_images.is_empty() {
-        logger.log("No images found in directory (or none selected).".to_string());
-        return Ok(());
+        logger.log(t("ERROR_NO_IMAGES_IN_DIR", &[]));
+        return Ok(RunReport::default());
     }
 
     fs::create_dir_all(output_dir)?;
     
     // Load history from input directory (local to the folder being processed)
     let history_path = input_dir.join(".f_history");
-    log_debug(&format!("HISTORY PATH: {:?}", history_path));
+    debug!("HISTORY PATH: {:?}", history_path);
     let mut history = load_history(&history_path);
     
     // Filter images
@@ -110,7 +197,8 @@ pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, o
     let total_start = all_images.len();
     
     // Log start of filtering
-    logger.log(format!("Dosyalar taranıyor ({})...", total_start));
+    logger.event(ProgressEvent::stage(Stage::Scanning));
+    logger.log(t("INFO_SCANNING_FILES", &[("count", &total_start)]));
 
     let mut skipped_count = 0;
     
@@ -141,16 +229,19 @@ pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, o
     }
 
     if skipped_count > 0 {
-        logger.log(format!("... {} dosya zaten var, atlandı.", skipped_count));
+        logger.log(t("INFO_SKIPPED_EXISTING", &[("count", &skipped_count)]));
     }
+    let mut report = RunReport { skipped: skipped_count, ..Default::default() };
     
     // Now process hashes for the remaining
     skipped_count = 0; // Reset for hash skips
-    let _total_to_hash = pending_images.len();
+    let total_to_hash = pending_images.len();
     let mut processed_hash_count = 0;
+    if total_to_hash > 0 {
+        logger.event(ProgressEvent::stage(Stage::Hashing));
+    }
 
-    // Check DB for existing hashes if available
-    let mut db_existing_hashes = HashSet::new();
+    // Dedup against the DB with a point lookup per hash instead of loading the whole table
     let db_manager = if let Some(ref db_rwlock) = options.db {
         let db_lock = db_rwlock.read().await;
         db_lock.clone()
@@ -158,32 +249,27 @@ pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, o
         None
     };
 
-    if let Some(db) = db_manager {
-        if let Ok(entries) = db.list_all().await {
-            for entry in entries {
-                db_existing_hashes.insert(entry.hash);
-            }
-        }
-    }
-
     for img_path in pending_images {
         // Manage concurrency
-        log_debug(&format!("Queueing hash for: {:?}", img_path));
+        debug!("Queueing hash for: {:?}", img_path);
         while join_set.len() >= max_concurrent {
             if let Some(res) = join_set.join_next().await {
                 if let Ok(Ok((path, hash))) = res {
-                     log_debug(&format!("Joined hash task: {:?}", path));
+                     debug!("Joined hash task: {:?}", path);
                      processed_hash_count += 1;
-                     if history.contains(&hash) || db_existing_hashes.contains(&hash) {
-                         skipped_count += 1;
-                     } else {
-                         // Calculate output path again here to pass it down
-                         let relative_path = path.strip_prefix(input_dir).unwrap_or_else(|_| path.file_name().map(Path::new).unwrap_or(Path::new("unknown"))).to_path_buf();
-                         let out_path = output_dir.join(relative_path);
-                         images_to_process.push((path, out_path, hash));
+                     logger.event(ProgressEvent::StageProgress { stage: Stage::Hashing, current: processed_hash_count, total: total_to_hash });
+                     match is_known_hash(&hash, &history, &db_manager).await {
+                         Ok(true) => skipped_count += 1,
+                         Ok(false) => {
+                             // Calculate output path again here to pass it down
+                             let relative_path = path.strip_prefix(input_dir).unwrap_or_else(|_| path.file_name().map(Path::new).unwrap_or(Path::new("unknown"))).to_path_buf();
+                             let out_path = output_dir.join(relative_path);
+                             images_to_process.push((path, out_path, hash));
+                         }
+                         Err(e) => lookup_failed(logger, &mut report, &path, e),
                      }
                 } else {
-                     log_debug("Failed to join hash task");
+                     debug!("Failed to join hash task");
                 }
             }
             // Give breathing room to the runtime
@@ -203,43 +289,137 @@ pub async fn process_directory(logger: &impl ProgressLogger, input_dir: &Path, o
         }
     }
     
-    log_debug("Finished queuing all hash tasks. Waiting for remaining...");
+    debug!("Finished queuing all hash tasks. Waiting for remaining...");
 
     // Wait for remaining tasks
     while let Some(res) = join_set.join_next().await {
         if let Ok(Ok((path, hash))) = res {
-             log_debug(&format!("Joined remaining hash task: {:?}", path));
-             //processed_hash_count += 1;
-             if history.contains(&hash) || db_existing_hashes.contains(&hash) {
-                  skipped_count += 1;
-             } else {
-                  let relative_path = path.strip_prefix(input_dir).unwrap_or_else(|_| path.file_name().map(Path::new).unwrap_or(Path::new("unknown"))).to_path_buf();
-                  let out_path = output_dir.join(relative_path);
-                  images_to_process.push((path, out_path, hash));
+             debug!("Joined remaining hash task: {:?}", path);
+             processed_hash_count += 1;
+             logger.event(ProgressEvent::StageProgress { stage: Stage::Hashing, current: processed_hash_count, total: total_to_hash });
+             match is_known_hash(&hash, &history, &db_manager).await {
+                  Ok(true) => skipped_count += 1,
+                  Ok(false) => {
+                       let relative_path = path.strip_prefix(input_dir).unwrap_or_else(|_| path.file_name().map(Path::new).unwrap_or(Path::new("unknown"))).to_path_buf();
+                       let out_path = output_dir.join(relative_path);
+                       images_to_process.push((path, out_path, hash));
+                  }
+                  Err(e) => lookup_failed(logger, &mut report, &path, e),
              }
         }
     }
 
     if skipped_count > 0 {
-         logger.log(format!("... {} dosya tarihçeye göre atlandı.", skipped_count));
+         logger.log(t("INFO_SKIPPED_HISTORY", &[("count", &skipped_count)]));
     }
+    report.skipped += skipped_count;
+    
+    // Estimate from what is actually left to send
+    let estimate = CostEstimate::new(&options.pricing, &options.model, images_to_process.len());
+    let cost = estimate.cost_per_image;
+    report.estimate = Some(estimate.clone());
     
     if images_to_process.is_empty() {
-        logger.log("Tüm dosyalar zaten işlenmiş.".to_string());
-        return Ok(());
+        logger.log(t("INFO_ALL_PROCESSED", &[]));
+        return Ok(report);
     }
     
-    logger.log(format!("İşlenecek dosya sayısı: {}", images_to_process.len()));
+    logger.log(t("INFO_FILES_TO_PROCESS", &[("count", &images_to_process.len())]));
+    if !estimate.known_price {
+        logger.log(t("WARN_NO_PRICE", &[("model", &estimate.model), ("cost", &cost)]));
+    }
+    logger.log(t("INFO_TOTAL_COST", &[("count", &estimate.images), ("cost_per_image", &cost), ("total_cost", &estimate.credits)]));
 
-    let client = if let Some(endpoints) = &options.endpoints {
-        ApiClient::new_with_endpoints(options.api_key.clone(), endpoints.clone())
-    } else {
-        ApiClient::new(options.api_key.clone())
+    if let Some(ref db) = db_manager {
+        if let Err(e) = options.budget.load_today(db).await {
+            logger.log(t("WARN_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+    if let Some(left) = options.budget.remaining() {
+        if estimate.credits > left {
+            let pages = left.checked_div(cost).unwrap_or(estimate.images as u64);
+            logger.log(t("WARN_OVER_BUDGET", &[("left", &left), ("pages", &pages)]));
+        }
+    }
+
+    if options.dry_run {
+        logger.log(t("INFO_DRY_RUN", &[]));
+        return Ok(report);
+    }
+
+    let client = options.client();
+
+    // With database mode on, the profile can turn the per-folder .f_history off entirely.
+    // It is still read above, so skips recorded before the switch keep working.
+    let write_history = match (&options.profile, &db_manager) {
+        (Some(profile_rwlock), Some(_)) => {
+            let profile = profile_rwlock.read().await;
+            profile.database_mode == DatabaseMode::Off || profile.write_history_file
+        }
+        _ => true,
     };
 
-        process_individual(logger, images_to_process, output_dir, &client, options, &mut history, &history_path).await?;
+    // The profile's key pool, or just the key this run was started with
+    let keys = match &options.profile {
+        Some(profile_rwlock) => KeyPool::from_profile(&*profile_rwlock.read().await, &options.api_key),
+        None => KeyPool::single(&options.api_key),
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = keys.load_usage(db).await {
+            logger.log(t("WARN_KEY_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
 
-    Ok(())
+    process_individual(logger, images_to_process, output_dir, &client, &keys, cost, options, &mut history, &history_path, write_history, &mut report).await?;
+
+    Ok(report)
+}
+
+/// Books a page the API translated: key pool, credit caps, per-key usage table and the
+/// profile's running total (written by `autosave`, off the hot path). The provider's own
+/// figure wins over the price table when it sends one.
+pub(crate) async fn record_charge(
+    options: &TranslationOptions,
+    keys: &KeyPool,
+    autosave: &mut Option<ProfileAutosave>,
+    selected: &SelectedKey,
+    credits: CreditInfo,
+    cost: u64,
+    page: &mut PageReport,
+    report: &mut RunReport
+) {
+    let charged = credits.charged.unwrap_or(cost);
+    keys.record(selected.index, charged);
+    options.budget.spend(charged);
+    page.credits = charged;
+    page.credits_reported = credits.charged.is_some();
+    if credits.remaining.is_some() {
+        report.balance = credits.remaining;
+    }
+
+    if let Some(profile_rwlock) = &options.profile {
+        let mut profile = profile_rwlock.write().await;
+        profile.total_credits_used += charged;
+        if let Some(balance) = credits.remaining {
+            profile.credit_balance = Some(balance);
+            profile.credit_balance_at = Some(chrono::Utc::now());
+        }
+    }
+    if let Some(autosave) = autosave {
+        autosave.touch().await;
+    }
+
+    if let Some(ref db_rwlock) = options.db {
+        let db_manager = {
+            let db_lock = db_rwlock.read().await;
+            db_lock.clone()
+        };
+        if let Some(db) = db_manager {
+            if let Err(e) = db.record_key_usage(&selected.label, keys.day(), charged).await {
+                warn!("KEY USAGE SAVE ERROR: {}", e);
+            }
+        }
+    }
 }
 
 async fn process_individual(
@@ -247,118 +427,177 @@ async fn process_individual(
     images: Vec<(PathBuf, PathBuf, String)>, 
     _output_dir_parent: &Path, // Not strictly needed as we have full output paths in images
     client: &ApiClient, 
+    keys: &KeyPool,
+    cost: u64,
     options: &TranslationOptions,
     history: &mut HashSet<String>,
-    history_path: &Path
+    history_path: &Path,
+    write_history: bool,
+    report: &mut RunReport
 ) -> Result<()> {
     let mut processed_for_save = 0;
     let total_images = images.len();
+    let mut autosave = options.profile.clone().map(|p| ProfileAutosave::new(p, AUTOSAVE_INTERVAL));
+    let context = options.glossary.as_ref().map(|g| g.context());
+    let mut tracker = ProgressTracker::new(total_images);
 
     for (idx, (img_path, out_path, hash)) in images.into_iter().enumerate() {
         let current_num = idx + 1;
-        log_debug(&format!("START PROCESS INDIVIDUAL: {:?}", img_path));
-        let msg = format!("Processing {}/{} - {:?}", current_num, total_images, img_path.file_name().unwrap_or_default());
+        debug!("START PROCESS INDIVIDUAL: {:?}", img_path);
+        let msg = t("INFO_PROCESSING_FILE", &[("i", &current_num), ("total", &total_images), ("filename", &file_name(&img_path))]);
         logger.progress(current_num, total_images, msg);
+        let started = std::time::Instant::now();
+
+        // Checked before any work on the page, so a cap is never exceeded
+        if let Err(reason) = options.budget.check(cost) {
+            logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+            report.stopped = Some(reason);
+            break;
+        }
         
-        // Check file size and compress if needed
-        let mut path_to_send = img_path.clone();
-        let mut temp_file_created = false;
+        // Everything logged while this page is worked on carries its file
+        let file_span = info_span!("file", file = %img_path.display());
+        let out_of_keys = async {
+            // Check file size and compress if needed
+            let mut path_to_send = img_path.clone();
+            let mut temp_file_created = false;
         
-        if let Ok(metadata) = fs::metadata(&img_path) {
-            if metadata.len() > 15 * 1024 * 1024 {
-                log_debug(&format!("COMPRESSING LARGE FILE: {:?}", img_path));
-                let msg = format!("Compressing large file: {:?}", img_path.file_name().unwrap_or_default());
-                logger.log(msg);
-
-                let img_path_clone = img_path.clone(); // Clone for closure
-                let compress_result = tokio::task::spawn_blocking(move || {
-                     log_debug(&format!("BLOCKING COMPRESS START: {:?}", img_path_clone));
-                     if let Ok(img) = image::open(&img_path_clone) {
-                        let temp_path = img_path_clone.with_extension("tmp.jpg");
-                        if save_image_with_limit(img, &temp_path, 14.8).is_ok() {
-                            log_debug(&format!("BLOCKING COMPRESS SUCCESS: {:?}", temp_path));
-                            return Some(temp_path);
-                        }
-                     }
-                     log_debug(&format!("BLOCKING COMPRESS FAIL: {:?}", img_path_clone));
-                     None
-                }).await.unwrap_or(None);
-
-                if let Some(tp) = compress_result {
-                    path_to_send = tp;
-                    temp_file_created = true;
+            if let Ok(metadata) = fs::metadata(&img_path) {
+                if metadata.len() > 15 * 1024 * 1024 {
+                    debug!("COMPRESSING LARGE FILE: {:?}", img_path);
+                    logger.event(ProgressEvent::file_stage(Stage::Compressing, &img_path));
+                    logger.log(t("INFO_COMPRESSING_LARGE", &[("filename", &file_name(&img_path))]));
+
+                    let img_path_clone = img_path.clone(); // Clone for closure
+                    let compress_result = tokio::task::spawn_blocking(move || {
+                         debug!("BLOCKING COMPRESS START: {:?}", img_path_clone);
+                         if let Ok(img) = image::open(&img_path_clone) {
+                            let temp_path = img_path_clone.with_extension("tmp.jpg");
+                            if save_image_with_limit(img, &temp_path, 14.8).is_ok() {
+                                debug!("BLOCKING COMPRESS SUCCESS: {:?}", temp_path);
+                                return Some(temp_path);
+                            }
+                         }
+                         debug!("BLOCKING COMPRESS FAIL: {:?}", img_path_clone);
+                         None
+                    }).await.unwrap_or(None);
+
+                    if let Some(tp) = compress_result {
+                        path_to_send = tp;
+                        temp_file_created = true;
+                    }
                 }
             }
-        }
 
-        log_debug(&format!("SENDING API REQUEST: {:?}", path_to_send));
-        match client.translate_file(&path_to_send, &options.model, &options.target_lang, &options.font, &options.text_align, options.stroke_disabled, options.inpaint_only, options.min_font_size).await {
-            Ok(image_bytes) => {
-                log_debug(&format!("API SUCCESS, BYTES: {}", image_bytes.len()));
-                
-                // Ensure parent directory exists
-                if let Some(parent) = out_path.parent() {
-                    let _ = fs::create_dir_all(parent);
+            // Rejected keys (401/402/403) are dropped and the page goes to the next one
+            let mut page = PageReport::new(&img_path);
+            let bytes_sent = fs::metadata(&path_to_send).map(|m| m.len()).unwrap_or(0);
+            let mut bytes_received = 0;
+            logger.event(ProgressEvent::file_stage(Stage::Uploading, &img_path));
+            let result = loop {
+                let Some(selected) = keys.select(cost) else {
+                    break Err(anyhow!(t("REASON_NO_KEYS", &[])));
+                };
+                page.key = Some(selected.label.clone());
+
+                debug!("SENDING API REQUEST: {:?} (key {})", path_to_send, selected.label);
+                match client.with_api_key(&selected.key).translate_file(&path_to_send, &options.model, &options.target_lang, &options.font, &options.text_align, options.stroke_disabled, options.inpaint_only, options.min_font_size, context.as_deref()).await {
+                    Err(e) if e.downcast_ref::<KeyRejected>().is_some() => {
+                        logger.log(t("WARN_KEY_REJECTED", &[("key", &selected.label), ("error", &e)]));
+                        keys.reject(selected.index);
+                    }
+                    result => break result.map(|translated| (translated, selected)),
                 }
-                
-                // Save the received image bytes directly
-                if let Err(e) = fs::write(&out_path, image_bytes) {
-                     log_debug(&format!("SAVE ERROR: {}", e));
-                     logger.log(format!("Kaydetme Hatası: {}", e));
-                } else {
-                    log_debug(&format!("SAVED: {:?}", out_path));
+            };
 
-                    // Update credits used
-                    if let Some(profile_rwlock) = &options.profile {
-                        let mut profile = profile_rwlock.write().await;
-                        profile.total_credits_used += get_model_cost(&options.model);
-                        let _ = profile.save(Path::new("profile.json"));
-                    }
+            let mut out_of_keys = false;
+            match result {
+                Ok((translated, selected)) => {
+                    debug!("API SUCCESS, BYTES: {}", translated.image.len());
+                    bytes_received = translated.image.len() as u64;
 
-                    // Update history
-                    if !hash.is_empty() {
-                        history.insert(hash.clone());
+                    // Charged once the API answered, whether or not the file can be saved
+                    record_charge(options, keys, &mut autosave, &selected, translated.credits, cost, &mut page, report).await;
+                
+                    // Ensure parent directory exists
+                    if let Some(parent) = out_path.parent() {
+                        let _ = fs::create_dir_all(parent);
+                    }
+                
+                    // Save the received image bytes directly
+                    logger.event(ProgressEvent::file_stage(Stage::Saving, &img_path));
+                    if let Err(e) = fs::write(&out_path, translated.image) {
+                         warn!("SAVE ERROR: {}", e);
+                         logger.log(t("ERROR_SAVE", &[("error", &e)]));
+                         page.error = Some(format!("Save failed: {}", e));
+                    } else {
+                        debug!("SAVED: {:?}", out_path);
+                        page.status = PageStatus::Translated;
+
+                        // Update history
+                        if !hash.is_empty() {
+                            history.insert(hash.clone());
                         
-                        // ALSO Save to Database for centralized history
-                        if let Some(ref db_rwlock) = options.db {
-                            let db_lock = db_rwlock.read().await;
-                            if let Some(db) = db_lock.as_ref() {
-                                let name = img_path.file_name().unwrap_or_default().to_string_lossy().to_string();
-                                // Get the immediate folder name for grouping
-                                let folder_name = img_path.parent()
-                                    .and_then(|p| p.file_name())
-                                    .map(|n| n.to_string_lossy().to_string())
-                                    .unwrap_or_else(|| "Root".to_string());
+                            // ALSO Save to Database for centralized history.
+                            // Awaited (not spawned) so a failed write is reported instead of lost.
+                            if let Some(ref db_rwlock) = options.db {
+                                let db_manager = {
+                                    let db_lock = db_rwlock.read().await;
+                                    db_lock.clone()
+                                };
+                                if let Some(db) = db_manager {
+                                    let name = img_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                                    // Get the immediate folder name for grouping
+                                    let folder_name = img_path.parent()
+                                        .and_then(|p| p.file_name())
+                                        .map(|n| n.to_string_lossy().to_string())
+                                        .unwrap_or_else(|| "Root".to_string());
                                 
-                                let db_clone = db.clone();
-                                let hash_clone = hash.clone();
-                                tokio::spawn(async move {
-                                    let _ = db_clone.save_hash(hash_clone, name, folder_name).await;
-                                });
+                                    if let Err(e) = db.save_translation(hash.clone(), name, folder_name, options.model.clone(), options.target_lang.clone()).await {
+                                        warn!("DB SAVE ERROR: {}", e);
+                                        logger.log(t("ERROR_DB_SAVE", &[("filename", &file_name(&img_path)), ("error", &e)]));
+                                    }
+                                }
                             }
-                        }
 
-                        processed_for_save += 1;
-                        // Batch saves to disk to prevent IO overload
-                        if processed_for_save >= 10 {
-                            save_history(history_path, history);
-                            processed_for_save = 0;
+                            if write_history {
+                                processed_for_save += 1;
+                                // Batch saves to disk to prevent IO overload
+                                if processed_for_save >= 10 {
+                                    save_history(history_path, history);
+                                    processed_for_save = 0;
+                                }
+                            }
                         }
                     }
-                }
-            },
-            Err(e) => {
-                let err_msg = format!("Failed to translate {:?}: {}", img_path.file_name().unwrap_or_default(), e);
-                logger.log(err_msg);
-            },
-        }
+                },
+                Err(e) => {
+                    logger.log(t("ERROR_TRANSLATE_FILE", &[("filename", &file_name(&img_path)), ("error", &e)]));
+                    out_of_keys = !keys.has_usable(cost);
+                    page.error = Some(e.to_string());
+                },
+            }
+            debug::record_timing(&img_path, started.elapsed(), page.error.as_deref().unwrap_or("translated"));
+            logger.event(page.file_done(&img_path, started.elapsed(), bytes_sent, bytes_received));
+            logger.event(tracker.file_done(bytes_sent + bytes_received));
+            report.pages.push(page);
         
-        if temp_file_created {
-            let _ = fs::remove_file(path_to_send);
+            if temp_file_created {
+                let _ = fs::remove_file(path_to_send);
+            }
+            out_of_keys
+        }.instrument(file_span).await;
+
+        if out_of_keys {
+            let reason = t("REASON_NO_KEYS", &[]);
+            logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+            report.stopped = Some(reason);
+            report.keys_exhausted = true;
+            break;
         }
 
         // Rate limit delay (Individual mode)
-        logger.log("Waiting 3 seconds...".to_string());
+        logger.log(t("INFO_WAITING", &[("secs", &3)]));
         tokio::time::sleep(std::time::Duration::from_secs(3)).await;
     }
     
@@ -366,6 +605,45 @@ async fn process_individual(
     if processed_for_save > 0 {
         save_history(history_path, history);
     }
+    if let Some(ref mut autosave) = autosave {
+        autosave.flush().await;
+    }
+    logger.event(ProgressEvent::stage(Stage::Done));
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::logger::ConsoleLogger;
+
+    #[tokio::test]
+    async fn relative_includes_pick_folders_under_the_input() {
+        let root = std::env::temp_dir().join(format!("tapi-include-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for page in ["ch01/01.png", "ch01/02.png", "ch02/01.png"] {
+            let path = root.join(page);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, page).unwrap();
+        }
+
+        let options = TranslationOptions {
+            model: "test".to_string(),
+            included_paths: Some(vec!["ch01".to_string()]),
+            dry_run: true,
+            ..TranslationOptions::for_profile("", None, None).await
+        };
+        let report = process_directory(&ConsoleLogger, &root, &root.join("translated"), &options).await.unwrap();
+        assert_eq!(report.estimate.map(|e| e.images), Some(2));
+
+        let absolute = TranslationOptions {
+            included_paths: Some(vec![root.join("ch02").to_string_lossy().to_string()]),
+            ..options
+        };
+        let report = process_directory(&ConsoleLogger, &root, &root.join("translated"), &absolute).await.unwrap();
+        assert_eq!(report.estimate.map(|e| e.images), Some(1));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::core::database::{DatabaseManager, HashEntry, ImportMode};
+use crate::core::image::find_all_images;
+use crate::core::processor::{calculate_file_hash, load_history, save_history};
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::file_name;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+const REPAIR_BATCH_SIZE: usize = 500;
+
+/// Which side gets repaired. `Report` only compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileMode {
+    Report,
+    ToDatabase,
+    ToHistory,
+    Both,
+}
+
+impl std::str::FromStr for ReconcileMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "report" => Ok(ReconcileMode::Report),
+            "to_database" | "database" | "db" => Ok(ReconcileMode::ToDatabase),
+            "to_history" | "history" => Ok(ReconcileMode::ToHistory),
+            "both" => Ok(ReconcileMode::Both),
+            other => Err(anyhow!("Unknown reconcile mode '{}' (expected report, to_database, to_history or both)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FolderDiff {
+    pub folder: String,
+    /// Hashes listed in `.f_history` but missing from the database
+    pub missing_in_database: usize,
+    /// Files in the folder that the database knows but `.f_history` does not
+    pub missing_in_history: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReconcileReport {
+    pub folders: Vec<FolderDiff>,
+    pub missing_in_database: usize,
+    pub missing_in_history: usize,
+    pub added_to_database: usize,
+    pub added_to_history: usize,
+}
+
+/// Hashes every image under `folder`, mapping hash -> (file name, parent folder name).
+pub async fn hash_folder_images(logger: &impl ProgressLogger, folder: &Path) -> HashMap<String, (String, String)> {
+    let images = find_all_images(folder);
+    let total = images.len();
+    let mut names = HashMap::with_capacity(total);
+
+    for (idx, img) in images.iter().enumerate() {
+        logger.progress(idx + 1, total, t("INFO_HASHING_FILE", &[("filename", &file_name(img))]));
+        if let Ok(hash) = calculate_file_hash(img).await {
+            let name = img.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let parent = img.parent()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Root".to_string());
+            names.insert(hash, (name, parent));
+        }
+    }
+    names
+}
+
+// Every folder under `root` that has its own .f_history (output folders are skipped)
+fn find_history_folders(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            name != "translated" && !name.ends_with("_output")
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == ".f_history")
+        .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+        .collect()
+}
+
+/// Walks a library root, compares each `.f_history` with `file_hashes`, and repairs the
+/// differences in the direction given by `mode`.
+pub async fn reconcile_library(logger: &impl ProgressLogger, db: &DatabaseManager, root: &Path, mode: ReconcileMode) -> Result<ReconcileReport> {
+    if !root.is_dir() {
+        return Err(anyhow!("Path does not exist: {}", root.display()));
+    }
+
+    let mut report = ReconcileReport::default();
+    let folders = find_history_folders(root);
+    logger.log(t("INFO_HISTORY_FOLDERS", &[("count", &folders.len())]));
+
+    for folder in folders {
+        let history_path = folder.join(".f_history");
+        let mut history = load_history(&history_path);
+        let files = hash_folder_images(logger, &folder).await;
+
+        // History -> DB: every listed hash should have a live row
+        let known = db.existing_hashes(history.iter().cloned().collect()).await?;
+        let missing_in_db: Vec<String> = history.iter().filter(|h| !known.contains(*h)).cloned().collect();
+
+        // DB -> history: only files still present in the folder can be attributed to it
+        let unlisted: Vec<String> = files.keys().filter(|h| !history.contains(*h)).cloned().collect();
+        let missing_in_history: HashSet<String> = db.existing_hashes(unlisted).await?;
+
+        let folder_name = folder.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Root".to_string());
+
+        if !missing_in_db.is_empty() || !missing_in_history.is_empty() {
+            logger.log(t("INFO_RECONCILE_FOLDER", &[
+                ("folder", &folder.display()), ("missing_db", &missing_in_db.len()), ("missing_history", &missing_in_history.len()),
+            ]));
+        }
+
+        report.missing_in_database += missing_in_db.len();
+        report.missing_in_history += missing_in_history.len();
+        report.folders.push(FolderDiff {
+            folder: folder.to_string_lossy().to_string(),
+            missing_in_database: missing_in_db.len(),
+            missing_in_history: missing_in_history.len(),
+        });
+
+        if matches!(mode, ReconcileMode::ToDatabase | ReconcileMode::Both) && !missing_in_db.is_empty() {
+            let now = chrono::Utc::now();
+            let entries: Vec<HashEntry> = missing_in_db.into_iter().map(|hash| {
+                let (name, parent) = files.get(&hash).cloned().unwrap_or_else(|| (String::new(), folder_name.clone()));
+                HashEntry {
+                    hash,
+                    name,
+                    folder: parent,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                    deleted: false,
+                    model: String::new(),
+                    target_lang: String::new(),
+                    synced_at: None,
+                }
+            }).collect();
+            let (written, _) = db.import_entries(entries, ImportMode::Merge, REPAIR_BATCH_SIZE).await?;
+            report.added_to_database += written;
+        }
+
+        if matches!(mode, ReconcileMode::ToHistory | ReconcileMode::Both) && !missing_in_history.is_empty() {
+            report.added_to_history += missing_in_history.len();
+            history.extend(missing_in_history);
+            save_history(&history_path, &history);
+        }
+    }
+
+    logger.log(t("INFO_RECONCILE_DONE", &[
+        ("missing_db", &report.missing_in_database), ("missing_history", &report.missing_in_history),
+        ("added_db", &report.added_to_database), ("added_history", &report.added_to_history),
+    ]));
+    Ok(report)
+}
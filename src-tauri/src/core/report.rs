@@ -0,0 +1,171 @@
+use crate::core::pricing::CostEstimate;
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::{file_name, FileStatus, ProgressEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PageStatus {
+    Translated,
+    Failed,
+}
+
+/// Outcome of one page sent to the API.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageReport {
+    pub file: String,
+    pub status: PageStatus,
+    /// Label of the API key that translated the page (or was tried last)
+    pub key: Option<String>,
+    /// What the provider reported it charged, or the price table's cost when it did not say
+    pub credits: u64,
+    pub credits_reported: bool,
+    pub error: Option<String>,
+}
+
+impl PageReport {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            file: path.to_string_lossy().to_string(),
+            status: PageStatus::Failed,
+            key: None,
+            credits: 0,
+            credits_reported: false,
+            error: None,
+        }
+    }
+
+    /// The page's outcome as a progress event for `path`, the image it was made from.
+    pub fn file_done(&self, path: &Path, elapsed: Duration, bytes_sent: u64, bytes_received: u64) -> ProgressEvent {
+        ProgressEvent::FileDone {
+            file: file_name(path),
+            status: match self.status {
+                PageStatus::Translated => FileStatus::Done,
+                PageStatus::Failed => FileStatus::Failed,
+            },
+            error: self.error.clone(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            bytes_sent,
+            bytes_received,
+        }
+    }
+}
+
+/// What a translation run did, page by page. Returned to the UI / API callers and
+/// printed at the end of CLI runs.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RunReport {
+    pub pages: Vec<PageReport>,
+    /// Pages skipped because their output or hash already existed
+    pub skipped: usize,
+    /// Why the run ended before every page was sent, if it did
+    pub stopped: Option<String>,
+    /// Images left after filtering and their cost (the only result of a dry run)
+    pub estimate: Option<CostEstimate>,
+    /// Balance the provider reported last during the run
+    pub balance: Option<u64>,
+    /// The run stopped because every API key was rejected or over its budget
+    #[serde(default)]
+    pub keys_exhausted: bool,
+}
+
+/// How a run went as a whole, for callers that act on it (the CLI's exit code).
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    /// Every page sent was translated, and the run was not cut short
+    Success,
+    /// Some pages failed, or the run stopped before sending them all
+    Partial,
+    /// Pages were sent and none of them was translated
+    Failed,
+    /// Nothing was translated because no API key was accepted
+    Unauthorized,
+}
+
+impl RunReport {
+    pub fn translated(&self) -> usize {
+        self.pages.iter().filter(|p| p.status == PageStatus::Translated).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.pages.iter().filter(|p| p.status == PageStatus::Failed).count()
+    }
+
+    pub fn credits(&self) -> u64 {
+        self.pages.iter().map(|p| p.credits).sum()
+    }
+
+    pub fn outcome(&self) -> RunOutcome {
+        let translated = self.translated();
+        if self.keys_exhausted && translated == 0 {
+            RunOutcome::Unauthorized
+        } else if self.failed() == 0 && self.stopped.is_none() {
+            RunOutcome::Success
+        } else if translated == 0 && self.stopped.is_none() {
+            RunOutcome::Failed
+        } else {
+            RunOutcome::Partial
+        }
+    }
+
+    /// Folds the report of another directory (e.g. the next archive) into this one.
+    pub fn merge(&mut self, other: RunReport) {
+        self.pages.extend(other.pages);
+        self.skipped += other.skipped;
+        if self.stopped.is_none() {
+            self.stopped = other.stopped;
+        }
+        if other.balance.is_some() {
+            self.balance = other.balance;
+        }
+        self.keys_exhausted |= other.keys_exhausted;
+        if let Some(estimate) = other.estimate {
+            self.estimate.get_or_insert_with(CostEstimate::default).merge(&estimate);
+        }
+    }
+
+    pub fn log_summary(&self, logger: &impl ProgressLogger) {
+        logger.log(t("INFO_REPORT", &[
+            ("translated", &self.translated()), ("failed", &self.failed()), ("skipped", &self.skipped), ("credits", &self.credits()),
+        ]));
+        let estimated = self.pages.iter().filter(|p| p.status == PageStatus::Translated && !p.credits_reported).count();
+        if estimated > 0 {
+            logger.log(format!("  {}", t("INFO_REPORT_ESTIMATED", &[("count", &estimated)])));
+        }
+        if let Some(balance) = self.balance {
+            logger.log(format!("  {}", t("INFO_REPORT_BALANCE", &[("balance", &balance)])));
+        }
+        if let Some(ref e) = self.estimate {
+            logger.log(format!("  {}", t("INFO_REPORT_ESTIMATE", &[("model", &e.model), ("count", &e.images), ("cost_per_image", &e.cost_per_image), ("total_cost", &e.credits)])));
+        }
+
+        // (pages, credits) per key label
+        let mut per_key: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+        for page in self.pages.iter().filter(|p| p.status == PageStatus::Translated) {
+            let entry = per_key.entry(page.key.as_deref().unwrap_or("-")).or_default();
+            entry.0 += 1;
+            entry.1 += page.credits;
+        }
+        for (label, (pages, credits)) in per_key {
+            logger.log(format!("  {}", t("INFO_REPORT_KEY", &[("key", &label), ("pages", &pages), ("credits", &credits)])));
+        }
+
+        for page in &self.pages {
+            let name = Path::new(&page.file).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| page.file.clone());
+            let key = page.key.as_deref().unwrap_or("-");
+            match page.status {
+                PageStatus::Translated => logger.log(format!("  {}", t("INFO_REPORT_PAGE_OK", &[("filename", &name), ("key", &key)]))),
+                PageStatus::Failed => logger.log(format!("  {}", t("INFO_REPORT_PAGE_FAILED", &[("filename", &name), ("key", &key), ("error", &page.error.as_deref().unwrap_or("unknown error"))]))),
+            }
+        }
+
+        if let Some(ref reason) = self.stopped {
+            logger.log(t("INFO_REPORT_STOPPED", &[("reason", reason)]));
+        }
+    }
+}
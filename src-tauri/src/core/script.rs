@@ -0,0 +1,397 @@
+use crate::config::autosave::{ProfileAutosave, AUTOSAVE_INTERVAL};
+use crate::core::api::KeyRejected;
+use crate::core::keys::KeyPool;
+use crate::core::ocr::{BoundingBox, OcrPage, OCR_PAGES_DIR};
+use crate::core::pricing::CostEstimate;
+use crate::core::processor::{record_charge, TranslationOptions};
+use crate::core::report::{PageReport, PageStatus, RunReport};
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::{ProgressEvent, ProgressTracker, Stage};
+use tracing::{debug, info_span, Instrument};
+use crate::utils::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Result, anyhow};
+use walkdir::WalkDir;
+
+pub const SCRIPT_VERSION: u32 = 1;
+
+/// `file` (a script page's image) under `base`. Only plain relative paths are accepted, so an
+/// edited script cannot make a render read or write outside its folders.
+pub fn page_path(base: &Path, file: &str) -> Result<PathBuf> {
+    let relative = Path::new(file);
+    let plain = relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if file.trim().is_empty() || !plain {
+        return Err(anyhow!("Invalid page path in script: '{}' (must be relative, without '..')", file));
+    }
+    Ok(base.join(relative))
+}
+
+/// One speech bubble: where it is, what it said and what it should say.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Bubble {
+    pub bbox: Option<BoundingBox>,
+    pub source: String,
+    #[serde(default)]
+    pub translation: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScriptPage {
+    /// Image path relative to the chapter folder
+    pub file: String,
+    pub bubbles: Vec<Bubble>,
+}
+
+/// A bilingual script: every bubble of every page with its source and translated text.
+/// Exported for proofreading and imported back to re-render pages without OCRing again.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Script {
+    #[serde(default)]
+    pub version: u32,
+    pub pages: Vec<ScriptPage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ScriptFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ScriptFormat::Json),
+            "csv" => Ok(ScriptFormat::Csv),
+            other => Err(anyhow!("Unknown script format '{}' (expected json or csv)", other)),
+        }
+    }
+}
+
+impl ScriptFormat {
+    /// From the file extension; anything but `.csv` is JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().map(|e| e.to_string_lossy().to_lowercase()) {
+            Some(ext) if ext == "csv" => ScriptFormat::Csv,
+            _ => ScriptFormat::Json,
+        }
+    }
+}
+
+// One CSV line per bubble, so the script opens as a spreadsheet
+#[derive(Debug, Serialize, Deserialize)]
+struct ScriptRow {
+    page: String,
+    bubble: usize,
+    x: Option<f64>,
+    y: Option<f64>,
+    width: Option<f64>,
+    height: Option<f64>,
+    source: String,
+    translation: String,
+}
+
+impl Script {
+    pub fn from_ocr_pages(pages: &[OcrPage]) -> Self {
+        let pages = pages.iter().map(|page| ScriptPage {
+            file: page.file.clone(),
+            bubbles: page.blocks.iter().map(|block| Bubble {
+                bbox: block.bbox,
+                source: block.text.clone(),
+                translation: block.translation.clone().unwrap_or_default(),
+            }).collect(),
+        }).collect();
+        Self { version: SCRIPT_VERSION, pages }
+    }
+
+    /// Builds a script from an OCR run's output folder (the per-page JSON under `pages/`).
+    pub fn from_ocr_dir(dir: &Path) -> Result<Self> {
+        let pages_dir = dir.join(OCR_PAGES_DIR);
+        let root = if pages_dir.is_dir() { pages_dir } else { dir.to_path_buf() };
+        let mut pages = Vec::new();
+        for entry in WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.is_file() || path.extension().map(|e| e != "json").unwrap_or(true) {
+                continue;
+            }
+            match fs::read_to_string(path).map_err(anyhow::Error::from).and_then(|s| Ok(serde_json::from_str::<OcrPage>(&s)?)) {
+                Ok(page) => pages.push(page),
+                Err(e) => debug!("SCRIPT: skipping {:?}: {}", path, e),
+            }
+        }
+        if pages.is_empty() {
+            return Err(anyhow!("No OCR results found in {} (run OCR on the folder first)", dir.display()));
+        }
+        pages.sort_by(|a, b| a.file.cmp(&b.file));
+        Ok(Self::from_ocr_pages(&pages))
+    }
+
+    pub fn bubble_count(&self) -> usize {
+        self.pages.iter().map(|p| p.bubbles.len()).sum()
+    }
+
+    pub fn save(&self, path: &Path, format: ScriptFormat) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match format {
+            ScriptFormat::Json => fs::write(path, serde_json::to_string_pretty(self)?)?,
+            ScriptFormat::Csv => {
+                let mut writer = csv::Writer::from_path(path)?;
+                for page in &self.pages {
+                    for (i, bubble) in page.bubbles.iter().enumerate() {
+                        writer.serialize(ScriptRow {
+                            page: page.file.clone(),
+                            bubble: i + 1,
+                            x: bubble.bbox.map(|b| b.x),
+                            y: bubble.bbox.map(|b| b.y),
+                            width: bubble.bbox.map(|b| b.width),
+                            height: bubble.bbox.map(|b| b.height),
+                            source: bubble.source.clone(),
+                            translation: bubble.translation.clone(),
+                        })?;
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a script in the format its extension says. Page paths are checked with `page_path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let script = Self::read(path)?;
+        for page in &script.pages {
+            page_path(Path::new(""), &page.file)?;
+        }
+        Ok(script)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        match ScriptFormat::from_path(path) {
+            ScriptFormat::Json => {
+                let text = fs::read_to_string(path)?;
+                let script: Script = serde_json::from_str(&text)
+                    .map_err(|e| anyhow!("Invalid script {}: {}", path.display(), e))?;
+                if script.version > SCRIPT_VERSION {
+                    return Err(anyhow!("Script version {} is newer than this app supports ({})", script.version, SCRIPT_VERSION));
+                }
+                Ok(script)
+            }
+            ScriptFormat::Csv => {
+                let mut reader = csv::Reader::from_path(path)?;
+                let mut rows: Vec<ScriptRow> = Vec::new();
+                for (line, row) in reader.deserialize().enumerate() {
+                    // +2: header line and 1-based numbering
+                    rows.push(row.map_err(|e| anyhow!("Invalid script row {}: {}", line + 2, e))?);
+                }
+                // Spreadsheets may reorder rows; pages keep their first appearance, bubbles their number
+                let mut pages: Vec<ScriptPage> = Vec::new();
+                let mut order: Vec<Vec<usize>> = Vec::new();
+                for row in rows {
+                    let index = match pages.iter().position(|p| p.file == row.page) {
+                        Some(i) => i,
+                        None => {
+                            pages.push(ScriptPage { file: row.page.clone(), bubbles: Vec::new() });
+                            order.push(Vec::new());
+                            pages.len() - 1
+                        }
+                    };
+                    let bbox = match (row.x, row.y, row.width, row.height) {
+                        (Some(x), Some(y), Some(width), Some(height)) => Some(BoundingBox { x, y, width, height }),
+                        _ => None,
+                    };
+                    pages[index].bubbles.push(Bubble { bbox, source: row.source, translation: row.translation });
+                    order[index].push(row.bubble);
+                }
+                for (page, numbers) in pages.iter_mut().zip(order) {
+                    let mut numbered: Vec<(usize, Bubble)> = numbers.into_iter().zip(page.bubbles.drain(..)).collect();
+                    numbered.sort_by_key(|(n, _)| *n);
+                    page.bubbles = numbered.into_iter().map(|(_, b)| b).collect();
+                }
+                Ok(Self { version: SCRIPT_VERSION, pages })
+            }
+        }
+    }
+}
+
+/// Re-renders the pages of an edited script: each page image (looked up under `image_dir`)
+/// goes to the backend with its bubbles' translations, and the result is written to the same
+/// relative path under `output_dir`. Pages with no translated bubble are skipped. Caps, key
+/// pool and reporting work as for translation runs.
+pub async fn render_script(logger: &impl ProgressLogger, script: &Script, image_dir: &Path, output_dir: &Path, options: &TranslationOptions) -> Result<RunReport> {
+    let mut report = RunReport::default();
+
+    let mut pending = Vec::new();
+    for page in &script.pages {
+        if page.bubbles.iter().all(|b| b.translation.trim().is_empty()) {
+            report.skipped += 1;
+            continue;
+        }
+        let (img_path, out_path) = match page_path(image_dir, &page.file).and_then(|img| Ok((img, page_path(output_dir, &page.file)?))) {
+            Ok(paths) => paths,
+            Err(e) => {
+                let mut page_report = PageReport::new(Path::new(&page.file));
+                page_report.error = Some(e.to_string());
+                logger.log(t("ERROR_RENDER", &[("filename", &page.file), ("error", &e)]));
+                report.pages.push(page_report);
+                continue;
+            }
+        };
+        if !img_path.is_file() {
+            let mut page_report = PageReport::new(&img_path);
+            page_report.error = Some("Image not found".to_string());
+            logger.log(t("ERROR_SCRIPT_IMAGE_MISSING", &[("file", &page.file)]));
+            report.pages.push(page_report);
+            continue;
+        }
+        pending.push((page, img_path, out_path));
+    }
+
+    let estimate = CostEstimate::new(&options.pricing, &options.model, pending.len());
+    let cost = estimate.cost_per_image;
+    report.estimate = Some(estimate.clone());
+    if pending.is_empty() {
+        logger.log(t("INFO_NOTHING_TO_RENDER", &[]));
+        return Ok(report);
+    }
+    logger.log(t("INFO_TOTAL_COST", &[("count", &estimate.images), ("cost_per_image", &cost), ("total_cost", &estimate.credits)]));
+
+    let db_manager = match options.db {
+        Some(ref db_rwlock) => db_rwlock.read().await.clone(),
+        None => None,
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = options.budget.load_today(db).await {
+            logger.log(t("WARN_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+
+    if options.dry_run {
+        logger.log(t("INFO_DRY_RUN", &[]));
+        return Ok(report);
+    }
+
+    let client = options.client();
+    let keys = match &options.profile {
+        Some(profile_rwlock) => KeyPool::from_profile(&*profile_rwlock.read().await, &options.api_key),
+        None => KeyPool::single(&options.api_key),
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = keys.load_usage(db).await {
+            logger.log(t("WARN_KEY_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+    let mut autosave = options.profile.clone().map(|p| ProfileAutosave::new(p, AUTOSAVE_INTERVAL));
+
+    let total = pending.len();
+    let mut tracker = ProgressTracker::new(total);
+    for (idx, (page, img_path, out_path)) in pending.into_iter().enumerate() {
+        logger.progress(idx + 1, total, t("INFO_RENDERING", &[("i", &(idx + 1)), ("total", &total), ("filename", &page.file)]));
+
+        if let Err(reason) = options.budget.check(cost) {
+            logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+            report.stopped = Some(reason);
+            break;
+        }
+
+        // Untranslated bubbles are left as they are in the source image
+        let bubbles: Vec<Bubble> = page.bubbles.iter().filter(|b| !b.translation.trim().is_empty()).cloned().collect();
+        let mut page_report = PageReport::new(&img_path);
+        let started = std::time::Instant::now();
+        let bytes_sent = fs::metadata(&img_path).map(|m| m.len()).unwrap_or(0);
+        logger.event(ProgressEvent::file_stage(Stage::Uploading, &img_path));
+        let file_span = info_span!("file", file = %img_path.display());
+        let result = async {
+            loop {
+                let Some(selected) = keys.select(cost) else {
+                    break Err(anyhow!(t("REASON_NO_KEYS", &[])));
+                };
+                page_report.key = Some(selected.label.clone());
+                match client.with_api_key(&selected.key).render_page(&img_path, &bubbles, &options.target_lang, &options.font, &options.text_align, options.stroke_disabled, options.min_font_size).await {
+                    Err(e) if e.downcast_ref::<KeyRejected>().is_some() => {
+                        logger.log(t("WARN_KEY_REJECTED", &[("key", &selected.label), ("error", &e)]));
+                        keys.reject(selected.index);
+                    }
+                    result => break result.map(|rendered| (rendered, selected)),
+                }
+            }
+        }.instrument(file_span).await;
+
+        match result {
+            Ok((rendered, selected)) => {
+                record_charge(options, &keys, &mut autosave, &selected, rendered.credits, cost, &mut page_report, &mut report).await;
+                let bytes_received = rendered.image.len() as u64;
+                logger.event(ProgressEvent::file_stage(Stage::Saving, &img_path));
+                if let Some(parent) = out_path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                match fs::write(&out_path, rendered.image) {
+                    Ok(()) => page_report.status = PageStatus::Translated,
+                    Err(e) => {
+                        logger.log(t("ERROR_SAVE_FILE", &[("filename", &out_path.display()), ("error", &e)]));
+                        page_report.error = Some(format!("Save failed: {}", e));
+                    }
+                }
+                debug::record_timing(&img_path, started.elapsed(), page_report.error.as_deref().unwrap_or("rendered"));
+                logger.event(page_report.file_done(&img_path, started.elapsed(), bytes_sent, bytes_received));
+                logger.event(tracker.file_done(bytes_sent + bytes_received));
+                report.pages.push(page_report);
+            }
+            Err(e) => {
+                logger.log(t("ERROR_RENDER", &[("filename", &page.file), ("error", &e)]));
+                page_report.error = Some(e.to_string());
+                debug::record_timing(&img_path, started.elapsed(), &e.to_string());
+                logger.event(page_report.file_done(&img_path, started.elapsed(), bytes_sent, 0));
+                logger.event(tracker.file_done(bytes_sent));
+                report.pages.push(page_report);
+                if !keys.has_usable(cost) {
+                    let reason = t("REASON_NO_KEYS", &[]);
+                    logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+                    report.stopped = Some(reason);
+                    report.keys_exhausted = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(ref mut autosave) = autosave {
+        autosave.flush().await;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_paths_stay_inside_their_folder() {
+        let base = Path::new("out");
+        assert_eq!(page_path(base, "ch1/01.jpg").unwrap(), base.join("ch1/01.jpg"));
+        assert_eq!(page_path(base, "./01.jpg").unwrap(), base.join("./01.jpg"));
+        assert!(page_path(base, "../01.jpg").is_err());
+        assert!(page_path(base, "ch1/../../01.jpg").is_err());
+        assert!(page_path(base, "/etc/passwd").is_err());
+        assert!(page_path(base, "").is_err());
+    }
+
+    #[test]
+    fn scripts_with_escaping_pages_do_not_load() {
+        let path = std::env::temp_dir().join(format!("tapi-script-{}.json", std::process::id()));
+        let script = Script {
+            version: SCRIPT_VERSION,
+            pages: vec![ScriptPage { file: "../outside.jpg".to_string(), bubbles: Vec::new() }],
+        };
+        script.save(&path, ScriptFormat::Json).unwrap();
+        let loaded = Script::load(&path);
+        let _ = fs::remove_file(&path);
+        assert!(loaded.is_err());
+    }
+}
@@ -0,0 +1,334 @@
+use crate::config::autosave::{ProfileAutosave, AUTOSAVE_INTERVAL};
+use crate::core::api::{ApiClient, CreditInfo, KeyRejected};
+use crate::core::keys::KeyPool;
+use crate::core::pricing::CostEstimate;
+use crate::core::processor::{record_charge, TranslationOptions};
+use crate::core::report::{PageReport, PageStatus, RunReport};
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::progress::{ProgressEvent, ProgressTracker, Stage};
+use tracing::warn;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+
+/// URLs per submit request.
+pub const STORAGE_BATCH_SIZE: usize = 20;
+const POLL_INTERVAL_SECS: u64 = 3;
+/// A batch still unfinished after this long is given up on.
+const POLL_TIMEOUT_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Pending,
+    Done,
+    Failed,
+}
+
+/// One image URL the provider translates on its side, as the storage endpoint lists it:
+/// `{"url": "<source url>", "status": "pending"|"done"|"failed", "result_url"?, "image"?, "error"?, "credits_used"?}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageJob {
+    /// The source URL, as it was sent in `storage_urls`
+    pub url: String,
+    pub status: JobState,
+    #[serde(default)]
+    pub result_url: Option<String>,
+    /// Inline result (base64, optionally a data URL)
+    #[serde(default, skip_serializing)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub credits_used: Option<u64>,
+}
+
+/// A storage response, `{"results": [job, ...]}`, and any credit figures it carried.
+#[derive(Debug, Clone, Default)]
+pub struct StorageBatch {
+    pub jobs: Vec<StorageJob>,
+    pub credits: CreditInfo,
+}
+
+#[derive(Deserialize)]
+struct StorageResponse {
+    results: Vec<StorageJob>,
+}
+
+impl StorageJob {
+    /// The translated image, inline or downloaded.
+    async fn fetch_image(&self, client: &ApiClient) -> Result<Vec<u8>> {
+        if let Some(ref data) = self.image {
+            let clean = data.find(',').map(|i| &data[i + 1..]).unwrap_or(data);
+            return general_purpose::STANDARD.decode(clean).map_err(|e| anyhow!("Base64 decode failed: {}", e));
+        }
+        match self.result_url {
+            Some(ref url) => client.download_result(url).await,
+            None => Err(anyhow!("{} finished without a result", self.url)),
+        }
+    }
+}
+
+impl StorageBatch {
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let response: StorageResponse = serde_json::from_value(value.clone())
+            .map_err(|e| anyhow!("Unexpected storage response ({}): {}", e, value))?;
+        Ok(Self { jobs: response.results, credits: CreditInfo::from_json(value) })
+    }
+}
+
+// Asks about `urls` with the run's translation settings
+async fn query(client: &ApiClient, urls: &[String], options: &TranslationOptions) -> Result<StorageBatch> {
+    client.call_storage(urls, &options.model, &options.target_lang, &options.font, &options.text_align, options.stroke_disabled, options.inpaint_only, options.min_font_size).await
+}
+
+// `0007_page.jpg`: keeps the page order of the input list and the URL's file name
+fn output_name(index: usize, url: &str) -> String {
+    let last = url.split(['?', '#']).next().unwrap_or(url).rsplit('/').next().unwrap_or("");
+    let name = sanitize_filename::sanitize(last);
+    let name = if name.is_empty() { "image".to_string() } else { name };
+    let has_ext = Path::new(&name).extension().is_some();
+    format!("{:04}_{}{}", index + 1, name, if has_ext { "" } else { ".png" })
+}
+
+/// Translates images by URL through the Storage API: submits them in batches, polls until
+/// every job is finished and saves the results in `output_dir`. Pages that already have an
+/// output are skipped; caps, key pool and reporting work as for local folders.
+pub async fn process_urls(logger: &impl ProgressLogger, urls: &[String], output_dir: &Path, options: &TranslationOptions) -> Result<RunReport> {
+    let mut report = RunReport::default();
+
+    let mut seen = std::collections::HashSet::new();
+    let mut pending: Vec<(String, PathBuf)> = Vec::new();
+    for (index, url) in urls.iter().map(|u| u.trim()).filter(|u| !u.is_empty()).enumerate() {
+        if !seen.insert(url.to_string()) {
+            continue;
+        }
+        // `storage_urls` is a comma-separated list
+        if url.contains(',') {
+            let mut page = PageReport::new(Path::new(url));
+            page.error = Some("URLs containing a comma cannot be sent to the storage API".to_string());
+            report.pages.push(page);
+            continue;
+        }
+        let out_path = output_dir.join(output_name(index, url));
+        if out_path.exists() {
+            report.skipped += 1;
+        } else {
+            pending.push((url.to_string(), out_path));
+        }
+    }
+
+    let estimate = CostEstimate::new(&options.pricing, &options.model, pending.len());
+    let cost = estimate.cost_per_image;
+    report.estimate = Some(estimate.clone());
+    if pending.is_empty() {
+        logger.log(t("INFO_NOTHING_TO_TRANSLATE_URLS", &[]));
+        return Ok(report);
+    }
+    logger.log(t("INFO_TOTAL_COST", &[("count", &estimate.images), ("cost_per_image", &cost), ("total_cost", &estimate.credits)]));
+
+    let db_manager = match options.db {
+        Some(ref db_rwlock) => db_rwlock.read().await.clone(),
+        None => None,
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = options.budget.load_today(db).await {
+            logger.log(t("WARN_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+
+    if options.dry_run {
+        logger.log(t("INFO_DRY_RUN", &[]));
+        return Ok(report);
+    }
+    fs::create_dir_all(output_dir)?;
+
+    let client = options.client();
+    let keys = match &options.profile {
+        Some(profile_rwlock) => KeyPool::from_profile(&*profile_rwlock.read().await, &options.api_key),
+        None => KeyPool::single(&options.api_key),
+    };
+    if let Some(ref db) = db_manager {
+        if let Err(e) = keys.load_usage(db).await {
+            logger.log(t("WARN_KEY_USAGE_UNREAD", &[("error", &e)]));
+        }
+    }
+    let mut autosave = options.profile.clone().map(|p| ProfileAutosave::new(p, AUTOSAVE_INTERVAL));
+
+    let total = pending.len();
+    let mut done = 0;
+    let mut tracker = ProgressTracker::new(total);
+    for chunk in pending.chunks(STORAGE_BATCH_SIZE) {
+        // Only as many pages as the caps still allow
+        let fits = (1..=chunk.len()).take_while(|&n| options.budget.check(cost * n as u64).is_ok()).count();
+        if fits == 0 {
+            if let Err(reason) = options.budget.check(cost) {
+                logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+                report.stopped = Some(reason);
+            }
+            break;
+        }
+        let chunk = &chunk[..fits];
+        let batch_urls: Vec<String> = chunk.iter().map(|(u, _)| u.clone()).collect();
+
+        // Rejected keys (401/402/403) are dropped and the batch goes to the next one
+        logger.event(ProgressEvent::stage(Stage::Uploading));
+        let submitted = loop {
+            let Some(selected) = keys.select(cost * chunk.len() as u64) else {
+                break Err(anyhow!(t("REASON_NO_KEYS", &[])));
+            };
+            let keyed = client.with_api_key(&selected.key);
+            match query(&keyed, &batch_urls, options).await {
+                Err(e) if e.downcast_ref::<KeyRejected>().is_some() => {
+                    logger.log(t("WARN_KEY_REJECTED", &[("key", &selected.label), ("error", &e)]));
+                    keys.reject(selected.index);
+                }
+                result => break result.map(|batch| (batch, selected, keyed)),
+            }
+        };
+
+        let (batch, selected, keyed) = match submitted {
+            Ok(s) => s,
+            Err(e) => {
+                logger.log(t("ERROR_STORAGE_SUBMIT", &[("error", &e)]));
+                for (url, _) in chunk {
+                    let mut page = PageReport::new(Path::new(url));
+                    page.error = Some(e.to_string());
+                    report.pages.push(page);
+                }
+                if !keys.has_usable(cost) {
+                    let reason = t("REASON_NO_KEYS", &[]);
+                    logger.log(t("INFO_STOPPING", &[("reason", &reason)]));
+                    report.stopped = Some(reason);
+                    report.keys_exhausted = true;
+                    break;
+                }
+                continue;
+            }
+        };
+        if batch.credits.remaining.is_some() {
+            report.balance = batch.credits.remaining;
+        }
+
+        // Results are matched to their page by source URL only; URLs the response does not
+        // list yet stay pending until the timeout
+        let mut by_url: HashMap<String, PathBuf> = chunk.iter().cloned().collect();
+        logger.log(t("INFO_STORAGE_SUBMITTED", &[("count", &by_url.len()), ("key", &selected.label)]));
+
+        let started = Instant::now();
+        let mut latest = batch.jobs;
+        while !by_url.is_empty() {
+            for job in latest.iter().filter(|j| j.status != JobState::Pending) {
+                let Some(out_path) = by_url.remove(&job.url) else { continue };
+                let url = job.url.clone();
+                let mut page = PageReport::new(Path::new(&url));
+                page.key = Some(selected.label.clone());
+                done += 1;
+                logger.progress(done, total, t("INFO_STORAGE_PROGRESS", &[("i", &done), ("total", &total), ("url", &url)]));
+
+                if job.status == JobState::Failed {
+                    let err = job.error.clone().unwrap_or_else(|| "failed on the provider's side".to_string());
+                    logger.log(t("ERROR_TRANSLATE_FILE", &[("filename", &url), ("error", &err)]));
+                    page.error = Some(err);
+                    logger.event(page.file_done(&out_path, started.elapsed(), 0, 0));
+                    logger.event(tracker.file_done(0));
+                    report.pages.push(page);
+                    continue;
+                }
+
+                // Charged once the provider finished the job, whether or not the file can be saved
+                let credits = CreditInfo { charged: job.credits_used, remaining: report.balance };
+                record_charge(options, &keys, &mut autosave, &selected, credits, cost, &mut page, &mut report).await;
+                let mut bytes_received = 0;
+                match job.fetch_image(&keyed).await {
+                    Ok(bytes) => {
+                        bytes_received = bytes.len() as u64;
+                        match fs::write(&out_path, bytes) {
+                            Ok(()) => page.status = PageStatus::Translated,
+                            Err(e) => page.error = Some(format!("Save failed: {}", e)),
+                        }
+                    }
+                    Err(e) => page.error = Some(e.to_string()),
+                }
+                if let Some(ref err) = page.error {
+                    logger.log(t("ERROR_SAVE_FILE", &[("filename", &url), ("error", err)]));
+                }
+                // Jobs finish together, so the time is the batch's so far
+                logger.event(page.file_done(&out_path, started.elapsed(), 0, bytes_received));
+                logger.event(tracker.file_done(bytes_received));
+                report.pages.push(page);
+            }
+            if by_url.is_empty() {
+                break;
+            }
+
+            if started.elapsed() > Duration::from_secs(POLL_TIMEOUT_SECS) {
+                for (url, _) in by_url.drain() {
+                    let mut page = PageReport::new(Path::new(&url));
+                    page.key = Some(selected.label.clone());
+                    page.error = Some(format!("No result after {} minutes", POLL_TIMEOUT_SECS / 60));
+                    report.pages.push(page);
+                }
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+            let waiting: Vec<String> = by_url.keys().cloned().collect();
+            match query(&keyed, &waiting, options).await {
+                Ok(polled) => {
+                    if polled.credits.remaining.is_some() {
+                        report.balance = polled.credits.remaining;
+                    }
+                    latest = polled.jobs;
+                }
+                Err(e) => {
+                    // Transient; the timeout above bounds the retries
+                    warn!("STORAGE POLL ERROR: {}", e);
+                    latest = Vec::new();
+                }
+            }
+        }
+    }
+
+    if let Some(ref mut autosave) = autosave {
+        autosave.flush().await;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_documented_response() {
+        let json = serde_json::json!({
+            "results": [
+                {"url": "https://a/1.jpg", "status": "done", "result_url": "https://r/1.png", "credits_used": 1},
+                {"url": "https://a/2.jpg", "status": "pending"}
+            ],
+            "credits_remaining": 41
+        });
+        let batch = StorageBatch::from_json(&json).unwrap();
+        assert_eq!(batch.jobs.len(), 2);
+        assert_eq!(batch.jobs[0].url, "https://a/1.jpg");
+        assert_eq!(batch.jobs[0].status, JobState::Done);
+        assert_eq!(batch.jobs[0].credits_used, Some(1));
+        assert_eq!(batch.jobs[1].status, JobState::Pending);
+        assert_eq!(batch.credits.remaining, Some(41));
+    }
+
+    #[test]
+    fn rejects_other_shapes() {
+        // Jobs without their source URL could only be matched by position
+        assert!(StorageBatch::from_json(&serde_json::json!({"results": [{"id": "x", "status": "done"}]})).is_err());
+        assert!(StorageBatch::from_json(&serde_json::json!({"jobs": []})).is_err());
+        assert!(StorageBatch::from_json(&serde_json::json!([{"url": "https://a/1.jpg", "status": "done"}])).is_err());
+    }
+}
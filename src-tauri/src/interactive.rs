@@ -0,0 +1,179 @@
+// The `interactive` subcommand: the step-by-step flow described by lang/*.ini (API key,
+// target language, model, folder, cost confirmation), ending in a normal CLI run.
+use anyhow::{anyhow, Result};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tapi_lib::commands::file_ops::list_subdirectories;
+use tapi_lib::config::language::{self, t};
+use tapi_lib::config::profile::Profile;
+use tapi_lib::config::profiles::ProfileStore;
+use tapi_lib::config::runtime::RuntimeConfig;
+use tapi_lib::core::api::{ApiClient, ApiEndpoints};
+use tapi_lib::core::catalog::{self, Catalog};
+use tapi_lib::core::database::DatabaseManager;
+use tapi_lib::core::image::find_all_images;
+use tapi_lib::core::pricing::PricingTable;
+use tapi_lib::core::processor::TranslationOptions;
+use tapi_lib::core::report::{RunOutcome, RunReport};
+use tapi_lib::modes;
+use tapi_lib::utils::logger::ConsoleLogger;
+use crate::{load_effective_profile, CliError};
+
+fn ask(prompt: &str) -> Result<String> {
+    print!("{} ", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Err(anyhow!("No input (stdin closed)"));
+    }
+    Ok(line.trim().to_string())
+}
+
+// Y/N prompts; the Turkish strings ask E/H
+fn confirm(prompt: &str) -> Result<bool> {
+    let answer = ask(prompt)?.to_lowercase();
+    Ok(matches!(answer.as_str(), "y" | "yes" | "e" | "evet"))
+}
+
+// Index of a 1-based choice out of `count`, asked again until a valid one is given
+fn choose(prompt: &str, count: usize) -> Result<usize> {
+    loop {
+        match ask(prompt)?.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => return Ok(n - 1),
+            Ok(_) => println!("{}", t("ERROR_INVALID_CHOICE", &[])),
+            Err(_) => println!("{}", t("ERROR_VALUE_ERROR", &[])),
+        }
+    }
+}
+
+// The selected (or active) profile with the config overrides applied and its secrets readable
+pub fn load_profile(config: &RuntimeConfig) -> Result<(String, Profile)> {
+    let (name, profile) = load_effective_profile(config).map_err(|e| CliError::Config(e.to_string()))?;
+    if profile.is_locked() {
+        return Err(CliError::Config("Profile secrets are protected by a master passphrase, set TAPI_PASSPHRASE".to_string()).into());
+    }
+    Ok((name, profile))
+}
+
+pub fn require_models(catalog: &Catalog) -> Result<()> {
+    if catalog.models.is_empty() {
+        return Err(CliError::Config("The provider lists no models".to_string()).into());
+    }
+    Ok(())
+}
+
+/// Walks through the settings of a run over one subfolder of `root`, then translates it.
+/// Returns the run's report, or None when nothing was run (no subfolders, cancelled).
+pub async fn run(config: &RuntimeConfig, root: &Path, dry_run: bool) -> Result<Option<RunReport>> {
+    let store = ProfileStore::new(&config.profile_dir);
+    let (name, mut profile) = load_profile(config)?;
+    language::use_language(profile.message_language());
+
+    if let Some(ref model) = profile.model.clone().filter(|m| !m.is_empty()) {
+        println!("{}", t("INFO_PROFILE_LOADED", &[]));
+        println!("{}", t("INFO_PROFILE_TARGET", &[("target", &profile.language)]));
+        println!("{}", t("INFO_PROFILE_MODEL", &[("model", model)]));
+    }
+
+    // API key: the profile's, or asked for and optionally saved to it
+    if profile.has_api_key() {
+        println!("{}", t("INFO_API_KEY_FOUND", &[]));
+    } else {
+        println!("{}", t("INFO_GET_API_KEY", &[]));
+        println!("{}", t("INFO_EXAMPLE_KEY", &[]));
+        let key = loop {
+            let key = ask(&t("PROMPT_API_KEY", &[]))?;
+            if !key.is_empty() {
+                break key;
+            }
+        };
+        profile.api_key = Some(key.clone());
+        if confirm(&t("PROMPT_SAVE_API_KEY", &[]))? {
+            // Saved onto the stored profile, without the config overrides applied above
+            let mut stored = store.load(&name)?;
+            config.unlock(&mut stored)?;
+            stored.api_key = Some(key);
+            store.save(&name, &stored)?;
+            println!("{}", t("INFO_API_KEY_SAVED", &[("profile", &name)]));
+        }
+    }
+
+    let answer = ask(&format!("{} [{}]", t("PROMPT_TARGET_LANGUAGE", &[]), profile.language))?;
+    let target_lang = if answer.is_empty() { profile.language.clone() } else { answer };
+
+    // The database only caches the model list here
+    if let Some(parent) = config.db_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let db = DatabaseManager::new(config.db_path.clone()).await.ok();
+    let endpoints = ApiEndpoints { catalog: profile.catalog_url.clone(), ..Default::default() };
+    let client = ApiClient::new_with_endpoints(profile.api_key.clone().unwrap_or_default(), endpoints);
+    let catalog = catalog::resolve_catalog(&ConsoleLogger, &client, db.as_ref(), false).await;
+    drop(db);
+    let pricing = PricingTable::from_catalog(&catalog).with_overrides(&profile);
+    require_models(&catalog)?;
+
+    println!("\n{}", t("HEADER_MODEL_SELECTION", &[]));
+    for (i, model) in catalog.models.iter().enumerate() {
+        let label = if model.label.is_empty() { &model.name } else { &model.label };
+        println!("[{}] {} {}", i + 1, label, t("INFO_CREDITS", &[("cost", &pricing.cost(&model.name))]));
+    }
+    let count = catalog.models.len();
+    let model = &catalog.models[choose(&t("PROMPT_SELECT_MODEL", &[("min", &1), ("max", &count)]), count)?];
+
+    println!("\n{}", t("HEADER_FOLDER_SELECTION", &[]));
+    let folders = list_subdirectories(root.to_string_lossy().to_string()).map_err(|e| anyhow!("{}: {}", root.display(), e))?;
+    if folders.is_empty() {
+        println!("{}", t("ERROR_NO_SUBFOLDERS", &[]));
+        return Ok(None);
+    }
+    for (i, folder) in folders.iter().enumerate() {
+        println!("[{}] {}", i + 1, folder);
+    }
+    let folder_name = &folders[choose(&t("PROMPT_SELECT_FOLDER", &[("min", &1), ("max", &folders.len())]), folders.len())?];
+    let folder = root.join(folder_name);
+
+    // Before dedup, so the most this run can cost
+    let images = find_all_images(&folder).len();
+    let cost = pricing.cost(&model.name);
+    println!("\n{}", t("HEADER_COST_CONFIRM", &[]));
+    println!("{}", t("INFO_FOLDER_SELECTED", &[("folder", folder_name), ("count", &images)]));
+    println!("{}", t("INFO_MODEL_SELECTED", &[("model_name", &model.name)]));
+    // Every catalog model is in the pricing table built from that catalog
+    let total_cost = images as u64 * cost;
+    println!("{}", t("INFO_TOTAL_COST", &[("count", &images), ("cost_per_image", &cost), ("total_cost", &total_cost)]));
+    if !confirm(&t("PROMPT_CONFIRM_ACTION", &[]))? {
+        println!("{}", t("INFO_ACTION_CANCELLED", &[]));
+        return Ok(None);
+    }
+
+    println!("\n{}", t("HEADER_TRANSLATION_START", &[]));
+    let api_key = profile.api_key.clone().unwrap_or_default();
+    let font = profile.font.clone().filter(|f| !f.is_empty()).unwrap_or_else(|| "wildwords".to_string());
+    let db = crate::open_run_database(config, profile.database_mode).await;
+    let profile = Some(Arc::new(RwLock::new(profile)));
+    let options = TranslationOptions {
+        model: model.name.clone(),
+        target_lang,
+        font,
+        dry_run,
+        ..TranslationOptions::for_profile(&api_key, profile, db).await
+    };
+    let report = modes::cli_mode::start_cli_translation(&ConsoleLogger, &folder, None, options).await?;
+
+    // A dry run only printed its estimate
+    if dry_run {
+        return Ok(Some(report));
+    }
+    println!("\n{}", t("HEADER_TRANSLATION_DONE", &[]));
+    let (translated, failed) = (report.translated(), report.failed());
+    match report.outcome() {
+        RunOutcome::Success => println!("{}", t("INFO_ALL_DONE", &[])),
+        RunOutcome::Partial => println!("{}", t("INFO_RUN_PARTIAL", &[("translated", &translated), ("failed", &failed)])),
+        RunOutcome::Failed => println!("{}", t("INFO_RUN_FAILED", &[("failed", &failed)])),
+        RunOutcome::Unauthorized => println!("{}", t("INFO_RUN_UNAUTHORIZED", &[])),
+    }
+    Ok(Some(report))
+}
@@ -1,3 +1,7 @@
+// Nested `if let`s are this crate's idiom rather than let chains, and Tauri commands take
+// their arguments one by one
+#![allow(clippy::collapsible_if, clippy::too_many_arguments)]
+
 pub mod commands;
 pub mod config;
 pub mod core;
@@ -8,6 +12,10 @@ pub mod utils;
 use state::AppState;
 use tauri::Manager;
 use std::path::PathBuf;
+use tracing::{error, info};
+
+// Keeps the background log writer alive for the life of the app
+struct LogGuard(#[allow(dead_code)] std::sync::Mutex<Option<tracing_appender::non_blocking::WorkerGuard>>);
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -24,16 +32,33 @@ pub fn run() {
                 // Load settings from config dir
                 let handle = app.handle();
                 let config_dir = handle.path().app_config_dir().unwrap_or(PathBuf::from("."));
-                let profile_path = config_dir.join("profile.json");
-                
-                if profile_path.exists() {
-                    if let Ok(profile) = crate::config::profile::Profile::load(&profile_path) {
+                let profile_store = crate::config::profiles::ProfileStore::new(&config_dir);
+                crate::utils::debug::set_root(&config_dir);
+                crate::config::language::set_dirs(&config_dir);
+
+                // Same TAPI_LOG_* variables as the CLI, files in the platform's log directory
+                let log_dir = handle.path().app_log_dir().unwrap_or_else(|_| config_dir.join("logs"));
+                let log_config = crate::config::runtime::ConfigLayer::from_env()
+                    .and_then(|layer| layer.log_config(log_dir.clone()))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Invalid log settings, using defaults: {}", e);
+                        crate::utils::logger::LogConfig { dir: Some(log_dir), ..Default::default() }
+                    });
+                if let Some(guard) = crate::utils::logger::init_logger(&log_config) {
+                    // Flushed when the app state is dropped on exit
+                    handle.manage(LogGuard(std::sync::Mutex::new(Some(guard))));
+                }
+
+                match profile_store.load_active() {
+                    Ok((_, profile)) => {
                          let state = handle.state::<AppState>();
                          tauri::async_runtime::block_on(async {
+                             state.set_language(profile.message_language()).await;
                              let mut lock = state.profile.write().await;
                              *lock = profile;
                          });
                     }
+                    Err(e) => error!("Failed to load profile: {}", e),
                 }
 
                 // Create translations directory and extract defaults
@@ -63,26 +88,49 @@ pub fn run() {
                             let mut db_lock = state.db.write().await;
                             *db_lock = Some(db_manager);
                         });
-                        println!("[INFO] Database initialized successfully");
+                        info!("Database initialized successfully");
                     }
                     Err(e) => {
-                        eprintln!("[ERROR] Failed to initialize database: {}", e);
+                        error!("Failed to initialize database: {}", e);
                     }
                 }
 
+                // Periodic remote sync (no-op until enabled in the profile)
+                {
+                    let state = handle.state::<AppState>();
+                    tauri::async_runtime::spawn(crate::core::database::run_auto_sync(state.profile.clone(), state.db.clone(), state.settings_changed.clone()));
+                }
+
                 Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::translation::start_translation,
+            commands::translation::start_storage_translation,
+            commands::translation::start_ocr,
+            commands::translation::export_script,
+            commands::translation::render_script,
             commands::file_ops::open_folder,
             commands::file_ops::open_translations_folder,
             commands::settings::save_settings,
             commands::settings::load_settings,
+            commands::settings::list_profiles,
+            commands::settings::create_profile,
+            commands::settings::clone_profile,
+            commands::settings::switch_profile,
+            commands::settings::delete_profile,
+            commands::settings::unlock_profile,
+            commands::settings::set_master_passphrase,
+            commands::settings::export_profile,
+            commands::settings::import_profile,
+            commands::settings::get_catalog,
+            commands::settings::list_debug_bundles,
+            commands::settings::zip_debug_bundle,
             commands::mangadex::download_mangadex_chapter,
             commands::mangadex::save_mangadex_history,
             commands::mangadex::load_mangadex_history,
             commands::downloader::wrapper_download_url,
             commands::downloader::save_base64_image,
+            commands::downloader::list_image_urls,
             commands::file_ops::get_directory_structure,
             commands::file_ops::list_subdirectories,
             commands::database::save_hash_name,
@@ -92,7 +140,20 @@ pub fn run() {
             commands::database::clear_all_database,
             commands::database::pull_remote_database,
             commands::database::push_remote_database,
+            commands::database::sync_remote_database,
             commands::database::test_database_connection,
+            commands::database::export_database,
+            commands::database::import_database,
+            commands::database::import_history_file,
+            commands::database::reconcile_history,
+            commands::database::list_key_usage,
+            commands::database::list_glossary_series,
+            commands::database::list_glossary,
+            commands::database::save_glossary_term,
+            commands::database::delete_glossary_term,
+            commands::database::import_glossary,
+            commands::database::export_glossary,
+            commands::database::detect_series,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -2,67 +2,1114 @@
     all(not(debug_assertions), target_os = "windows"),
     windows_subsystem = "windows"
 )]
+#![allow(clippy::collapsible_if, clippy::too_many_arguments)]
 
-use clap::Parser;
-use std::path::Path;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use tapi_lib::{modes, utils};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tapi_lib::config::language;
+use tapi_lib::config::profile::{DatabaseMode, KeyStrategy, Profile};
+use tapi_lib::config::profiles::ProfileStore;
+use tapi_lib::config::runtime::{ConfigLayer, RuntimeConfig};
+use tapi_lib::core::api::{ApiClient, ApiEndpoints};
+use tapi_lib::core::backup::{self, DumpFormat};
+use tapi_lib::core::catalog::{self, CatalogSource, UnknownCatalogEntry, DEFAULT_MODEL};
+use tapi_lib::core::pricing::PricingTable;
+use tapi_lib::core::database::{DatabaseManager, HashQuery, ImportMode};
+use tapi_lib::core::reconcile::{self, ReconcileMode};
+use tapi_lib::core::script::{Script, ScriptFormat};
+use tapi_lib::core::glossary;
+use tapi_lib::core::ocr::TextFormat;
+use tapi_lib::core::api::KeyRejected;
+use tapi_lib::core::report::{RunOutcome, RunReport};
+use tapi_lib::core::processor::TranslationOptions;
+use tapi_lib::commands::{downloader, mangadex};
+mod interactive;
 mod server;
-use tapi_lib::utils::logger::{ConsoleLogger, log_debug};
+use tapi_lib::utils::logger::{ConsoleLogger, LogFormat};
+
+// Sent to MangaDex, which asks API clients to identify themselves
+const USER_AGENT: &str = concat!("tapi/", env!("CARGO_PKG_VERSION"));
+
+// Exit codes, listed in the help text; 2 is clap's own for usage errors
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_PARTIAL: i32 = 3;
+const EXIT_CONFIG: i32 = 4;
+const EXIT_AUTH: i32 = 5;
 
 const AFTER_HELP: &str = "\
+Without a subcommand the desktop app starts.
+
 EXAMPLES:
-  # Translate a folder using default settings (Grid Mode, Auto Columns)
-   --folder /path/to/manga --api-key YOUR_API_KEY
+  # Pick the API key, language, model and folder step by step
+   interactive
+   interactive --root /path/to/library
+
+  # Translate a folder with the profile's model and font
+   translate /path/to/manga --api-key YOUR_API_KEY
+
+  # Keep the key out of shell history: environment or config file
+   TAPI_API_KEY=YOUR_API_KEY  translate /path/to/manga
+   --config /etc/tapi.toml translate /path/to/manga
+   --config /etc/tapi.toml profile show
+
+  # Translate with a specific model, language and rendering
+   translate /path/to/manga --model deepseek --target-lang tr --text-align center --min-font-size 14
+   translate /path/to/manga --include ch01 --include ch02 --output /path/to/translated
+
+  # Models, prices and fonts the provider offers (cached for a day)
+   models
+   models --refresh
+
+  # See what a run would cost, and cap what it may spend
+   translate /path/to/manga --dry-run
+   translate /path/to/manga --max-credits 200 --max-daily-credits 1000
+
+  # Share a pool of labelled keys (api_keys in the profile) and see what each one spent
+   translate /path/to/manga --profile team --key-strategy failover
+   db key-usage 2026-10-01
+
+  # Keep separate settings per team and run with one of them
+   profile clone default tr-team
+   profile switch tr-team
+   profile set language tr --profile tr-team
+   translate /path/to/manga --profile tr-team
+
+  # Move a profile to another machine (secrets re-encrypted with a passphrase)
+   TAPI_PASSPHRASE=secret  profile export team.json
+   TAPI_PASSPHRASE=secret  profile import team.json --profile team
+
+  # Move the API key and database credentials into the OS keyring
+   profile keyring on --profile tr-team
+
+  # Per-folder overrides: put a .tapi.toml in the input folder
+   target_lang = \"tr\"
+   font = \"animeace\"
+   output = \"../translated\"
+   series = \"One Piece\"
+
+  # Translate images by URL without downloading them (Storage API)
+   translate --urls pages.txt --output /path/to/translated
+
+  # Download a chapter, then translate it
+   download mangadex https://mangadex.org/chapter/UUID --output /path/to/manga --folder-name ch01
+   download url https://example.com/gallery/123 --output /path/to/manga
+
+  # Extract the text of every page (per-page JSON + one Markdown file per chapter)
+   ocr /path/to/manga --text-format markdown
 
-  # Translate with specific model and languages
-   --folder /path/to/manga --api-key KEY --model gemini-1.5-pro --target-lang tr
+  # Proofreading round-trip: export the script, edit the translation column, re-render
+   script export /path/to/manga/ocr_output chapter.csv
+   script render chapter.csv /path/to/manga
 
-  # Disable Grid Mode (process images individually)
-   --folder /path/to/manga --api-key KEY --no-grid
+  # Archives (zip/cbz files)
+   archive /path/to/archives
 
-  # Archive Mode (process zip/cbz files)
-   --folder /path/to/archives --api-key KEY --mode archive
+  # Browse, back up and sync the hash database
+   db list --search \"one piece\"
+   db export backup.jsonl
+   db import backup.csv --mode replace
+   db test && db push
+
+  # Load an existing .f_history into the database
+   db import-history /path/to/manga
+
+  # Per-series glossary (source,target,notes CSV), sent with pages or applied to OCR/script text
+   glossary import terms.csv --series \"One Piece\"
+   glossary export terms.csv --folder /path/to/manga/one_piece
+
+  # Check every .f_history under a library against the database and fix both sides
+   db reconcile /path/to/library --mode both
+
+  # Serve the web UI and API
+   serve --port 8080
+
+  # Debug logs as JSON, rotated at 10 MB (RUST_LOG also works)
+   translate /path/to/manga --log-level debug --log-format json --log-rotation 10mb
+
+  # Record a run for a bug report, then zip what was recorded
+   translate /path/to/manga --debug
+   debug-zip tapi-debug.zip
+
+  # Scripted runs: summary and per-file results as JSON on stdout, logs on stderr
+   translate /path/to/manga --json > result.json
+
+EXIT CODES:
+  0  success (every page translated, or a dry run)
+  1  failure (no page translated, or the command failed)
+  2  invalid command line
+  3  partial failure (some pages failed, or the run stopped early)
+  4  configuration error (config file, profile or options)
+  5  authentication error (no API key, or every key rejected)
 ";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, after_help = AFTER_HELP)]
 struct Args {
-    /// Path to the folder to translate
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+}
+
+// Accepted before or after any subcommand
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
+    /// API Key (visible in shell history, prefer TAPI_API_KEY or --config)
+    #[arg(short, long, global = true)]
+    api_key: Option<String>,
+
+    /// How to pick from the profile's key pool: round_robin, failover or daily_budget
+    #[arg(long, global = true)]
+    key_strategy: Option<KeyStrategy>,
+
+    /// Stop the run before it spends more than N credits
+    #[arg(long, value_name = "N", global = true)]
+    max_credits: Option<u64>,
+
+    /// Stop before today's spending (all keys, needs database mode) exceeds N credits
+    #[arg(long, value_name = "N", global = true)]
+    max_daily_credits: Option<u64>,
+
+    /// Config file (TOML, or JSON by extension); also read from TAPI_CONFIG
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<String>,
+
+    /// Storage API endpoint
+    #[arg(long, global = true)]
+    storage_url: Option<String>,
+
+    /// OCR API endpoint
+    #[arg(long, global = true)]
+    ocr_url: Option<String>,
+
+    /// Translate API endpoint
+    #[arg(long, global = true)]
+    translate_url: Option<String>,
+
+    /// Database mode: off, local or remote
+    #[arg(long, global = true)]
+    database_mode: Option<DatabaseMode>,
+
+    /// Remote database URL (token and password: TAPI_REMOTE_DB_TOKEN / TAPI_REMOTE_DB_PASS or --config)
+    #[arg(long, global = true)]
+    remote_db_url: Option<String>,
+
+    /// Remote database user
+    #[arg(long, global = true)]
+    remote_db_user: Option<String>,
+
+    /// Directory for runtime data such as the database (default: current directory)
+    #[arg(long, value_name = "DIR", global = true)]
+    data_dir: Option<String>,
+
+    /// Log filter such as info, debug or tapi_lib::core::api=debug (RUST_LOG wins)
+    #[arg(long, value_name = "FILTER", global = true)]
+    log_level: Option<String>,
+
+    /// Log format for the console and log files: text or json
+    #[arg(long, global = true)]
+    log_format: Option<LogFormat>,
+
+    /// Rotate the log file daily, never, or at a size such as 10mb
+    #[arg(long, value_name = "daily|never|SIZE", global = true)]
+    log_rotation: Option<String>,
+
+    /// Log file directory (default: <data-dir>/logs)
+    #[arg(long, value_name = "DIR", global = true)]
+    log_dir: Option<String>,
+
+    /// Directory holding the profiles/ folder (default: data directory)
+    #[arg(long, value_name = "DIR", global = true)]
+    profile_dir: Option<String>,
+
+    /// Database directory for CLI and server mode (default: <data-dir>/tapi.db)
+    #[arg(long, global = true)]
+    db_path: Option<String>,
+
+    /// Named profile to use (API key, endpoints, database settings; default: the active profile)
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Record this run's requests, responses, timings and debug log in <data-dir>/debug/
+    #[arg(long, global = true)]
+    debug: bool,
+}
+
+/// The settings of a translation run: the part of `TranslationOptions` a user picks,
+/// shared by every subcommand that translates.
+#[derive(clap::Args, Debug, Clone)]
+struct RunArgs {
+    /// Model to use (default: the profile's model, else the provider's default)
     #[arg(short, long)]
-    folder: Option<String>,
+    model: Option<String>,
 
-    /// API Key
+    /// Target language (default: the profile's language)
     #[arg(short, long)]
-    api_key: Option<String>,
+    target_lang: Option<String>,
+
+    /// Font to use (default: the profile's font, else wildwords)
+    #[arg(long)]
+    font: Option<String>,
+
+    /// Text alignment in the bubbles: auto, left, center or right
+    #[arg(long, default_value = "auto")]
+    text_align: String,
+
+    /// Render text without the outline stroke
+    #[arg(long)]
+    stroke_disabled: bool,
 
-    /// Model to use
-    #[arg(short, long, default_value = "gemini-2.5-flash")]
-    model: String,
+    /// Only remove the original text, without drawing the translation
+    #[arg(long)]
+    inpaint_only: bool,
+
+    /// Smallest font size the text is shrunk to
+    #[arg(long, default_value_t = 12)]
+    min_font_size: u32,
 
-    /// Target language
-    #[arg(short, long, default_value = "en")]
-    target_lang: String,
+    /// Output folder (default: translated/, archive_outputs/ or ocr_output/ in the input folder, ./translated for --urls)
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<String>,
 
-    /// Font to use
-    #[arg(long, default_value = "wildwords")]
-    font: String,
+    /// Only process PATH (relative to the input folder); repeat for more
+    #[arg(long = "include", value_name = "PATH")]
+    included_paths: Vec<String>,
 
-    /// Mode: cli or archive
-    /// Mode: cli or archive
-    #[arg(long, default_value = "cli")]
-    mode: String,
+    /// Only hash and filter, then print the cost estimate without calling the API
+    #[arg(long)]
+    dry_run: bool,
 
-    /// Run as Web Server
+    /// Print the run summary and per-file results as JSON on stdout, and the logs on stderr
     #[arg(long)]
-    server: bool,
+    json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Translate the images in a folder, or image URLs through the Storage API
+    Translate {
+        /// Folder of images
+        #[arg(required_unless_present = "urls")]
+        folder: Option<String>,
+
+        /// Translate the image URLs in FILE (one per line, - for stdin) instead of a folder
+        #[arg(long, value_name = "FILE", conflicts_with = "folder")]
+        urls: Option<String>,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Translate the archives (zip, cbz, ...) in a folder
+    Archive {
+        folder: String,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Extract the text of every page without translating it
+    Ocr {
+        folder: String,
+
+        /// Chapter text file: plain, markdown or tsv
+        #[arg(long, default_value = "plain")]
+        text_format: String,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Bilingual scripts (bubbles with source and translated text) for proofreading
+    #[command(subcommand)]
+    Script(ScriptCommand),
+    /// The hash database: browse, back up, sync and repair it
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Per-series glossaries
+    #[command(subcommand)]
+    Glossary(GlossaryCommand),
+    /// Saved profiles and their settings
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Download chapters and galleries into a folder
+    #[command(subcommand)]
+    Download(DownloadCommand),
+    /// Run as a web server
+    Serve {
+        #[arg(long, default_value_t = 3000)]
+        port: u16,
+
+        #[arg(long, default_value = "0.0.0.0")]
+        host: String,
+    },
+    /// Print the models (with credits per image) and fonts the provider offers
+    Models {
+        /// Fetch the list again even if the cached copy is still fresh
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Zip a debug bundle (the newest, or --bundle NAME) to FILE for a bug report
+    DebugZip {
+        file: String,
+
+        /// Directory name under <data-dir>/debug/
+        #[arg(long, value_name = "NAME")]
+        bundle: Option<String>,
+    },
+    /// Ask for the API key, target language, model and folder, confirm the cost, then translate
+    Interactive {
+        /// Folder whose subfolders are offered (default: current directory)
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        root: String,
+
+        /// Only print the cost estimate after the prompts
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+impl Command {
+    // Whether stdout is reserved for the run summary
+    fn json(&self) -> bool {
+        match self {
+            Command::Translate { run, .. } | Command::Archive { run, .. } | Command::Ocr { run, .. } => run.json,
+            Command::Script(ScriptCommand::Render { run, .. }) => run.json,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ScriptCommand {
+    /// Write the script of an OCR output folder to FILE (.csv or .json)
+    Export {
+        folder: String,
+        file: String,
+    },
+    /// Re-render the images in FOLDER from an edited script, without OCR
+    Render {
+        file: String,
+        folder: String,
+
+        #[command(flatten)]
+        run: RunArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// List entries, newest first
+    List {
+        /// Case-insensitive part of the name, folder or hash
+        #[arg(long)]
+        search: Option<String>,
+
+        #[arg(long)]
+        model: Option<String>,
+
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Delete the entry of one image hash
+    Delete {
+        hash: String,
+    },
+    /// Delete every entry
+    Clear {
+        /// Confirm deleting everything
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Upload local entries to the profile's remote database
+    Push,
+    /// Download entries from the profile's remote database
+    Pull,
+    /// Check that the profile's remote database is reachable
+    Test,
+    /// Export the database to FILE (.jsonl or .csv)
+    Export {
+        file: String,
+
+        /// Dump format, overrides the file extension: jsonl or csv
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Import a database dump from FILE (.jsonl or .csv)
+    Import {
+        file: String,
+
+        /// Dump format, overrides the file extension: jsonl or csv
+        #[arg(long)]
+        format: Option<String>,
+
+        /// merge or replace
+        #[arg(long, default_value = "merge")]
+        mode: String,
+    },
+    /// Import the .f_history of FOLDER
+    ImportHistory {
+        folder: String,
+    },
+    /// Compare every .f_history under ROOT with the database
+    Reconcile {
+        root: String,
+
+        /// report, to_database, to_history or both
+        #[arg(long, default_value = "report")]
+        mode: String,
+    },
+    /// Credits used per API key and day (optionally from SINCE, YYYY-MM-DD)
+    KeyUsage {
+        since: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GlossaryCommand {
+    /// Load a source,target,notes CSV into a series' glossary
+    Import {
+        file: String,
+
+        #[command(flatten)]
+        series: SeriesArgs,
+
+        /// merge or replace
+        #[arg(long, default_value = "merge")]
+        mode: String,
+    },
+    /// Write a series' glossary to a CSV file
+    Export {
+        file: String,
+
+        #[command(flatten)]
+        series: SeriesArgs,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct SeriesArgs {
+    /// Series name
+    #[arg(long, value_name = "NAME", required_unless_present = "folder")]
+    series: Option<String>,
+
+    /// Detect the series from a folder of it instead
+    #[arg(long, value_name = "DIR")]
+    folder: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// Print the effective settings (secrets masked)
+    Show,
+    /// Change one setting of the profile, e.g. `profile set language tr`
+    Set {
+        key: String,
+
+        /// Text, or JSON for numbers, booleans and lists
+        value: String,
+    },
+    /// List the saved profiles
+    List,
+    /// Create a new profile with default settings
+    Create {
+        name: String,
+    },
+    /// Copy a profile
+    Clone {
+        from: String,
+        to: String,
+    },
+    /// Make NAME the active profile
+    Switch {
+        name: String,
+    },
+    /// Delete a profile (not the active one)
+    Delete {
+        name: String,
+    },
+    /// Write the profile to FILE with secrets encrypted by TAPI_PASSPHRASE
+    Export {
+        file: String,
+    },
+    /// Import a profile export from FILE as --profile NAME (default "imported"), using TAPI_PASSPHRASE
+    Import {
+        file: String,
+    },
+    /// Keep the profile's secrets in the OS keyring: on or off (TAPI_KEYRING=mock for a test store)
+    Keyring {
+        #[arg(value_name = "on|off")]
+        mode: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DownloadCommand {
+    /// Download a MangaDex chapter
+    Mangadex {
+        url: String,
+
+        /// Folder the chapter folder is created in
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        output: String,
+
+        /// Name of the chapter folder (default: the chapter id)
+        #[arg(long, value_name = "NAME")]
+        folder_name: Option<String>,
+
+        /// Series folder to put the chapter folder in
+        #[arg(long, value_name = "NAME")]
+        series: Option<String>,
+
+        /// Download the compressed images
+        #[arg(long)]
+        data_saver: bool,
+    },
+    /// Download the images of a gallery page (nhentai, pixiv or any page with images)
+    Url {
+        url: String,
+
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        output: String,
+    },
+}
+
+// Flags form the top configuration layer
+fn cli_layer(args: &GlobalArgs) -> ConfigLayer {
+    ConfigLayer {
+        api_key: args.api_key.clone(),
+        key_strategy: args.key_strategy,
+        max_credits_per_run: args.max_credits,
+        max_credits_per_day: args.max_daily_credits,
+        storage_url: args.storage_url.clone(),
+        ocr_url: args.ocr_url.clone(),
+        translate_url: args.translate_url.clone(),
+        database_mode: args.database_mode,
+        remote_db_url: args.remote_db_url.clone(),
+        remote_db_user: args.remote_db_user.clone(),
+        data_dir: args.data_dir.as_ref().map(PathBuf::from),
+        profile_dir: args.profile_dir.as_ref().map(PathBuf::from),
+        db_path: args.db_path.as_ref().map(PathBuf::from),
+        profile: args.profile.clone(),
+        log_level: args.log_level.clone(),
+        log_format: args.log_format,
+        log_rotation: args.log_rotation.clone(),
+        log_dir: args.log_dir.as_ref().map(PathBuf::from),
+        ..Default::default()
+    }
+}
+
+// Failures with their own exit code; any other error exits with EXIT_FAILURE
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("{0}")]
+    Config(String),
+    #[error("{0}")]
+    Auth(String),
+}
+
+// The selected (or active) profile with the configuration overrides applied
+fn load_effective_profile(config: &RuntimeConfig) -> anyhow::Result<(String, Profile)> {
+    let store = ProfileStore::new(&config.profile_dir);
+    let (name, mut profile) = match config.profile {
+        Some(ref name) => (name.clone(), store.load(name)?),
+        None => store.load_active()?,
+    };
+    config.unlock(&mut profile)?;
+    config.apply(&mut profile);
+    Ok((name, profile))
+}
+
+fn print_config(config: &RuntimeConfig) -> anyhow::Result<()> {
+    let (name, profile) = load_effective_profile(config)?;
+    for (key, value) in config.describe(&profile, &name) {
+        println!("{} = {}", key, value);
+    }
+    Ok(())
+}
+
+async fn open_database(config: &RuntimeConfig) -> anyhow::Result<DatabaseManager> {
+    if let Some(parent) = config.db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    DatabaseManager::new(config.db_path.clone()).await
+}
+
+// The profile's remote database settings, for push, pull and test
+fn remote_profile(config: &RuntimeConfig) -> anyhow::Result<Profile> {
+    let (_, profile) = load_effective_profile(config)?;
+    profile.ensure_unlocked()?;
+    if profile.remote_db_url.is_empty() {
+        return Err(anyhow::anyhow!("Remote URL not set"));
+    }
+    Ok(profile)
+}
+
+async fn run_database_command(command: &DbCommand, config: &RuntimeConfig) -> anyhow::Result<()> {
+    let db = open_database(config).await?;
+    let logger = ConsoleLogger;
+
+    match command {
+        DbCommand::List { search, model, limit } => {
+            let query = HashQuery { search: search.clone(), model: model.clone(), limit: Some(*limit), ..Default::default() };
+            let page = db.query_entries(&query).await?;
+            for entry in &page.entries {
+                println!("{}  {}  {:<16} {:<5} {}", entry.created_at, entry.hash, entry.model, entry.target_lang, entry.name);
+            }
+            println!("{} of {} entries", page.entries.len(), page.total);
+        }
+        DbCommand::Delete { hash } => {
+            db.delete_hash(hash).await?;
+            println!("Deleted {}", hash);
+        }
+        DbCommand::Clear { yes } => {
+            if !yes {
+                return Err(anyhow::anyhow!("This deletes every entry, pass --yes to confirm"));
+            }
+            db.clear_all().await?;
+            println!("Cleared the database");
+        }
+        DbCommand::Push | DbCommand::Pull => {
+            let p = remote_profile(config)?;
+            if p.database_mode != DatabaseMode::Remote {
+                return Err(anyhow::anyhow!("Not in remote mode (database_mode = remote or --database-mode remote)"));
+            }
+            let report = if let DbCommand::Push = command {
+                db.push_to_remote(&p.remote_db_url, &p.remote_db_token, &p.remote_db_user, &p.remote_db_pass, p.sync_batch_size, &logger).await?
+            } else {
+                db.pull_from_remote(&p.remote_db_url, &p.remote_db_token, &p.remote_db_user, &p.remote_db_pass, p.sync_batch_size, &logger).await?
+            };
+            println!("Pushed {}, pulled {}, conflicts {}", report.pushed, report.pulled, report.conflicts);
+        }
+        DbCommand::Test => {
+            let p = remote_profile(config)?;
+            DatabaseManager::test_remote_connection(&p.remote_db_url, &p.remote_db_token, &p.remote_db_user, &p.remote_db_pass)
+                .await
+                .map_err(|e| anyhow::anyhow!("Connection failed: {}", e))?;
+            println!("Connection successful!");
+        }
+        DbCommand::Export { file, format } => {
+            let path = Path::new(file);
+            let format = DumpFormat::resolve(format.as_deref(), path)?;
+            let count = backup::export_to_file(&db, path, format).await?;
+            println!("Exported {} entries to {}", count, path.display());
+        }
+        DbCommand::Import { file, format, mode } => {
+            let path = Path::new(file);
+            let format = DumpFormat::resolve(format.as_deref(), path)?;
+            let mode: ImportMode = mode.parse()?;
+            let report = backup::import_from_file(&db, path, format, mode).await?;
+            println!("Imported {}, skipped {}, invalid {}", report.imported, report.skipped, report.invalid);
+            for err in &report.errors {
+                eprintln!("  {}", err);
+            }
+        }
+        DbCommand::ImportHistory { folder } => {
+            backup::import_history_folder(&logger, &db, Path::new(folder)).await?;
+        }
+        DbCommand::Reconcile { root, mode } => {
+            let mode: ReconcileMode = mode.parse()?;
+            reconcile::reconcile_library(&logger, &db, Path::new(root), mode).await?;
+        }
+        DbCommand::KeyUsage { since } => {
+            for row in db.list_key_usage(since.clone()).await? {
+                println!("{}  {:<24} {:>6} pages {:>8} credits", row.day, row.label, row.pages, row.credits);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl SeriesArgs {
+    fn resolve(&self) -> anyhow::Result<String> {
+        self.series.clone()
+            .or_else(|| self.folder.as_deref().and_then(|f| glossary::find_series(Path::new(f))))
+            .ok_or_else(|| anyhow::anyhow!("No series found for the folder, pass --series NAME"))
+    }
+}
+
+async fn run_glossary_command(command: &GlossaryCommand, config: &RuntimeConfig) -> anyhow::Result<()> {
+    let db = open_database(config).await?;
+    match command {
+        GlossaryCommand::Import { file, series, mode } => {
+            let series = series.resolve()?;
+            let mode: ImportMode = mode.parse()?;
+            let count = glossary::import_from_file(&db, &series, Path::new(file), mode).await?;
+            println!("Imported {} term(s) into the glossary of '{}'", count, series);
+        }
+        GlossaryCommand::Export { file, series } => {
+            let series = series.resolve()?;
+            let count = glossary::export_to_file(&db, &series, Path::new(file)).await?;
+            println!("Exported {} term(s) of '{}' to {}", count, series, file);
+        }
+    }
+    Ok(())
+}
+
+// With database mode on, dedup and per-key usage go through the database
+async fn open_run_database(config: &RuntimeConfig, mode: DatabaseMode) -> Option<Arc<RwLock<Option<DatabaseManager>>>> {
+    if mode == DatabaseMode::Off {
+        return None;
+    }
+    match open_database(config).await {
+        Ok(db) => Some(Arc::new(RwLock::new(Some(db)))),
+        Err(e) => {
+            tracing::warn!("Database unavailable, continuing without it: {}", e);
+            None
+        }
+    }
+}
+
+// One URL per line ("-" reads stdin); blank lines and # comments are ignored
+fn read_url_list(source: &str) -> anyhow::Result<Vec<String>> {
+    let text = if source == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(source).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", source, e))?
+    };
+    Ok(text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// The selected (or active) profile's settings with file/env/flag overrides on top, and the
+// API key (only used when the profile has no key pool)
+fn run_profile(config: &RuntimeConfig, debug: bool, dry_run: bool) -> anyhow::Result<(Profile, String)> {
+    let (_, mut profile) = load_effective_profile(config).map_err(|e| CliError::Config(e.to_string()))?;
+    if profile.is_locked() {
+        return Err(CliError::Config("profile secrets are protected by a master passphrase, set TAPI_PASSPHRASE".to_string()).into());
+    }
+    if debug {
+        profile.save_debug_json = true;
+    }
+    language::use_language(profile.message_language());
+    if !profile.has_api_key() && !dry_run {
+        return Err(CliError::Auth("no API key, set TAPI_API_KEY, api_key in --config, --api-key or a --profile that has one".to_string()).into());
+    }
+    let api_key = profile.api_key.clone().unwrap_or_default();
+    Ok((profile, api_key))
+}
+
+// What a translating subcommand runs over
+enum Job<'a> {
+    Folder(&'a str),
+    Archive(&'a str),
+    Urls(&'a str),
+    Render { script: &'a str, folder: &'a str },
+}
+
+// The run's options: flags first, then the profile's settings, then the defaults
+async fn run_options(config: &RuntimeConfig, debug: bool, run: &RunArgs) -> anyhow::Result<TranslationOptions> {
+    let (profile, api_key) = run_profile(config, debug, run.dry_run)?;
+    let model = run.model.clone()
+        .or_else(|| profile.model.clone().filter(|m| !m.is_empty()))
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let target_lang = run.target_lang.clone()
+        .or_else(|| Some(profile.language.clone()).filter(|l| !l.is_empty()))
+        .unwrap_or_else(|| "en".to_string());
+    let font = run.font.clone()
+        .or_else(|| profile.font.clone().filter(|f| !f.is_empty()))
+        .unwrap_or_else(|| "wildwords".to_string());
+    let db = open_run_database(config, profile.database_mode).await;
+    let profile = Some(Arc::new(RwLock::new(profile)));
 
-    /// Server Port
-    #[arg(long, default_value_t = 3000)]
-    port: u16,
+    Ok(TranslationOptions {
+        model,
+        target_lang,
+        font,
+        text_align: run.text_align.clone(),
+        stroke_disabled: run.stroke_disabled,
+        inpaint_only: run.inpaint_only,
+        min_font_size: run.min_font_size,
+        included_paths: (!run.included_paths.is_empty()).then(|| run.included_paths.clone()),
+        dry_run: run.dry_run,
+        ..TranslationOptions::for_profile(&api_key, profile, db).await
+    })
+}
+
+async fn run_translation(config: &RuntimeConfig, debug: bool, run: &RunArgs, job: Job<'_>) -> anyhow::Result<RunReport> {
+    let options = run_options(config, debug, run).await?;
+    let logger = ConsoleLogger;
+
+    tracing::info!("Running in CLI mode");
+
+    let result = match job {
+        Job::Folder(folder) => modes::cli_mode::start_cli_translation(&logger, Path::new(folder), run.output.clone(), options).await,
+        Job::Archive(folder) => modes::archive_mode::start_archive_translation(&logger, Path::new(folder), run.output.clone(), options).await,
+        Job::Urls(list) => {
+            let urls = read_url_list(list)?;
+            let output = PathBuf::from(run.output.clone().unwrap_or_else(|| "translated".to_string()));
+            modes::storage_mode::start_storage_translation(&logger, &urls, &output, options).await
+        }
+        Job::Render { script, folder } => {
+            modes::script_mode::start_script_render(&logger, Path::new(script), Path::new(folder), run.output.clone(), options).await
+        }
+    };
+    result.map_err(config_error)
+}
+
+// An unknown --model or --font is a configuration error, not a failed run
+fn config_error(e: anyhow::Error) -> anyhow::Error {
+    match e.downcast::<UnknownCatalogEntry>() {
+        Ok(unknown) => CliError::Config(unknown.0).into(),
+        Err(e) => e,
+    }
+}
+
+async fn run_ocr(config: &RuntimeConfig, debug: bool, folder: &str, text_format: &str, run: &RunArgs) -> anyhow::Result<RunReport> {
+    let format: TextFormat = text_format.parse().map_err(|e: anyhow::Error| CliError::Config(e.to_string()))?;
+    let options = run_options(config, debug, run).await?;
+    modes::ocr_mode::start_ocr_run(&ConsoleLogger, Path::new(folder), run.output.clone(), format, options).await
+}
+
+fn export_script(folder: &str, file: &str) -> anyhow::Result<()> {
+    let path = Path::new(file);
+    let script = Script::from_ocr_dir(Path::new(folder))?;
+    script.save(path, ScriptFormat::from_path(path))?;
+    println!("Wrote {} page(s), {} bubble(s) to {}", script.pages.len(), script.bubble_count(), file);
+    Ok(())
+}
+
+async fn list_models(refresh: bool, config: &RuntimeConfig) -> anyhow::Result<()> {
+    let (_, profile) = load_effective_profile(config).map_err(|e| CliError::Config(e.to_string()))?;
+    let endpoints = ApiEndpoints { catalog: profile.catalog_url.clone(), ..Default::default() };
+    let client = ApiClient::new_with_endpoints(profile.api_key.clone().unwrap_or_default(), endpoints);
+
+    // The database only caches the list here, so run without it if it is unavailable
+    let db = open_database(config).await.ok();
 
-    /// Server Host
-    #[arg(long, default_value = "0.0.0.0")]
-    host: String,
+    let catalog = catalog::resolve_catalog(&ConsoleLogger, &client, db.as_ref(), refresh).await;
+    let pricing = PricingTable::from_catalog(&catalog).with_overrides(&profile);
+    let source = match catalog.source {
+        CatalogSource::Provider => "provider".to_string(),
+        CatalogSource::Cache => format!("cache, fetched {}", catalog.fetched_at.map(|at| at.to_rfc3339()).unwrap_or_default()),
+        CatalogSource::Bundled => "bundled list".to_string(),
+    };
+    println!("Models ({}):", source);
+    for model in &catalog.models {
+        println!("  {:<24} {:>4} credit(s)  {}", model.name, pricing.cost(&model.name), model.label);
+    }
+    println!("Fonts:");
+    for font in &catalog.fonts {
+        println!("  {:<24} {}", font.name, font.label);
+    }
+    Ok(())
+}
+
+// --profile, else the active profile
+fn selected_profile_name(store: &ProfileStore, config: &RuntimeConfig) -> anyhow::Result<String> {
+    match config.profile {
+        Some(ref name) => Ok(name.clone()),
+        None => store.active_name(),
+    }
+}
+
+// Strings stay strings; other settings take JSON (numbers, booleans, lists, null)
+fn setting_value(current: Option<&serde_json::Value>, value: &str) -> serde_json::Value {
+    match current {
+        Some(serde_json::Value::String(_)) => serde_json::Value::String(value.to_string()),
+        _ => serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+    }
+}
+
+fn run_profile_command(command: &ProfileCommand, config: &RuntimeConfig) -> anyhow::Result<()> {
+    let store = ProfileStore::new(&config.profile_dir);
+
+    match command {
+        ProfileCommand::Show => print_config(config)?,
+        ProfileCommand::Set { key, value } => {
+            let name = selected_profile_name(&store, config)?;
+            let mut stored = store.load(&name)?;
+            config.unlock(&mut stored)?;
+            stored.ensure_unlocked()?;
+
+            // Validated like a save from the settings panel
+            let mut settings = serde_json::to_value(&stored)?;
+            let fields = settings.as_object_mut().ok_or_else(|| anyhow::anyhow!("Profile is not an object"))?;
+            if !fields.contains_key(key) {
+                return Err(anyhow::anyhow!("Unknown setting '{}'", key));
+            }
+            let parsed = setting_value(fields.get(key), value);
+            fields.insert(key.clone(), parsed);
+            let mut profile = Profile::from_settings_json(settings).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+            profile.keep_runtime_state(&stored);
+
+            store.save(&name, &profile)?;
+            // Turning the keyring off moved the secrets back into the file
+            if stored.use_keyring && !profile.use_keyring {
+                profile.forget_keyring()?;
+            } else {
+                stored.forget_dropped_keys(&profile)?;
+            }
+            println!("Set {} of profile '{}'", key, name);
+        }
+        ProfileCommand::List => {
+            for info in store.list()? {
+                println!("{} {}", if info.active { "*" } else { " " }, info.name);
+            }
+        }
+        ProfileCommand::Create { name } => {
+            store.create(name)?;
+            println!("Created profile '{}'", name);
+        }
+        ProfileCommand::Clone { from, to } => {
+            store.clone_profile(from, to)?;
+            println!("Cloned profile '{}' to '{}'", from, to);
+        }
+        ProfileCommand::Switch { name } => {
+            store.switch(name)?;
+            println!("Switched to profile '{}'", name);
+        }
+        ProfileCommand::Delete { name } => {
+            store.delete(name)?;
+            println!("Deleted profile '{}'", name);
+        }
+        ProfileCommand::Export { file } => {
+            let passphrase = config.overrides.passphrase.as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Set TAPI_PASSPHRASE to encrypt the export"))?;
+            let (name, profile) = load_effective_profile(config)?;
+            std::fs::write(file, profile.export_portable(passphrase)?)?;
+            println!("Exported profile '{}' to {}", name, file);
+        }
+        ProfileCommand::Import { file } => {
+            let passphrase = config.overrides.passphrase.as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Set TAPI_PASSPHRASE to decrypt the import"))?;
+            let name = config.profile.clone().unwrap_or_else(|| "imported".to_string());
+            if store.exists(&name) {
+                return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+            }
+            let profile = Profile::import_portable(&std::fs::read_to_string(file)?, passphrase)?;
+            store.save(&name, &profile)?;
+            println!("Imported profile '{}' from {}", name, file);
+        }
+        ProfileCommand::Keyring { mode } => {
+            let enable = match mode.as_str() {
+                "on" => true,
+                "off" => false,
+                other => return Err(anyhow::anyhow!("Unknown keyring mode '{}' (expected on or off)", other)),
+            };
+            let name = selected_profile_name(&store, config)?;
+            let mut profile = store.load(&name)?;
+            config.unlock(&mut profile)?;
+            profile.ensure_unlocked()?;
+
+            let was_keyring = profile.use_keyring;
+            profile.use_keyring = enable;
+            store.save(&name, &profile)?;
+            if was_keyring && !enable {
+                profile.forget_keyring()?;
+            }
+            println!("Keyring {} for profile '{}'", if enable { "enabled" } else { "disabled" }, name);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_download_command(command: &DownloadCommand) -> anyhow::Result<()> {
+    let logger = ConsoleLogger;
+    let message = match command {
+        DownloadCommand::Mangadex { url, output, folder_name, series, data_saver } => {
+            let folder_name = folder_name.clone()
+                .or_else(|| mangadex::extract_uuid_from_url(url))
+                .ok_or_else(|| anyhow::anyhow!("Invalid MangaDex URL"))?;
+            mangadex::download_chapter(&logger, url.clone(), folder_name, output.clone(), *data_saver, USER_AGENT.to_string(), series.clone()).await
+        }
+        DownloadCommand::Url { url, output } => downloader::download_url(&logger, url.clone(), output.clone(), None).await,
+    }
+    .map_err(|e| anyhow::anyhow!(e))?;
+    println!("{}", message);
+    Ok(())
+}
+
+fn error_code(e: &anyhow::Error) -> i32 {
+    match e.downcast_ref::<CliError>() {
+        Some(CliError::Config(_)) => EXIT_CONFIG,
+        Some(CliError::Auth(_)) => EXIT_AUTH,
+        None if e.downcast_ref::<KeyRejected>().is_some() => EXIT_AUTH,
+        None => EXIT_FAILURE,
+    }
+}
+
+/// What `--json` prints on stdout when a run ends: the report with its totals, or the error.
+#[derive(serde::Serialize)]
+struct RunSummary<'a> {
+    exit_code: i32,
+    outcome: Option<RunOutcome>,
+    dry_run: bool,
+    error: Option<String>,
+    translated: usize,
+    failed: usize,
+    credits: u64,
+    #[serde(flatten)]
+    report: Option<&'a RunReport>,
+}
+
+fn print_summary(code: i32, dry_run: bool, report: Option<&RunReport>, error: Option<&anyhow::Error>) {
+    let summary = RunSummary {
+        exit_code: code,
+        outcome: report.map(RunReport::outcome),
+        dry_run,
+        error: error.map(|e| e.to_string()),
+        translated: report.map_or(0, RunReport::translated),
+        failed: report.map_or(0, RunReport::failed),
+        credits: report.map_or(0, RunReport::credits),
+        report,
+    };
+    match serde_json::to_string_pretty(&summary) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+// A dry run succeeds whatever its pages would have done
+fn outcome_code(dry_run: bool, outcome: RunOutcome) -> i32 {
+    match outcome {
+        _ if dry_run => EXIT_SUCCESS,
+        RunOutcome::Success => EXIT_SUCCESS,
+        RunOutcome::Partial => EXIT_PARTIAL,
+        RunOutcome::Failed => EXIT_FAILURE,
+        RunOutcome::Unauthorized => EXIT_AUTH,
+    }
+}
+
+// The closing line (or JSON summary) of a translating subcommand, and its exit code
+fn finish_run(dry_run: bool, json: bool, result: anyhow::Result<RunReport>) -> i32 {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            let code = error_code(&e);
+            if json {
+                print_summary(code, dry_run, None, Some(&e));
+            }
+            return code;
+        }
+    };
+
+    let outcome = report.outcome();
+    let code = outcome_code(dry_run, outcome);
+    let line = match outcome {
+        _ if dry_run => "Dry run finished, nothing was translated.".to_string(),
+        RunOutcome::Success => "Translation completed successfully.".to_string(),
+        RunOutcome::Partial => format!("Translation incomplete: {} page(s) translated, {} failed.", report.translated(), report.failed()),
+        RunOutcome::Failed => format!("Translation failed: none of the {} page(s) were translated.", report.failed()),
+        RunOutcome::Unauthorized => "Translation failed: no API key was accepted.".to_string(),
+    };
+    if json || code != EXIT_SUCCESS {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+    if json {
+        print_summary(code, dry_run, Some(&report), None);
+    }
+    code
 }
 
 fn main() {
@@ -78,79 +1125,157 @@ fn main() {
         let location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())).unwrap_or_else(|| "unknown".to_string());
         let log_msg = format!("CRITICAL PANIC at {}: {}", location, msg);
         eprintln!("{}", log_msg);
-        log_debug(&log_msg);
+        utils::debug::record_crash(&log_msg);
     }));
 
-    utils::logger::init_logger();
-    
     // Check for CLI arguments
     let args = Args::parse();
 
+    let Some(command) = args.command else {
+        // Run UI mode
+        tapi_lib::run();
+        return;
+    };
 
+    // Stdout is left to the summary
+    let json = command.json();
+    ConsoleLogger::use_stderr(json);
 
-    if args.server {
-        let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-        rt.block_on(async {
-            server::start_server(args.port, &args.host).await;
-        });
-        return;
-    }
+    let config_file = args.global.config.clone().or_else(|| std::env::var("TAPI_CONFIG").ok());
+    let config = match RuntimeConfig::resolve(config_file.as_deref().map(Path::new), cli_layer(&args.global)) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if json {
+                print_summary(EXIT_CONFIG, false, None, Some(&e));
+            }
+            std::process::exit(EXIT_CONFIG);
+        }
+    };
+    utils::debug::set_root(&config.data_dir);
+    language::set_dirs(&config.data_dir);
+    // Flushed before exiting
+    let log_guard = utils::logger::init_logger(&config.log);
+    let debug = args.global.debug;
 
-    if let Some(folder) = args.folder {
-        if let Some(api_key) = args.api_key {
-            // Run CLI mode
-            let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-            rt.block_on(async {
-                let logger = ConsoleLogger;
-                let path = Path::new(&folder);
-                
-                println!("Running in CLI mode...");
-                
-                let result = if args.mode == "archive" {
-                    modes::archive_mode::start_archive_translation(
-                        &logger, 
-                        path, 
-                        &args.model, 
-                        &api_key, 
-                        &args.target_lang, 
-                        &args.font,
-                        "auto", // default text_align
-                        false,
-                        false,
-                        12,
-                        None,
-                        None,
-                        None,
-                        None,
-                    ).await
-                } else {
-                     modes::cli_mode::start_cli_translation(
-                        &logger, 
-                        path, 
-                        &args.model, 
-                        &api_key, 
-                        &args.target_lang, 
-                        &args.font,
-                        "auto",
-                        false,
-                        false,
-                        12,
-                        None,
-                        None,
-                        None,
-                        None,
-                    ).await
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    let code = rt.block_on(async {
+        let result = match command {
+            Command::Translate { folder, urls, run } => {
+                let job = match (urls.as_deref(), folder.as_deref()) {
+                    (Some(list), _) => Job::Urls(list),
+                    (None, folder) => Job::Folder(folder.unwrap_or_default()),
                 };
+                return finish_run(run.dry_run, run.json, run_translation(&config, debug, &run, job).await);
+            }
+            Command::Archive { folder, run } => {
+                return finish_run(run.dry_run, run.json, run_translation(&config, debug, &run, Job::Archive(&folder)).await);
+            }
+            Command::Ocr { folder, text_format, run } => {
+                return finish_run(run.dry_run, run.json, run_ocr(&config, debug, &folder, &text_format, &run).await);
+            }
+            Command::Script(ScriptCommand::Export { folder, file }) => export_script(&folder, &file),
+            Command::Script(ScriptCommand::Render { file, folder, run }) => {
+                let job = Job::Render { script: &file, folder: &folder };
+                return finish_run(run.dry_run, run.json, run_translation(&config, debug, &run, job).await);
+            }
+            Command::Db(command) => run_database_command(&command, &config).await,
+            Command::Glossary(command) => run_glossary_command(&command, &config).await,
+            Command::Profile(command) => run_profile_command(&command, &config),
+            Command::Download(command) => run_download_command(&command).await,
+            Command::Serve { port, host } => {
+                server::start_server(port, &host, config.clone()).await;
+                Ok(())
+            }
+            Command::Models { refresh } => list_models(refresh, &config).await,
+            Command::DebugZip { file, bundle } => utils::debug::zip_bundle(bundle.as_deref(), Path::new(&file))
+                .map(|path| println!("Wrote {}", path.display())),
+            Command::Interactive { root, dry_run } => match interactive::run(&config, Path::new(&root), dry_run).await {
+                // The flow prints its own closing line; only the exit code is left
+                Ok(Some(report)) => return outcome_code(dry_run, report.outcome()),
+                result => result.map(|_| ()),
+            },
+        };
+        match result {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                error_code(&e)
+            }
+        }
+    });
 
-                match result {
-                    Ok(_) => println!("Translation completed successfully."),
-                    Err(e) => eprintln!("Error: {}", e),
-                }
-            });
+    // process::exit skips destructors
+    drop(rt);
+    drop(log_guard);
+    std::process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcomes_map_to_their_exit_codes() {
+        assert_eq!(outcome_code(false, RunOutcome::Success), EXIT_SUCCESS);
+        assert_eq!(outcome_code(false, RunOutcome::Partial), EXIT_PARTIAL);
+        assert_eq!(outcome_code(false, RunOutcome::Failed), EXIT_FAILURE);
+        assert_eq!(outcome_code(false, RunOutcome::Unauthorized), EXIT_AUTH);
+    }
+
+    #[test]
+    fn dry_runs_always_succeed() {
+        for outcome in [RunOutcome::Success, RunOutcome::Partial, RunOutcome::Failed, RunOutcome::Unauthorized] {
+            assert_eq!(outcome_code(true, outcome), EXIT_SUCCESS);
         }
-    } else {
-        // Run UI mode
-        tapi_lib::run();
     }
-}
 
+    #[test]
+    fn errors_map_to_their_exit_codes() {
+        assert_eq!(error_code(&CliError::Config("bad".into()).into()), EXIT_CONFIG);
+        assert_eq!(error_code(&CliError::Auth("no key".into()).into()), EXIT_AUTH);
+        assert_eq!(error_code(&KeyRejected { status: 401, message: "invalid_api_key".into() }.into()), EXIT_AUTH);
+        assert_eq!(error_code(&anyhow::anyhow!("disk full")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn unknown_model_or_font_is_a_config_error() {
+        let unknown = config_error(UnknownCatalogEntry("Unknown model 'nope'".into()).into());
+        assert_eq!(error_code(&unknown), EXIT_CONFIG);
+        assert_eq!(unknown.to_string(), "Unknown model 'nope'");
+
+        let other = config_error(anyhow::anyhow!("disk full"));
+        assert_eq!(error_code(&other), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn unusable_interactive_setups_are_config_errors() {
+        let dir = std::env::temp_dir().join(format!("tapi-interactive-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = |profile: &str| {
+            let layer = ConfigLayer { profile_dir: Some(dir.clone()), profile: Some(profile.to_string()), ..Default::default() };
+            RuntimeConfig::resolve(None, layer).unwrap()
+        };
+
+        let unloadable = interactive::load_profile(&config("no/such")).unwrap_err();
+        assert_eq!(error_code(&unloadable), EXIT_CONFIG);
+
+        let mut locked = Profile::default();
+        locked.api_key = Some("key".into());
+        locked.set_passphrase(Some("secret")).unwrap();
+        ProfileStore::new(&dir).save("locked", &locked).unwrap();
+        let locked = interactive::load_profile(&config("locked")).unwrap_err();
+        assert_eq!(error_code(&locked), EXIT_CONFIG);
+
+        let empty = catalog::Catalog { models: Vec::new(), fonts: Vec::new(), fetched_at: None, source: CatalogSource::Bundled };
+        assert_eq!(error_code(&interactive::require_models(&empty).unwrap_err()), EXIT_CONFIG);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_runs_exit_with_the_error_code() {
+        assert_eq!(finish_run(false, false, Err(CliError::Auth("no key".into()).into())), EXIT_AUTH);
+        assert_eq!(finish_run(true, false, Err(CliError::Config("bad".into()).into())), EXIT_CONFIG);
+    }
+}
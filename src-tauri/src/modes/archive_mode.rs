@@ -1,34 +1,38 @@
 use crate::core::processor::{process_directory, TranslationOptions};
-use crate::core::api::ApiEndpoints;
+use crate::core::glossary;
+use crate::core::report::RunReport;
 use crate::core::archive::{extract_zip, create_zip};
 use crate::core::pdf::extract_images_from_pdf;
+use crate::core::image::is_included;
 use crate::utils::logger::ProgressLogger;
-use crate::config::profile::Profile;
+use crate::config::language::t;
+use crate::utils::progress::file_name;
+use crate::utils::debug;
+use tracing::{info, instrument};
+use crate::config::folder_config::{FolderConfig, FOLDER_CONFIG_FILE};
 use std::path::Path;
 use std::fs;
 use walkdir::WalkDir;
 use anyhow::{Result, anyhow};
-use std::sync::Arc;
-use tokio::sync::RwLock;
 
+#[instrument(name = "run", skip_all, fields(mode = "archive", folder = %folder.display()))]
 pub async fn start_archive_translation(
     logger: &impl ProgressLogger, 
     folder: &Path, 
-    model: &str, 
-    api_key: &str, 
-    target_lang: &str, 
-    font: &str, 
-    text_align: &str,
-    stroke_disabled: bool,
-    inpaint_only: bool,
-    min_font_size: u32,
-    profile: Option<Arc<RwLock<Profile>>>,
-    db: Option<Arc<RwLock<Option<crate::core::database::DatabaseManager>>>>,
     output_folder: Option<String>,
-    included_paths: Option<Vec<String>>
-) -> Result<()> {
-    println!("Starting archive translation in {:?}", folder);
+    mut options: TranslationOptions
+) -> Result<RunReport> {
+    info!("Starting archive translation in {:?}", folder);
     
+    // A .tapi.toml in the input folder wins over the run settings
+    let mut output_folder = output_folder;
+    if let Some(folder_config) = FolderConfig::load(folder)? {
+        let changes = folder_config.apply(folder, &mut options.model, &mut options.target_lang, &mut options.font, &mut output_folder);
+        if !changes.is_empty() {
+            logger.log(t("INFO_FOLDER_OVERRIDES", &[("file", &FOLDER_CONFIG_FILE), ("changes", &changes.join(", "))]));
+        }
+    }
+
     let output_base = if let Some(out) = output_folder {
         Path::new(&out).to_path_buf()
     } else {
@@ -38,116 +42,113 @@ pub async fn start_archive_translation(
 
     let mut archives_found = 0;
     let mut success_count = 0;
+    let mut report = RunReport::default();
 
-    // Get custom endpoints from profile if available
-    let endpoints = if let Some(ref p) = profile {
-        let prof = p.read().await;
-        Some(ApiEndpoints {
-            storage: prof.storage_url.clone(),
-            storage_headers: Some(prof.storage_headers.clone()),
-            ocr: prof.ocr_url.clone(),
-            ocr_headers: Some(prof.ocr_headers.clone()),
-            translate: prof.translate_url.clone(),
-            save_debug_json: prof.save_debug_json,
-        })
-    } else {
-        None
-    };
+    let debug_run = debug::start_mode_run("archive", options.profile.as_ref(), serde_json::json!({ "folder": folder, "model": options.model, "target_lang": options.target_lang, "font": options.font, "dry_run": options.dry_run })).await;
+    debug::recorded(&debug_run, async {
+        // Unknown models/fonts fail here rather than on every page
+        let context = options.preflight(logger).await?;
+        let db_manager = options.database().await;
+        options.glossary = glossary::for_translation_run(logger, db_manager.as_ref(), folder, context).await;
+        // The includes pick archives; every page of a picked archive is translated
+        let includes = options.included_paths.take();
+
+        // Find all archives
+        for entry in WalkDir::new(folder).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_file() {
+                if let Some(ext) = path.extension() {
+                    let ext_str = ext.to_string_lossy().to_lowercase();
+                    if ["zip", "cbz", "pdf"].contains(&ext_str.as_str()) {
+                        // Filter by included_paths if provided
+                        if let Some(ref includes) = includes {
+                            if !is_included(path, folder, includes) {
+                                continue;
+                            }
+                        }
 
-    // Find all archives
-    for entry in WalkDir::new(folder).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if ["zip", "cbz", "pdf"].contains(&ext_str.as_str()) {
-                    // Filter by included_paths if provided
-                    if let Some(ref includes) = included_paths {
-                        let path_str = path.to_string_lossy().to_string();
-                        let is_included = includes.iter().any(|inc| path_str.starts_with(inc));
-                        if !is_included {
+                        archives_found += 1;
+                        logger.log(t("INFO_ARCHIVE_PROCESSING", &[("filename", &file_name(path))]));
+
+                        // 1. Extract
+                        let temp_dir = folder.join("temp_extract");
+                        if temp_dir.exists() { fs::remove_dir_all(&temp_dir)?; }
+                        fs::create_dir_all(&temp_dir)?;
+
+                        let extract_res = if ext_str == "pdf" {
+                            extract_images_from_pdf(path, &temp_dir)
+                        } else {
+                            extract_zip(path, &temp_dir).map(|_| 1) // Zip doesn't return count yet, assume > 0
+                        };
+
+                        if let Err(e) = extract_res {
+                            logger.log(t("ERROR_ARCHIVE_EXTRACT", &[("filename", &file_name(path)), ("error", &e)]));
+                            let _ = fs::remove_dir_all(&temp_dir);
                             continue;
                         }
-                    }
 
-                    archives_found += 1;
-                    logger.log(format!("Processing: {:?}", path.file_name().unwrap_or_default()));
-                    
-                    // 1. Extract
-                    let temp_dir = folder.join("temp_extract");
-                    if temp_dir.exists() { fs::remove_dir_all(&temp_dir)?; }
-                    fs::create_dir_all(&temp_dir)?;
-                    
-                    let extract_res = if ext_str == "pdf" {
-                        extract_images_from_pdf(path, &temp_dir)
-                    } else {
-                        extract_zip(path, &temp_dir).map(|_| 1) // Zip doesn't return count yet, assume > 0
-                    };
-
-                    if let Err(e) = extract_res {
-                        logger.log(format!("Extraction error for {:?}: {}", path.file_name().unwrap_or_default(), e));
-                        let _ = fs::remove_dir_all(&temp_dir);
-                        continue;
-                    }
+                        // 2. Translate
+                        let temp_out = folder.join("temp_translated");
+                        if temp_out.exists() { fs::remove_dir_all(&temp_out)?; }
+
+                        match process_directory(logger, &temp_dir, &temp_out, &options).await {
+                            Ok(archive_report) => report.merge(archive_report),
+                            Err(e) => {
+                                logger.log(t("ERROR_ARCHIVE_TRANSLATE", &[("filename", &file_name(path)), ("error", &e)]));
+                                let _ = fs::remove_dir_all(&temp_dir);
+                                let _ = fs::remove_dir_all(&temp_out);
+                                continue;
+                            }
+                        }
+
+                        // A dry run only counts the pages
+                        if options.dry_run {
+                            success_count += 1;
+                            let _ = fs::remove_dir_all(&temp_dir);
+                            let _ = fs::remove_dir_all(&temp_out);
+                            continue;
+                        }
+
+                        // 3. Repack
+                        let name = path.file_name().unwrap().to_string_lossy();
+                        let out_name = if ext_str == "pdf" {
+                            format!("{}.cbz", name)
+                        } else {
+                            name.to_string()
+                        };
+                        let out_path = output_base.join(out_name);
+
+                        if let Err(e) = create_zip(&temp_out, &out_path) {
+                            logger.log(t("ERROR_ARCHIVE_REPACK", &[("filename", &file_name(path)), ("error", &e)]));
+                        } else {
+                            success_count += 1;
+                            logger.log(t("INFO_ARCHIVE_DONE", &[("path", &out_path.display())]));
+                        }
 
-                    // 2. Translate
-                    let temp_out = folder.join("temp_translated");
-                    if temp_out.exists() { fs::remove_dir_all(&temp_out)?; }
-                    
-                    let options = TranslationOptions {
-                        model: model.to_string(),
-                        api_key: api_key.to_string(),
-                        target_lang: target_lang.to_string(),
-                        font: font.to_string(),
-                        text_align: text_align.to_string(),
-                        stroke_disabled,
-                        inpaint_only,
-                        min_font_size,
-                        profile: profile.clone(),
-                        endpoints: endpoints.clone(),
-                        included_paths: included_paths.clone(), // Pass down for fine-grained image filtering
-                        db: db.clone(),
-                    };
-                    
-                    if let Err(e) = process_directory(logger, &temp_dir, &temp_out, &options).await {
-                        logger.log(format!("Translation error for {:?}: {}", path.file_name().unwrap_or_default(), e));
+                        // Cleanup
                         let _ = fs::remove_dir_all(&temp_dir);
                         let _ = fs::remove_dir_all(&temp_out);
-                        continue;
-                    }
 
-                    // 3. Repack
-                    let file_name = path.file_name().unwrap().to_string_lossy();
-                    let out_name = if ext_str == "pdf" {
-                        format!("{}.cbz", file_name)
-                    } else {
-                        file_name.to_string()
-                    };
-                    let out_path = output_base.join(out_name);
-                    
-                    if let Err(e) = create_zip(&temp_out, &out_path) {
-                        logger.log(format!("Repack error for {:?}: {}", path.file_name().unwrap_or_default(), e));
-                    } else {
-                        success_count += 1;
-                        logger.log(format!("Successfully translated: {}", out_path.display()));
+                        // Out of budget or keys: the remaining archives would fail the same way
+                        if report.stopped.is_some() {
+                            logger.log(t("INFO_ARCHIVE_SKIP_REST", &[]));
+                            break;
+                        }
                     }
-                    
-                    // Cleanup
-                    let _ = fs::remove_dir_all(&temp_dir);
-                    let _ = fs::remove_dir_all(&temp_out);
                 }
             }
         }
-    }
 
-    if archives_found == 0 {
-        return Err(anyhow!("No valid archives (zip, cbz, pdf) found in the selected folder."));
-    }
+        if archives_found == 0 {
+            return Err(anyhow!("No valid archives (zip, cbz, pdf) found in the selected folder."));
+        }
 
-    if success_count == 0 {
-        return Err(anyhow!("Failed to translate any archives in the folder. Check logs for details."));
-    }
+        if success_count == 0 {
+            return Err(anyhow!("Failed to translate any archives in the folder. Check logs for details."));
+        }
 
-    logger.log(format!("Task completed! Processed {}/{} archives successfully.", success_count, archives_found));
-    Ok(())
+        logger.log(t("INFO_ARCHIVES_FINISHED", &[("done", &success_count), ("total", &archives_found)]));
+        report.log_summary(logger);
+        Ok::<_, anyhow::Error>(report)
+    }).await
 }
@@ -1,68 +1,48 @@
 use crate::core::processor::{process_directory, TranslationOptions};
-use crate::core::api::ApiEndpoints;
+use crate::core::glossary;
+use crate::core::report::RunReport;
 use crate::utils::logger::ProgressLogger;
-use crate::config::profile::Profile;
+use crate::config::language::t;
+use crate::utils::debug;
+use tracing::{info, instrument};
+use crate::config::folder_config::{FolderConfig, FOLDER_CONFIG_FILE};
 use std::path::Path;
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::RwLock;
 
+#[instrument(name = "run", skip_all, fields(mode = "cli", folder = %folder.display()))]
 pub async fn start_cli_translation(
     logger: &impl ProgressLogger, 
     folder: &Path, 
-    model: &str, 
-    api_key: &str, 
-    target_lang: &str, 
-    font: &str, 
-    text_align: &str,
-    stroke_disabled: bool,
-    inpaint_only: bool,
-    min_font_size: u32,
-    profile: Option<Arc<RwLock<Profile>>>,
-    db: Option<Arc<RwLock<Option<crate::core::database::DatabaseManager>>>>,
     output_folder: Option<String>,
-    included_paths: Option<Vec<String>>
-) -> Result<()> {
-    println!("Starting CLI translation for {:?}", folder);
+    mut options: TranslationOptions
+) -> Result<RunReport> {
+    info!("Starting CLI translation for {:?}", folder);
     
+    // A .tapi.toml in the input folder wins over the run settings
+    let mut output_folder = output_folder;
+    if let Some(folder_config) = FolderConfig::load(folder)? {
+        let changes = folder_config.apply(folder, &mut options.model, &mut options.target_lang, &mut options.font, &mut output_folder);
+        if !changes.is_empty() {
+            logger.log(t("INFO_FOLDER_OVERRIDES", &[("file", &FOLDER_CONFIG_FILE), ("changes", &changes.join(", "))]));
+        }
+    }
+
     let output_dir = if let Some(out) = output_folder {
         Path::new(&out).to_path_buf()
     } else {
         folder.join("translated")
     };
-    
-    // Get custom endpoints from profile if available
-    let endpoints = if let Some(ref p) = profile {
-        let prof = p.read().await;
-        Some(ApiEndpoints {
-            storage: prof.storage_url.clone(),
-            storage_headers: Some(prof.storage_headers.clone()),
-            ocr: prof.ocr_url.clone(),
-            ocr_headers: Some(prof.ocr_headers.clone()),
-            translate: prof.translate_url.clone(),
-            save_debug_json: prof.save_debug_json,
-        })
-    } else {
-        None
-    };
-    
-    let options = TranslationOptions {
-        model: model.to_string(),
-        api_key: api_key.to_string(),
-        target_lang: target_lang.to_string(),
-        font: font.to_string(),
-        text_align: text_align.to_string(),
-        stroke_disabled,
-        inpaint_only,
-        min_font_size,
-        profile,
-        endpoints,
-        included_paths,
-        db,
-    };
 
-    process_directory(logger, folder, &output_dir, &options).await?;
-    
-    Ok(())
-}
+    let debug_run = debug::start_mode_run("cli", options.profile.as_ref(), serde_json::json!({ "folder": folder, "model": options.model, "target_lang": options.target_lang, "font": options.font, "output": output_dir, "dry_run": options.dry_run })).await;
+    debug::recorded(&debug_run, async {
+        // Unknown models/fonts fail here rather than on every page
+        let context = options.preflight(logger).await?;
+        let db_manager = options.database().await;
+        options.glossary = glossary::for_translation_run(logger, db_manager.as_ref(), folder, context).await;
 
+        let report = process_directory(logger, folder, &output_dir, &options).await?;
+        report.log_summary(logger);
+
+        Ok::<_, anyhow::Error>(report)
+    }).await
+}
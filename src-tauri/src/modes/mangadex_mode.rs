@@ -1,7 +1,8 @@
 use anyhow::Result;
+use tracing::info;
 
 #[allow(dead_code)]
 pub async fn start_mangadex_download() -> Result<()> {
-    println!("Starting MangaDex download");
+    info!("Starting MangaDex download");
     Ok(())
 }
@@ -1,3 +1,6 @@
 pub mod cli_mode;
 pub mod archive_mode;
 pub mod mangadex_mode;
+pub mod storage_mode;
+pub mod ocr_mode;
+pub mod script_mode;
@@ -0,0 +1,39 @@
+use crate::core::ocr::{process_ocr_directory, TextFormat};
+use crate::core::processor::TranslationOptions;
+use crate::core::glossary;
+use crate::core::report::RunReport;
+use crate::utils::logger::ProgressLogger;
+use crate::utils::debug;
+use tracing::{info, instrument};
+use std::path::Path;
+use anyhow::Result;
+
+/// OCR only: extracts the text of every page under `folder` (for proofreaders and
+/// translators working outside the app) without translating or rendering anything.
+/// Only the key, caps, endpoints, included paths and dry run of `options` apply.
+#[instrument(name = "run", skip_all, fields(mode = "ocr", folder = %folder.display()))]
+pub async fn start_ocr_run(
+    logger: &impl ProgressLogger,
+    folder: &Path,
+    output_folder: Option<String>,
+    format: TextFormat,
+    options: TranslationOptions
+) -> Result<RunReport> {
+    info!("Starting OCR for {:?}", folder);
+
+    let output_dir = match output_folder {
+        Some(out) => Path::new(&out).to_path_buf(),
+        None => folder.join("ocr_output"),
+    };
+
+    let debug_run = debug::start_mode_run("ocr", options.profile.as_ref(), serde_json::json!({ "folder": folder, "output": output_dir, "format": format.extension(), "dry_run": options.dry_run })).await;
+    debug::recorded(&debug_run, async {
+        let db_manager = options.database().await;
+        let glossary = glossary::load_for_folder(logger, db_manager.as_ref(), folder).await;
+
+        let report = process_ocr_directory(logger, folder, &output_dir, format, glossary.as_ref(), &options).await?;
+        report.log_summary(logger);
+
+        Ok::<_, anyhow::Error>(report)
+    }).await
+}
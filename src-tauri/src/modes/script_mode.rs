@@ -0,0 +1,43 @@
+use crate::core::script::{render_script, Script};
+use crate::core::processor::TranslationOptions;
+use crate::core::report::RunReport;
+use crate::utils::logger::ProgressLogger;
+use crate::config::language::t;
+use crate::utils::debug;
+use tracing::{info, instrument};
+use std::path::Path;
+use anyhow::Result;
+
+/// Applies an edited script (JSON or CSV) to the images in `folder` and writes the re-rendered
+/// pages to `output_folder` (default: `<folder>/translated`). No OCR or translation is run.
+#[instrument(name = "run", skip_all, fields(mode = "script", script = %script_path.display(), folder = %folder.display()))]
+pub async fn start_script_render(
+    logger: &impl ProgressLogger,
+    script_path: &Path,
+    folder: &Path,
+    output_folder: Option<String>,
+    mut options: TranslationOptions
+) -> Result<RunReport> {
+    info!("Rendering script {:?} onto {:?}", script_path, folder);
+
+    let script = Script::load(script_path)?;
+    logger.log(t("INFO_SCRIPT_LOADED", &[("pages", &script.pages.len()), ("bubbles", &script.bubble_count())]));
+
+    let output_dir = match output_folder {
+        Some(out) => Path::new(&out).to_path_buf(),
+        None => folder.join("translated"),
+    };
+    // The script's text is always drawn
+    options.inpaint_only = false;
+
+    let debug_run = debug::start_mode_run("script", options.profile.as_ref(), serde_json::json!({ "script": script_path, "folder": folder, "model": options.model, "target_lang": options.target_lang, "font": options.font, "output": output_dir, "dry_run": options.dry_run })).await;
+    debug::recorded(&debug_run, async {
+        // Rendering is priced like a page of the given model
+        options.preflight(logger).await?;
+
+        let report = render_script(logger, &script, folder, &output_dir, &options).await?;
+        report.log_summary(logger);
+
+        Ok::<_, anyhow::Error>(report)
+    }).await
+}
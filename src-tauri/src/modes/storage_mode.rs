@@ -0,0 +1,38 @@
+use crate::core::storage::process_urls;
+use crate::core::processor::TranslationOptions;
+use crate::core::report::RunReport;
+use crate::utils::logger::ProgressLogger;
+use crate::utils::debug;
+use tracing::{info, instrument};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+/// Translates remote images without downloading them first: the provider fetches the URLs
+/// through the Storage API and the results land in `output_folder`.
+#[instrument(name = "run", skip_all, fields(mode = "storage", urls = urls.len()))]
+pub async fn start_storage_translation(
+    logger: &impl ProgressLogger,
+    urls: &[String],
+    output_folder: &Path,
+    mut options: TranslationOptions
+) -> Result<RunReport> {
+    info!("Starting storage translation of {} URL(s)", urls.len());
+
+    if urls.iter().all(|u| u.trim().is_empty()) {
+        return Err(anyhow!("No image URLs given"));
+    }
+    if let Some(bad) = urls.iter().map(|u| u.trim()).find(|u| !(u.is_empty() || u.starts_with("http://") || u.starts_with("https://"))) {
+        return Err(anyhow!("Not an http(s) URL: {}", bad));
+    }
+
+    let debug_run = debug::start_mode_run("storage", options.profile.as_ref(), serde_json::json!({ "urls": urls, "model": options.model, "target_lang": options.target_lang, "font": options.font, "output": output_folder, "dry_run": options.dry_run })).await;
+    debug::recorded(&debug_run, async {
+        // Unknown models/fonts fail here rather than on every page
+        options.preflight(logger).await?;
+
+        let report = process_urls(logger, urls, output_folder, &options).await?;
+        report.log_summary(logger);
+
+        Ok::<_, anyhow::Error>(report)
+    }).await
+}
@@ -1,4 +1,5 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Router, Json, response::IntoResponse,
     http::{StatusCode, header},
@@ -7,17 +8,36 @@ use tower_http::cors::CorsLayer;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use rust_embed::RustEmbed;
-use tapi_lib::config::profile::Profile;
-use tapi_lib::utils::logger::ProgressLogger;
-use tokio::sync::{broadcast, RwLock};
+use tapi_lib::config::profile::{Profile, SettingsError};
+use tapi_lib::config::language;
+use tapi_lib::config::profiles::ProfileStore;
+use tapi_lib::config::runtime::RuntimeConfig;
+use tapi_lib::utils::logger::{trace_event, trace_log, trace_progress, ProgressLogger};
+use tapi_lib::utils::progress::ProgressEvent;
+use tapi_lib::utils::debug;
+use tokio::sync::{broadcast, Notify, RwLock};
 use serde::Deserialize;
-use std::path::{Path, PathBuf};
-use tapi_lib::core::database::{DatabaseManager, HashEntryOutput};
+use std::path::{Component, Path, PathBuf};
+use tapi_lib::core::database::{DatabaseManager, HashPage, HashQuery};
+use tapi_lib::core::database::{run_auto_sync, ImportMode};
+use tapi_lib::core::backup::{self, DumpFormat};
+use tapi_lib::core::reconcile::{self, ReconcileMode};
+use tapi_lib::core::api::{ApiClient, ApiEndpoints};
+use tapi_lib::core::catalog::resolve_catalog;
+use tapi_lib::core::ocr::TextFormat;
+use tapi_lib::core::processor::TranslationOptions;
+use tapi_lib::core::script::{Script, ScriptFormat};
+use tapi_lib::core::glossary::{self, GlossaryTerm};
+use tapi_lib::modes;
+use tracing::{error, info};
 
 #[derive(RustEmbed)]
 #[folder = "../build/"] // Svelte build output
 struct Assets;
 
+// Database dumps are posted as one body; axum's default 2 MB cap is far below a real library
+const MAX_IMPORT_BYTES: usize = 512 * 1024 * 1024;
+
 #[allow(dead_code)]
 struct ServerLogger {
     tx: broadcast::Sender<String>,
@@ -25,60 +45,136 @@ struct ServerLogger {
 
 impl ProgressLogger for ServerLogger {
     fn log(&self, message: String) {
+        trace_log(&message);
         let _ = self.tx.send(format!("LOG:{}", message));
     }
     fn progress(&self, current: usize, total: usize, message: String) {
+        trace_progress(current, total, &message);
         let _ = self.tx.send(format!("PROG:{}:{}:{}", current, total, message));
     }
+    fn event(&self, event: ProgressEvent) {
+        trace_event(&event);
+        if let Ok(json) = serde_json::to_string(&event) {
+            let _ = self.tx.send(format!("EVT:{}", json));
+        }
+    }
 }
 
 #[derive(Clone)]
 struct AppState {
     profile: Arc<RwLock<Profile>>,
+    profiles: Arc<ProfileStore>,
     db: Arc<RwLock<Option<DatabaseManager>>>,
-    _tx: broadcast::Sender<String>,
+    tx: broadcast::Sender<String>,
+    settings_changed: Arc<Notify>,
+    // Env/config-file/flag overrides, applied to every profile loaded and kept out of saves
+    config: Arc<RuntimeConfig>,
+}
+
+// The active profile as stored on disk, unlocked as far as this process can
+fn stored_profile(state: &AppState, current: &Profile) -> anyhow::Result<Profile> {
+    let (_, mut stored) = state.profiles.load_active()?;
+    state.config.unlock(&mut stored)?;
+    stored.unlock_like(current)?;
+    Ok(stored)
 }
 
-pub async fn start_server(port: u16, host: &str) {
+pub async fn start_server(port: u16, host: &str, config: RuntimeConfig) {
     let (tx, _) = broadcast::channel(100);
     
-    // Load profile
-    let profile = Arc::new(RwLock::new(Profile::load(Path::new("profile.json")).unwrap_or_default()));
+    // Load the selected (or active) profile; a selected one becomes active so saves land there
+    let profiles = Arc::new(ProfileStore::new(&config.profile_dir));
+    let loaded = match config.profile {
+        Some(ref name) => profiles.switch(name),
+        None => profiles.load_active().map(|(_, p)| p),
+    };
+    let mut profile = match loaded {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to load profile: {}", e);
+            Profile::default()
+        }
+    };
+    if let Err(e) = config.unlock(&mut profile) {
+        error!("Failed to unlock profile: {}", e);
+    }
+    // Env/config-file overrides live in memory only; saves write the stored values back
+    config.apply(&mut profile);
+    language::use_language(profile.message_language());
+    let profile = Arc::new(RwLock::new(profile));
     
     // Initialize DB
-    let db_path = PathBuf::from("tapi.db");
-    let db = match DatabaseManager::new(db_path).await {
+    if let Some(parent) = config.db_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let db = match DatabaseManager::new(config.db_path.clone()).await {
         Ok(dm) => Arc::new(RwLock::new(Some(dm))),
         Err(_) => Arc::new(RwLock::new(None)),
     };
     
+    let settings_changed = Arc::new(Notify::new());
+    tokio::spawn(run_auto_sync(profile.clone(), db.clone(), settings_changed.clone()));
+
     let state = AppState {
         profile,
+        profiles,
         db,
-        _tx: tx,
+        tx,
+        settings_changed,
+        config: Arc::new(config),
     };
 
     let app = Router::new()
         .route("/api/status", get(status_handler))
         .route("/api/settings/load", get(load_settings))
         .route("/api/settings/save", post(save_settings))
+        .route("/api/profiles/list", get(list_profiles))
+        .route("/api/profiles/create", post(create_profile))
+        .route("/api/profiles/clone", post(clone_profile))
+        .route("/api/profiles/switch", post(switch_profile))
+        .route("/api/profiles/delete", post(delete_profile))
+        .route("/api/profiles/unlock", post(unlock_profile))
+        .route("/api/profiles/passphrase", post(set_master_passphrase))
+        .route("/api/profiles/export", post(export_profile))
+        .route("/api/profiles/import", post(import_profile))
         .route("/api/database/list", get(list_hash_names))
         .route("/api/database/save", post(save_hash_name))
         .route("/api/database/delete", post(delete_hash_entry))
         .route("/api/database/clear", post(clear_all_database))
         .route("/api/database/pull", post(pull_remote_database))
         .route("/api/database/push", post(push_remote_database))
+        .route("/api/database/sync", post(sync_remote_database))
         .route("/api/database/test", post(test_database_connection))
+        .route("/api/database/export", get(export_database))
+        .route("/api/database/import", post(import_database).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)))
+        .route("/api/database/import_history", post(import_history_file))
+        .route("/api/database/reconcile", post(reconcile_history))
+        .route("/api/keys/usage", get(list_key_usage))
+        .route("/api/glossary", get(list_glossary))
+        .route("/api/glossary/series", get(list_glossary_series))
+        .route("/api/glossary/save", post(save_glossary_term))
+        .route("/api/glossary/delete", post(delete_glossary_term))
+        .route("/api/glossary/export", get(export_glossary))
+        .route("/api/glossary/import", post(import_glossary))
+        .route("/api/glossary/detect", get(detect_series))
+        .route("/api/catalog", get(get_catalog))
+        .route("/api/debug/bundles", get(list_debug_bundles))
+        .route("/api/debug/zip", get(zip_debug_bundle))
         .route("/api/translate/cli", post(start_cli))
+        .route("/api/translate/estimate", post(estimate_translation))
+        .route("/api/translate/storage", post(storage_translation))
+        .route("/api/ocr", post(ocr_run))
+        .route("/api/script/export", post(export_script))
+        .route("/api/script/render", post(render_script))
         .fallback(static_handler)
         .layer(CorsLayer::permissive())
         .with_state(state);
 
     let addr: SocketAddr = format!("{}:{}", host, port).parse().expect("Invalid address");
-    println!("Server running at http://{}", addr);
+    info!("Server running at http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
 async fn static_handler(uri: axum::http::Uri) -> impl IntoResponse {
@@ -109,19 +205,226 @@ async fn status_handler() -> impl IntoResponse {
     "SSE/WebSocket placeholder" 
 }
 
-async fn load_settings(axum::extract::State(state): axum::extract::State<AppState>) -> Json<Profile> {
+// For requests that hand out decrypted keys and header values or write wherever the client
+// says: only the machine running the server may make them
+fn loopback_only(peer: SocketAddr, what: &str) -> Result<(), (StatusCode, String)> {
+    if peer.ip().is_loopback() {
+        return Ok(());
+    }
+    Err((StatusCode::FORBIDDEN, format!("{} is only available from the machine running the server", what)))
+}
+
+async fn load_settings(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>
+) -> impl IntoResponse {
+    if let Err(denied) = loopback_only(peer, "Reading the settings") {
+        return denied.into_response();
+    }
     let profile = state.profile.read().await;
-    Json(profile.clone())
+    Json(profile.clone()).into_response()
 }
 
 async fn save_settings(
     axum::extract::State(state): axum::extract::State<AppState>,
-    Json(new_profile): Json<Profile>
-) -> StatusCode {
+    Json(body): Json<serde_json::Value>
+) -> impl IntoResponse {
+    // The UI client posts its invoke args, i.e. `{ "settings": {...} }`
+    let body = body.get("settings").cloned().unwrap_or(body);
+    let mut new_profile = match Profile::from_settings_json(body) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(e)).into_response(),
+    };
+
     let mut p = state.profile.write().await;
-    *p = new_profile.clone();
-    let _ = p.save(Path::new("profile.json"));
-    StatusCode::OK
+    new_profile.keep_runtime_state(&p);
+    // Overridden fields the user did not touch keep their stored values
+    match stored_profile(&state, &p) {
+        Ok(stored) => state.config.restore(&mut new_profile, &stored),
+        Err(e) => {
+            let error = SettingsError::Failed { message: format!("Failed to read the stored profile: {}", e) };
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    }
+    let was_keyring = p.use_keyring;
+    if let Err(e) = state.profiles.save_active(&mut new_profile) {
+        let error = SettingsError::Failed { message: format!("Failed to save profile: {}", e) };
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+    }
+    if was_keyring && !new_profile.use_keyring {
+        let _ = new_profile.forget_keyring();
+    } else {
+        let _ = p.forget_dropped_keys(&new_profile);
+    }
+    language::use_language(new_profile.message_language());
+    state.config.apply(&mut new_profile);
+    *p = new_profile;
+    state.settings_changed.notify_one();
+    StatusCode::OK.into_response()
+}
+
+async fn list_profiles(axum::extract::State(state): axum::extract::State<AppState>) -> impl IntoResponse {
+    match state.profiles.list() {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileNameRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CloneProfileRequest {
+    from: String,
+    to: String,
+}
+
+async fn create_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<ProfileNameRequest>
+) -> impl IntoResponse {
+    match state.profiles.create(&req.name) {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn clone_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<CloneProfileRequest>
+) -> impl IntoResponse {
+    match state.profiles.clone_profile(&req.from, &req.to) {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn switch_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    Json(req): Json<ProfileNameRequest>
+) -> impl IntoResponse {
+    // Answers with the new profile, secrets included
+    if let Err(denied) = loopback_only(peer, "Switching profiles") {
+        return denied.into_response();
+    }
+    match state.profiles.switch(&req.name) {
+        Ok(mut new_profile) => {
+            if let Err(e) = state.config.unlock(&mut new_profile) {
+                error!("Failed to unlock profile: {}", e);
+            }
+            state.config.apply(&mut new_profile);
+            language::use_language(new_profile.message_language());
+            let mut p = state.profile.write().await;
+            *p = new_profile.clone();
+            state.settings_changed.notify_one();
+            Json(new_profile).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn delete_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<ProfileNameRequest>
+) -> impl IntoResponse {
+    match state.profiles.delete(&req.name) {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PassphraseRequest {
+    passphrase: Option<String>,
+}
+
+async fn unlock_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    Json(req): Json<PassphraseRequest>
+) -> impl IntoResponse {
+    if let Err(denied) = loopback_only(peer, "Unlocking the profile") {
+        return denied.into_response();
+    }
+    let mut p = state.profile.write().await;
+    match p.unlock(req.passphrase.as_deref().unwrap_or("")) {
+        Ok(_) => Json(p.clone()).into_response(),
+        Err(e) => (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+    }
+}
+
+async fn set_master_passphrase(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<PassphraseRequest>
+) -> impl IntoResponse {
+    let mut p = state.profile.write().await;
+    let mut updated = p.clone();
+    // Re-encrypt what is stored, not the overrides layered on top of it
+    match stored_profile(&state, &p) {
+        Ok(stored) => state.config.restore(&mut updated, &stored),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+    if let Err(e) = updated.set_passphrase(req.passphrase.as_deref()) {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    match state.profiles.save_active(&mut updated) {
+        Ok(_) => {
+            state.config.apply(&mut updated);
+            *p = updated;
+            StatusCode::OK.into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+// The export is returned as a download, encrypted with the given passphrase. It holds every
+// secret of the profile, so only clients on this machine may ask for it.
+async fn export_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    Json(req): Json<PassphraseRequest>
+) -> impl IntoResponse {
+    if let Err(denied) = loopback_only(peer, "Profile export") {
+        return denied.into_response();
+    }
+    let p = state.profile.read().await;
+    match p.export_portable(req.passphrase.as_deref().unwrap_or("")) {
+        Ok(content) => (
+            [
+                (header::CONTENT_TYPE, "application/json".to_string()),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"tapi_profile.json\"".to_string()),
+            ],
+            content,
+        ).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ImportProfileRequest {
+    name: String,
+    passphrase: String,
+    content: String,
+}
+
+async fn import_profile(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<ImportProfileRequest>
+) -> impl IntoResponse {
+    if state.profiles.exists(&req.name) {
+        return (StatusCode::CONFLICT, format!("Profile '{}' already exists", req.name)).into_response();
+    }
+    let profile = match Profile::import_portable(&req.content, &req.passphrase) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    match state.profiles.save(&req.name, &profile) {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -148,13 +451,14 @@ async fn save_hash_name(
 }
 
 async fn list_hash_names(
-    axum::extract::State(state): axum::extract::State<AppState>
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<HashQuery>,
 ) -> impl IntoResponse {
     let db_lock = state.db.read().await;
 
     if let Some(db) = db_lock.as_ref() {
-        match db.list_all().await {
-            Ok(entries) => Json::<Vec<HashEntryOutput>>(entries).into_response(),
+        match db.query_entries(&query).await {
+            Ok(page) => Json::<HashPage>(page).into_response(),
             Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
     } else {
@@ -201,16 +505,17 @@ async fn clear_all_database(
 async fn pull_remote_database(
     axum::extract::State(state): axum::extract::State<AppState>
 ) -> impl IntoResponse {
-    let (url, token, user, pass) = {
+    let (url, token, user, pass, batch_size) = {
         let p = state.profile.read().await;
-        (p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone())
+        (p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone(), p.sync_batch_size)
     };
 
     let db_lock = state.db.read().await;
 
     if let Some(db) = db_lock.as_ref() {
-        match db.pull_from_remote(&url, &token, &user, &pass).await {
-            Ok(_) => StatusCode::OK.into_response(),
+        let logger = ServerLogger { tx: state.tx.clone() };
+        match db.pull_from_remote(&url, &token, &user, &pass, batch_size, &logger).await {
+            Ok(report) => Json(report).into_response(),
             Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
     } else {
@@ -221,16 +526,38 @@ async fn pull_remote_database(
 async fn push_remote_database(
     axum::extract::State(state): axum::extract::State<AppState>
 ) -> impl IntoResponse {
-    let (url, token, user, pass) = {
+    let (url, token, user, pass, batch_size) = {
         let p = state.profile.read().await;
-        (p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone())
+        (p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone(), p.sync_batch_size)
     };
 
     let db_lock = state.db.read().await;
 
     if let Some(db) = db_lock.as_ref() {
-        match db.push_to_remote(&url, &token, &user, &pass).await {
-            Ok(_) => StatusCode::OK.into_response(),
+        let logger = ServerLogger { tx: state.tx.clone() };
+        match db.push_to_remote(&url, &token, &user, &pass, batch_size, &logger).await {
+            Ok(report) => Json(report).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+async fn sync_remote_database(
+    axum::extract::State(state): axum::extract::State<AppState>
+) -> impl IntoResponse {
+    let (url, token, user, pass, batch_size) = {
+        let p = state.profile.read().await;
+        (p.remote_db_url.clone(), p.remote_db_token.clone(), p.remote_db_user.clone(), p.remote_db_pass.clone(), p.sync_batch_size)
+    };
+
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        let logger = ServerLogger { tx: state.tx.clone() };
+        match db.sync_with_remote(&url, &token, &user, &pass, batch_size, &logger).await {
+            Ok(report) => Json(report).into_response(),
             Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
     } else {
@@ -253,6 +580,573 @@ async fn test_database_connection(
     }
 }
 
+#[derive(Deserialize)]
+struct DumpQuery {
+    format: Option<String>,
+    mode: Option<String>,
+}
+
+async fn export_database(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<DumpQuery>,
+) -> impl IntoResponse {
+    // No file name to guess from, so a missing format means JSON-lines
+    let format = match DumpFormat::resolve(query.format.as_deref(), Path::new("")) {
+        Ok(format) => format,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        let entries = match db.export_entries().await {
+            Ok(entries) => entries,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        let mut body = Vec::new();
+        if let Err(e) = backup::write_dump(&entries, format, &mut body) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+        let (mime, file_name) = match format {
+            DumpFormat::Csv => ("text/csv", "tapi_db.csv"),
+            DumpFormat::Jsonl => ("application/x-ndjson", "tapi_db.jsonl"),
+        };
+        (
+            [
+                (header::CONTENT_TYPE, mime.to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+            ],
+            body,
+        ).into_response()
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+// The dump is sent as the raw request body; format and mode come from the query string
+async fn import_database(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<DumpQuery>,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    // No file name to guess from, so a missing format means JSON-lines
+    let format = match DumpFormat::resolve(query.format.as_deref(), Path::new("")) {
+        Ok(format) => format,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let mode: ImportMode = match query.mode.as_deref().unwrap_or("merge").parse() {
+        Ok(mode) => mode,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match backup::import_from_reader(db, body.as_ref(), format, mode).await {
+            Ok(report) => Json(report).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ImportHistoryRequest {
+    folder: String,
+}
+
+async fn import_history_file(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<ImportHistoryRequest>
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        let logger = ServerLogger { tx: state.tx.clone() };
+        match backup::import_history_folder(&logger, db, Path::new(&req.folder)).await {
+            Ok(report) => Json(report).into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ReconcileRequest {
+    root: String,
+    mode: Option<String>,
+}
+
+async fn reconcile_history(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    Json(req): Json<ReconcileRequest>
+) -> impl IntoResponse {
+    let mode: ReconcileMode = match req.mode.as_deref().unwrap_or("report").parse() {
+        Ok(mode) => mode,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    // Repairing the history writes history files under any root the client names
+    if matches!(mode, ReconcileMode::ToHistory | ReconcileMode::Both) {
+        if let Err(denied) = loopback_only(peer, "Repairing history files") {
+            return denied.into_response();
+        }
+    }
+
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        let logger = ServerLogger { tx: state.tx.clone() };
+        match reconcile::reconcile_library(&logger, db, Path::new(&req.root), mode).await {
+            Ok(report) => Json(report).into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct KeyUsageQuery {
+    since: Option<String>,
+}
+
+async fn list_key_usage(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<KeyUsageQuery>,
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match db.list_key_usage(query.since).await {
+            Ok(rows) => Json(rows).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct GlossaryQuery {
+    series: String,
+    mode: Option<String>,
+}
+
+async fn list_glossary(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<GlossaryQuery>,
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match db.list_glossary(&glossary::series_key(&query.series)).await {
+            Ok(terms) => Json(terms).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+async fn list_glossary_series(
+    axum::extract::State(state): axum::extract::State<AppState>,
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match db.list_glossary_series().await {
+            Ok(series) => Json(series).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+async fn save_glossary_term(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(term): Json<GlossaryTerm>,
+) -> impl IntoResponse {
+    if term.source.trim().is_empty() || term.target.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Source and target are required".to_string()).into_response();
+    }
+    let term = GlossaryTerm {
+        series: glossary::series_key(&term.series),
+        source: term.source.trim().to_string(),
+        target: term.target.trim().to_string(),
+        notes: term.notes.filter(|n| !n.trim().is_empty()),
+    };
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match db.save_glossary_term(&term).await {
+            Ok(_) => StatusCode::OK.into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteGlossaryTermRequest {
+    series: String,
+    source: String,
+}
+
+async fn delete_glossary_term(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<DeleteGlossaryTermRequest>,
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match db.delete_glossary_term(&glossary::series_key(&req.series), &req.source).await {
+            Ok(_) => StatusCode::OK.into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+/// The series' glossary as a CSV download.
+async fn export_glossary(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<GlossaryQuery>,
+) -> impl IntoResponse {
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        let terms = match db.list_glossary(&glossary::series_key(&query.series)).await {
+            Ok(terms) => terms,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        let mut body = Vec::new();
+        if let Err(e) = glossary::write_csv(&terms, &mut body) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+        let file_name = format!("glossary_{}.csv", glossary::series_key(&query.series).replace(' ', "_"));
+        (
+            [
+                (header::CONTENT_TYPE, "text/csv".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+            ],
+            body,
+        ).into_response()
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+/// Loads a CSV body into the series' glossary.
+async fn import_glossary(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<GlossaryQuery>,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    let mode: ImportMode = match query.mode.as_deref().unwrap_or("merge").parse() {
+        Ok(mode) => mode,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let db_lock = state.db.read().await;
+
+    if let Some(db) = db_lock.as_ref() {
+        match glossary::import_from_reader(db, &query.series, body.as_ref(), mode).await {
+            Ok(count) => Json(count).into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DetectSeriesQuery {
+    #[serde(alias = "folderPath")]
+    folder_path: String,
+}
+
+async fn detect_series(axum::extract::Query(query): axum::extract::Query<DetectSeriesQuery>) -> impl IntoResponse {
+    Json(glossary::find_series(Path::new(&query.folder_path)))
+}
+
+#[derive(Deserialize)]
+struct CatalogQuery {
+    refresh: Option<bool>,
+}
+
+async fn get_catalog(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<CatalogQuery>,
+) -> impl IntoResponse {
+    let client = {
+        let profile = state.profile.read().await;
+        let endpoints = ApiEndpoints { catalog: profile.catalog_url.clone(), ..Default::default() };
+        ApiClient::new_with_endpoints(profile.api_key.clone().unwrap_or_default(), endpoints)
+    };
+    let db = state.db.read().await.clone();
+    let logger = ServerLogger { tx: state.tx.clone() };
+
+    Json(resolve_catalog(&logger, &client, db.as_ref(), query.refresh.unwrap_or(false)).await)
+}
+
+async fn list_debug_bundles() -> impl IntoResponse {
+    Json(debug::list_bundles())
+}
+
+#[derive(Deserialize)]
+struct DebugZipQuery {
+    name: Option<String>,
+}
+
+/// The debug bundle (newest when `name` is missing) as a zip download.
+async fn zip_debug_bundle(
+    axum::extract::Query(query): axum::extract::Query<DebugZipQuery>,
+) -> impl IntoResponse {
+    let name = query.name.filter(|n| !n.trim().is_empty());
+    let tmp = std::env::temp_dir().join(format!("tapi-debug-{}-{}.zip", std::process::id(), chrono::Utc::now().timestamp_micros()));
+    let result = debug::zip_bundle(name.as_deref(), &tmp).and_then(|path| Ok(std::fs::read(path)?));
+    let _ = std::fs::remove_file(&tmp);
+    match result {
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"tapi-debug.zip\"".to_string()),
+            ],
+            body,
+        ).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct EstimateRequest {
+    folder: String,
+    model: String,
+    mode: Option<String>,
+    target_lang: Option<String>,
+    font: Option<String>,
+    output_folder: Option<String>,
+    included_paths: Option<Vec<String>>,
+}
+
+/// Dry run: hashes and filters the folder like a real run and returns the cost estimate.
+async fn estimate_translation(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<EstimateRequest>
+) -> impl IntoResponse {
+    let logger = ServerLogger { tx: state.tx.clone() };
+    let path = Path::new(&req.folder);
+    // An estimate never calls the API, so no key is needed
+    let options = TranslationOptions {
+        model: req.model,
+        target_lang: req.target_lang.unwrap_or_else(|| "en".to_string()),
+        font: req.font.unwrap_or_else(|| "wildwords".to_string()),
+        included_paths: req.included_paths,
+        dry_run: true,
+        ..TranslationOptions::for_profile("", Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    let result = if req.mode.as_deref() == Some("archive") {
+        modes::archive_mode::start_archive_translation(&logger, path, req.output_folder, options).await
+    } else {
+        modes::cli_mode::start_cli_translation(&logger, path, req.output_folder, options).await
+    };
+
+    match result {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct StorageRequest {
+    urls: Vec<String>,
+    output_folder: String,
+    model: String,
+    target_lang: Option<String>,
+    font: Option<String>,
+    dry_run: Option<bool>,
+}
+
+/// Translates images by URL through the Storage API into `output_folder` on the server.
+async fn storage_translation(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    Json(req): Json<StorageRequest>
+) -> impl IntoResponse {
+    let logger = ServerLogger { tx: state.tx.clone() };
+    let dry_run = req.dry_run.unwrap_or(false);
+    // The output folder is not tied to any input folder, so there is nothing to contain it in
+    if !dry_run {
+        if let Err(denied) = loopback_only(peer, "Storage translation") {
+            return denied.into_response();
+        }
+    }
+    let api_key = {
+        let profile = state.profile.read().await;
+        if !profile.has_api_key() && !dry_run {
+            return (StatusCode::BAD_REQUEST, language::t("ERROR_API_KEY_NOT_SET", &[])).into_response();
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+    let options = TranslationOptions {
+        model: req.model,
+        target_lang: req.target_lang.unwrap_or_else(|| "en".to_string()),
+        font: req.font.unwrap_or_else(|| "wildwords".to_string()),
+        dry_run,
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    let result = modes::storage_mode::start_storage_translation(&logger, &req.urls, Path::new(&req.output_folder), options).await;
+
+    match result {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct OcrRequest {
+    folder: String,
+    output_folder: Option<String>,
+    text_format: Option<String>,
+    included_paths: Option<Vec<String>>,
+}
+
+/// OCR only: per-page JSON and chapter text files, written on the server.
+async fn ocr_run(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<OcrRequest>
+) -> impl IntoResponse {
+    let logger = ServerLogger { tx: state.tx.clone() };
+    let api_key = {
+        let profile = state.profile.read().await;
+        if !profile.has_api_key() {
+            return (StatusCode::BAD_REQUEST, language::t("ERROR_API_KEY_NOT_SET", &[])).into_response();
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+    let format: TextFormat = match req.text_format.as_deref().unwrap_or("plain").parse() {
+        Ok(f) => f,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let folder = Path::new(&req.folder);
+    let output_folder = match req.output_folder.map(|o| contained_path(folder, &o, "OCR output")).transpose() {
+        Ok(o) => o.map(|p| p.to_string_lossy().into_owned()),
+        Err(e) => return (StatusCode::FORBIDDEN, e).into_response(),
+    };
+
+    let options = TranslationOptions {
+        included_paths: req.included_paths,
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    let result = modes::ocr_mode::start_ocr_run(&logger, folder, output_folder, format, options).await;
+
+    match result {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ScriptExportRequest {
+    ocr_folder: String,
+    path: String,
+}
+
+// Where a client may make the server write: inside `root` only (a relative path is taken from
+// there), so clients cannot overwrite other files on the server
+fn contained_path(root: &Path, path: &str, what: &str) -> Result<PathBuf, String> {
+    let root = root.canonicalize().map_err(|e| format!("{}: {}", root.display(), e))?;
+    let path = Path::new(path);
+    let target = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+    let escapes = target.components().any(|c| matches!(c, Component::ParentDir));
+    // Output folders usually do not exist yet, so their nearest existing ancestor decides
+    let existing = target.ancestors().find_map(|a| a.canonicalize().ok());
+    match existing {
+        Some(existing) if !escapes && existing.starts_with(&root) => Ok(target),
+        _ => Err(format!("{} can only be written inside {}", what, root.display())),
+    }
+}
+
+// Where an exported script may go: inside the OCR output folder only
+fn script_export_path(ocr_folder: &Path, path: &str) -> Result<PathBuf, String> {
+    if Path::new(path).file_name().is_none() {
+        return Err("The script path has no file name".to_string());
+    }
+    contained_path(ocr_folder, path, "Scripts")
+}
+
+/// Writes the bilingual script of an OCR output folder on the server, inside that folder.
+async fn export_script(Json(req): Json<ScriptExportRequest>) -> impl IntoResponse {
+    let ocr_folder = Path::new(&req.ocr_folder);
+    let path = match script_export_path(ocr_folder, &req.path) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::FORBIDDEN, e).into_response(),
+    };
+    let result = Script::from_ocr_dir(ocr_folder).and_then(|script| {
+        script.save(&path, ScriptFormat::from_path(&path))?;
+        Ok(script)
+    });
+    match result {
+        Ok(script) => Json(script).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ScriptRenderRequest {
+    script_path: String,
+    folder: String,
+    output_folder: Option<String>,
+    model: String,
+    target_lang: Option<String>,
+    font: Option<String>,
+    dry_run: Option<bool>,
+}
+
+/// Re-renders pages from an edited script on the server.
+async fn render_script(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Json(req): Json<ScriptRenderRequest>
+) -> impl IntoResponse {
+    let logger = ServerLogger { tx: state.tx.clone() };
+    let dry_run = req.dry_run.unwrap_or(false);
+    let api_key = {
+        let profile = state.profile.read().await;
+        if !profile.has_api_key() && !dry_run {
+            return (StatusCode::BAD_REQUEST, language::t("ERROR_API_KEY_NOT_SET", &[])).into_response();
+        }
+        profile.api_key.clone().unwrap_or_default()
+    };
+    let folder = Path::new(&req.folder);
+    let output_folder = match req.output_folder.map(|o| contained_path(folder, &o, "Rendered pages")).transpose() {
+        Ok(o) => o.map(|p| p.to_string_lossy().into_owned()),
+        Err(e) => return (StatusCode::FORBIDDEN, e).into_response(),
+    };
+    let options = TranslationOptions {
+        model: req.model,
+        target_lang: req.target_lang.unwrap_or_else(|| "en".to_string()),
+        font: req.font.unwrap_or_else(|| "wildwords".to_string()),
+        dry_run,
+        ..TranslationOptions::for_profile(&api_key, Some(state.profile.clone()), Some(state.db.clone())).await
+    };
+
+    let result = modes::script_mode::start_script_render(&logger, Path::new(&req.script_path), folder, output_folder, options).await;
+
+    match result {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize)]
 struct CliRequest {
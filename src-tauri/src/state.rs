@@ -1,13 +1,15 @@
-use crate::config::{language::Language, profile::Profile};
+use crate::config::{language::{self, Language}, profile::Profile};
 use crate::core::database::DatabaseManager;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 
 pub struct AppState {
-    #[allow(dead_code)]
+    /// Backend messages' language, following the profile's `message_language`
     pub language: RwLock<Language>,
     pub profile: Arc<RwLock<Profile>>,
     pub db: Arc<RwLock<Option<DatabaseManager>>>,
+    /// Wakes the auto-sync loop after the active profile's settings changed
+    pub settings_changed: Arc<Notify>,
 }
 
 impl AppState {
@@ -17,10 +19,18 @@ impl AppState {
             language: RwLock::new(Language::default()),
             profile: Arc::new(RwLock::new(Profile::default())),
             db: Arc::new(RwLock::new(None)),
+            settings_changed: Arc::new(Notify::new()),
         }
     }
 }
 
+impl AppState {
+    pub async fn set_language(&self, code: &str) {
+        let loaded = language::use_language(code);
+        *self.language.write().await = (*loaded).clone();
+    }
+}
+
 impl Default for AppState {
     fn default() -> Self {
         Self::new()
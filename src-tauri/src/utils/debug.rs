@@ -0,0 +1,489 @@
+use crate::config::profile::Profile;
+use crate::config::runtime::mask_secret;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use walkdir::WalkDir;
+
+/// Debug bundles live in `<data dir>/debug/<run>/`
+pub const DEBUG_DIR: &str = "debug";
+/// Panics, written synchronously so they survive an abort
+pub const CRASH_LOG: &str = "crash_debug.log";
+/// Bundles kept; the oldest are deleted when a run starts
+pub const MAX_BUNDLES: usize = 10;
+/// Past this, a bundle stops saving response bodies (metadata is still written)
+pub const MAX_BUNDLE_BYTES: u64 = 50 * 1024 * 1024;
+// Longest body saved for a single response
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+// The crash log is rotated to `crash_debug.log.1` past this
+const MAX_CRASH_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+tokio::task_local! {
+    // The bundle of the run this task belongs to (see `recorded`)
+    static CURRENT: Option<Arc<DebugBundle>>;
+}
+
+/// Sets where bundles and the crash log go. Called once at startup; until then (and in
+/// hosts that never call it) `./debug` is used.
+pub fn set_root(data_dir: &Path) {
+    if let Ok(mut root) = ROOT.write() {
+        *root = Some(data_dir.join(DEBUG_DIR));
+    }
+}
+
+pub fn root() -> PathBuf {
+    ROOT.read().ok()
+        .and_then(|r| r.clone())
+        .unwrap_or_else(|| PathBuf::from(DEBUG_DIR))
+}
+
+/// Everything recorded about one run.
+pub struct DebugBundle {
+    dir: PathBuf,
+    started: Instant,
+    bytes: AtomicU64,
+    requests: AtomicUsize,
+    // `log.txt`, kept open for the whole run
+    log: Mutex<Option<File>>,
+    // Values of the profile's custom storage/OCR headers, masked wherever they are sent
+    masked: Vec<String>,
+}
+
+impl DebugBundle {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn write(&self, name: &str, content: &[u8]) {
+        let path = self.dir.join(name);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if fs::write(&path, content).is_ok() {
+            self.bytes.fetch_add(content.len() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn append(&self, name: &str, line: &str) {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(self.dir.join(name)) {
+            if writeln!(file, "{}", line).is_ok() {
+                self.bytes.fetch_add(line.len() as u64 + 1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.bytes.load(Ordering::Relaxed) >= MAX_BUNDLE_BYTES
+    }
+}
+
+fn active() -> Option<Arc<DebugBundle>> {
+    CURRENT.try_with(|b| b.clone()).ok().flatten()
+}
+
+/// Runs `run` with the guard's bundle as the one its requests, timings and debug lines go
+/// to. The bundle belongs to this task, so runs going on at the same time (server requests,
+/// several windows) each record only their own.
+pub async fn recorded<F: std::future::Future>(guard: &Option<RunGuard>, run: F) -> F::Output {
+    CURRENT.scope(guard.as_ref().map(|g| g.bundle.clone()), run).await
+}
+
+/// A run's bundle; finishes `run.json` on drop.
+pub struct RunGuard {
+    bundle: Arc<DebugBundle>,
+}
+
+impl RunGuard {
+    pub fn dir(&self) -> &Path {
+        self.bundle.dir()
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let path = self.bundle.dir.join("run.json");
+        if let Some(mut run) = fs::read(&path).ok().and_then(|b| serde_json::from_slice::<Value>(&b).ok()) {
+            run["finished_at"] = json!(chrono::Local::now().to_rfc3339());
+            run["elapsed_ms"] = json!(self.bundle.started.elapsed().as_millis() as u64);
+            run["requests"] = json!(self.bundle.requests.load(Ordering::Relaxed));
+            run["bytes"] = json!(self.bundle.bytes.load(Ordering::Relaxed));
+            let _ = fs::write(&path, serde_json::to_vec_pretty(&run).unwrap_or_default());
+        }
+    }
+}
+
+/// Opens a new bundle for a run when `enabled` (the profile's `save_debug_json`). `settings`
+/// is stored redacted in `run.json`; header values listed in `masked` are never written.
+/// Old bundles beyond `MAX_BUNDLES` are removed first. Nothing is recorded until the run's
+/// work goes through `recorded`.
+pub fn start_run(mode: &str, enabled: bool, settings: &impl Serialize, masked: Vec<String>) -> Option<RunGuard> {
+    if !enabled {
+        return None;
+    }
+
+    let root = root();
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut dir = root.join(format!("{}-{}", stamp, mode));
+    let mut n = 1;
+    while dir.exists() {
+        n += 1;
+        dir = root.join(format!("{}-{}-{}", stamp, mode, n));
+    }
+    if let Err(e) = fs::create_dir_all(&dir) {
+        tracing::warn!("Debug bundle could not be created in {:?}: {}", dir, e);
+        return None;
+    }
+    rotate(&root, &dir);
+
+    let bundle = Arc::new(DebugBundle {
+        started: Instant::now(),
+        bytes: AtomicU64::new(0),
+        requests: AtomicUsize::new(0),
+        log: Mutex::new(OpenOptions::new().create(true).append(true).open(dir.join("log.txt")).ok()),
+        masked,
+        dir,
+    });
+
+    let mut settings = serde_json::to_value(settings).unwrap_or(Value::Null);
+    redact(&mut settings);
+    let run = json!({
+        "mode": mode,
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "started_at": chrono::Local::now().to_rfc3339(),
+        "settings": settings,
+    });
+    bundle.write("run.json", &serde_json::to_vec_pretty(&run).unwrap_or_default());
+    Some(RunGuard { bundle })
+}
+
+/// `start_run` for a translation mode: the run arguments plus the profile, when the
+/// profile asks for debug output. Modes run their whole body under `recorded` with the
+/// returned guard, so the bundle gets everything the run does.
+pub async fn start_mode_run(mode: &str, profile: Option<&Arc<tokio::sync::RwLock<Profile>>>, args: Value) -> Option<RunGuard> {
+    let prof = profile?.read().await;
+    start_run(mode, prof.save_debug_json, &json!({ "args": args, "profile": &*prof }), custom_header_values(&prof))
+}
+
+// Custom headers are sent under whatever names the provider wants, so their values are
+// masked by value rather than by header name
+fn custom_header_values(profile: &Profile) -> Vec<String> {
+    [&profile.storage_headers, &profile.ocr_headers].into_iter()
+        .filter_map(|text| serde_json::from_str::<serde_json::Map<String, Value>>(text).ok())
+        .flat_map(|headers| headers.into_iter().filter_map(|(_, v)| v.as_str().map(str::to_string)))
+        .filter(|v| !v.trim().is_empty())
+        .collect()
+}
+
+// Bundle names start with a timestamp, so name order is age order
+fn bundle_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root).map(|rd| rd
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn rotate(root: &Path, keep: &Path) {
+    let dirs: Vec<PathBuf> = bundle_dirs(root).into_iter().filter(|d| d != keep).collect();
+    let excess = (dirs.len() + 1).saturating_sub(MAX_BUNDLES);
+    for dir in dirs.iter().take(excess) {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase().replace('-', "_");
+    name == "authorization" || name == "cookie" || name == "key" || name.ends_with("_headers")
+        || ["api_key", "token", "pass", "secret", "kdf"].iter().any(|w| name.contains(w))
+}
+
+/// Masks every field whose name looks like it holds a credential, at any depth.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (name, v) in map.iter_mut() {
+                if is_secret_name(name) {
+                    *v = match v {
+                        Value::String(s) => Value::String(mask_secret(s)),
+                        Value::Null => Value::Null,
+                        _ => Value::String("****".to_string()),
+                    };
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn headers_json(headers: &HeaderMap, masked: &[String]) -> Value {
+    let map = headers.iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or("<binary>");
+            let secret = is_secret_name(name.as_str()) || masked.iter().any(|m| m == value);
+            let value = if secret { mask_secret(value) } else { value.to_string() };
+            (name.to_string(), Value::String(value))
+        })
+        .collect();
+    Value::Object(map)
+}
+
+/// One API call being recorded. `start` returns None when no bundle is active, so callers
+/// pay nothing outside debug runs.
+pub struct Exchange {
+    bundle: Arc<DebugBundle>,
+    name: String,
+    request: Value,
+    started: Instant,
+}
+
+impl Exchange {
+    pub fn start(kind: &str, request: &reqwest::Request) -> Option<Self> {
+        let bundle = active()?;
+        let seq = bundle.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        Some(Self {
+            name: format!("requests/{:04}-{}.json", seq, kind),
+            request: json!({
+                "method": request.method().as_str(),
+                "url": request.url().as_str(),
+                "headers": headers_json(request.headers(), &bundle.masked),
+            }),
+            started: Instant::now(),
+            bundle,
+        })
+    }
+
+    /// Records the response. Bodies are kept when they are text (JSON is stored inline);
+    /// images and other binary bodies are only measured.
+    pub fn finish(self, status: u16, headers: &HeaderMap, body: &[u8]) {
+        let textual = headers.get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| ct.contains("json") || ct.starts_with("text/"))
+            .unwrap_or(false)
+            || (status >= 400 && std::str::from_utf8(body).is_ok());
+
+        let body_json = if self.bundle.full() {
+            json!({ "omitted": "bundle size cap reached", "bytes": body.len() })
+        } else if !textual {
+            json!({ "omitted": "binary", "bytes": body.len() })
+        } else if let Ok(parsed) = serde_json::from_slice::<Value>(body) {
+            if body.len() > MAX_BODY_BYTES {
+                json!({ "omitted": "too large", "bytes": body.len() })
+            } else {
+                parsed
+            }
+        } else {
+            let text = String::from_utf8_lossy(&body[..body.len().min(MAX_BODY_BYTES)]).to_string();
+            json!({ "text": text, "bytes": body.len(), "truncated": body.len() > MAX_BODY_BYTES })
+        };
+
+        let record = json!({
+            "request": self.request,
+            "response": {
+                "status": status,
+                "headers": headers_json(headers, &self.bundle.masked),
+                "body": body_json,
+            },
+            "elapsed_ms": self.started.elapsed().as_millis() as u64,
+        });
+        self.bundle.write(&self.name, &serde_json::to_vec_pretty(&record).unwrap_or_default());
+    }
+
+    /// Records a request that never got a response (network error, timeout).
+    pub fn fail(self, error: &str) {
+        let record = json!({
+            "request": self.request,
+            "error": error,
+            "elapsed_ms": self.started.elapsed().as_millis() as u64,
+        });
+        self.bundle.write(&self.name, &serde_json::to_vec_pretty(&record).unwrap_or_default());
+    }
+}
+
+/// Appends a line to the active bundle's `timings.jsonl`: how long one image took and how it ended.
+pub fn record_timing(file: &Path, elapsed: Duration, outcome: &str) {
+    let Some(bundle) = active() else { return };
+    let line = json!({
+        "file": file.to_string_lossy(),
+        "elapsed_ms": elapsed.as_millis() as u64,
+        "outcome": outcome,
+    });
+    bundle.append("timings.jsonl", &line.to_string());
+}
+
+/// True while this task's run records a bundle; lets the tracing layer skip formatting otherwise.
+pub fn is_recording() -> bool {
+    active().is_some()
+}
+
+/// Tracing writer for the active bundle's `log.txt` (see `utils::logger::init_logger`).
+pub struct BundleWriter;
+
+pub struct BundleLog(Option<Arc<DebugBundle>>);
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for BundleWriter {
+    type Writer = BundleLog;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        BundleLog(active())
+    }
+}
+
+impl Write for BundleLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(ref bundle) = self.0 {
+            if let Ok(mut log) = bundle.log.lock() {
+                if let Some(file) = log.as_mut() {
+                    file.write_all(buf)?;
+                    bundle.bytes.fetch_add(buf.len() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends a panic to the crash log under the debug root. Synchronous on purpose: release
+/// builds abort right after the panic hook, before any background writer could flush.
+pub fn record_crash(msg: &str) {
+    let line = format!("[{}] {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"), msg);
+    let root = root();
+    let _ = fs::create_dir_all(&root);
+    let path = root.join(CRASH_LOG);
+    if fs::metadata(&path).map(|m| m.len() > MAX_CRASH_LOG_BYTES).unwrap_or(false) {
+        let _ = fs::rename(&path, root.join(format!("{}.1", CRASH_LOG)));
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{}", line);
+    }
+    if let Some(bundle) = active() {
+        bundle.append("log.txt", &line);
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BundleInfo {
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// Bundles on disk, newest first.
+pub fn list_bundles() -> Vec<BundleInfo> {
+    let mut bundles: Vec<BundleInfo> = bundle_dirs(&root()).into_iter()
+        .map(|dir| BundleInfo {
+            name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            bytes: WalkDir::new(&dir).into_iter()
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum(),
+            path: dir.to_string_lossy().to_string(),
+        })
+        .collect();
+    bundles.reverse();
+    bundles
+}
+
+/// Zips a bundle (the newest when `name` is None) to attach to a bug report. The crash
+/// log (panics) is included too.
+pub fn zip_bundle(name: Option<&str>, dest: &Path) -> Result<PathBuf> {
+    let root = root();
+    let dir = match name {
+        Some(name) => root.join(name),
+        None => bundle_dirs(&root).pop().ok_or_else(|| anyhow!("No debug bundles in {:?}", root))?,
+    };
+    if !dir.is_dir() || !dir.starts_with(&root) {
+        return Err(anyhow!("Debug bundle not found: {:?}", dir));
+    }
+    let bundle_name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    let file = File::create(dest)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut add = |path: &Path, name: String| -> Result<()> {
+        zip.start_file(name, options)?;
+        let mut buffer = Vec::new();
+        File::open(path)?.read_to_end(&mut buffer)?;
+        zip.write_all(&buffer)?;
+        Ok(())
+    };
+
+    for entry in WalkDir::new(&dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_file() {
+            let rel = path.strip_prefix(&dir)?.to_string_lossy().replace('\\', "/");
+            add(path, format!("{}/{}", bundle_name, rel))?;
+        }
+    }
+    let crash_log = root.join(CRASH_LOG);
+    if crash_log.is_file() {
+        add(&crash_log, CRASH_LOG.to_string())?;
+    }
+    zip.finish()?;
+    Ok(dest.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_header_values_are_masked_whatever_their_name() {
+        let mut profile = Profile::default();
+        profile.storage_headers = r#"{"x-storage-user": "customer-123456"}"#.to_string();
+        profile.ocr_headers = r#"{"x-ocr": "ocr-abcdefgh"}"#.to_string();
+        let masked = custom_header_values(&profile);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-storage-user", "customer-123456".parse().unwrap());
+        headers.insert("x-ocr", "ocr-abcdefgh".parse().unwrap());
+        headers.insert("target_lang", "en".parse().unwrap());
+        let json = headers_json(&headers, &masked);
+        assert_eq!(json["x-storage-user"], "****3456");
+        assert_eq!(json["x-ocr"], "****efgh");
+        assert_eq!(json["target_lang"], "en");
+    }
+
+    #[tokio::test]
+    async fn bundles_belong_to_the_task_that_records_them() {
+        let root = std::env::temp_dir().join(format!("tapi-debug-test-{}", std::process::id()));
+        set_root(&root);
+        let guard = start_run("test", true, &json!({}), Vec::new());
+        assert!(guard.is_some());
+        assert!(!is_recording());
+
+        let (inside, other_task) = recorded(&guard, async {
+            let other_task = tokio::spawn(async { is_recording() }).await.unwrap();
+            (is_recording(), other_task)
+        }).await;
+        assert!(inside);
+        assert!(!other_task);
+        assert!(!is_recording());
+
+        drop(guard);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
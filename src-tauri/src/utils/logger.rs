@@ -1,9 +1,250 @@
-use tracing_subscriber;
 use tauri::{Window, Emitter};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{Result, anyhow};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt as tfmt, prelude::*, EnvFilter, Layer, Registry};
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use crate::utils::progress::ProgressEvent;
 
-pub fn init_logger() {
-    tracing_subscriber::fmt::init();
+/// Log files are `tapi.log` (size rotation) or `tapi.<date>.log` (daily rotation)
+pub const LOG_FILE_PREFIX: &str = "tapi";
+/// Rotated log files kept
+pub const MAX_LOG_FILES: usize = 14;
+/// Target of the events bridged from `ProgressLogger`. The console already shows those
+/// messages, so only the log files and debug bundles record them.
+pub const PROGRESS_TARGET: &str = "tapi::progress";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "text" | "plain" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("Unknown log format '{}', expected text or json", other)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// When the log file is rotated: every day, once it reaches a size, or never.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    #[default]
+    Daily,
+    Size(u64),
+    Never,
+}
+
+impl std::str::FromStr for LogRotation {
+    type Err = anyhow::Error;
+
+    /// `daily`, `never`, or a size such as `10mb`, `512kb` or `1048576`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "daily" => return Ok(LogRotation::Daily),
+            "never" | "none" | "off" => return Ok(LogRotation::Never),
+            _ => {}
+        }
+        let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+        let multiplier = match unit.trim() {
+            "" | "b" => 1,
+            "k" | "kb" => 1024,
+            "m" | "mb" => 1024 * 1024,
+            "g" | "gb" => 1024 * 1024 * 1024,
+            _ => return Err(anyhow!("Unknown log rotation '{}', expected daily, never or a size like 10mb", s)),
+        };
+        let number: u64 = number.parse().map_err(|_| anyhow!("Unknown log rotation '{}', expected daily, never or a size like 10mb", s))?;
+        if number == 0 {
+            return Err(anyhow!("Log rotation size must be above zero"));
+        }
+        Ok(LogRotation::Size(number * multiplier))
+    }
+}
+
+impl fmt::Display for LogRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogRotation::Daily => f.write_str("daily"),
+            LogRotation::Never => f.write_str("never"),
+            LogRotation::Size(bytes) => write!(f, "{}", bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Filter directives, e.g. `info` or `tapi_lib=debug,reqwest=warn`. `RUST_LOG` wins.
+    pub level: String,
+    pub format: LogFormat,
+    pub rotation: LogRotation,
+    /// Where log files go; None logs to the console only
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+            rotation: LogRotation::Daily,
+            dir: None,
+        }
+    }
+}
+
+// `tapi.log`, shifted to `tapi.log.1` (`.1` to `.2`, ...) once it would grow past `max_bytes`
+struct SizeRotatingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    file: File,
+    size: u64,
+}
+
+impl SizeRotatingFile {
+    fn path(dir: &Path, n: usize) -> PathBuf {
+        match n {
+            0 => dir.join(format!("{}.log", LOG_FILE_PREFIX)),
+            n => dir.join(format!("{}.log.{}", LOG_FILE_PREFIX, n)),
+        }
+    }
+
+    fn open(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        let path = Self::path(dir, 0);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self { dir: dir.to_path_buf(), max_bytes, file, size })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = fs::remove_file(Self::path(&self.dir, MAX_LOG_FILES));
+        for n in (0..MAX_LOG_FILES).rev() {
+            let _ = fs::rename(Self::path(&self.dir, n), Self::path(&self.dir, n + 1));
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(Self::path(&self.dir, 0))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn file_writer(dir: &Path, rotation: LogRotation) -> Result<Box<dyn Write + Send>> {
+    fs::create_dir_all(dir)?;
+    Ok(match rotation {
+        LogRotation::Daily => Box::new(RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)?),
+        LogRotation::Never => Box::new(RollingFileAppender::new(Rotation::NEVER, dir, format!("{}.log", LOG_FILE_PREFIX))),
+        LogRotation::Size(max_bytes) => Box::new(SizeRotatingFile::open(dir, max_bytes)?),
+    })
+}
+
+/// Sets up tracing: the console (stderr), rotated log files when `config.dir` is set, and
+/// the debug bundle of the running job. Keep the returned guard for the life of the
+/// process; dropping it flushes the file writer. Calling this twice is a no-op.
+pub fn init_logger(config: &LogConfig) -> Option<WorkerGuard> {
+    let filter = || EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = config.format == LogFormat::Json;
+
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+
+    let console_filter = filter().add_directive(format!("{}=off", PROGRESS_TARGET).parse().expect("valid directive"));
+    layers.push(match json {
+        true => tfmt::layer().json().with_writer(io::stderr).with_filter(console_filter).boxed(),
+        false => tfmt::layer().with_writer(io::stderr).with_target(false).with_filter(console_filter).boxed(),
+    });
+
+    let mut guard = None;
+    let mut file_error = None;
+    if let Some(ref dir) = config.dir {
+        match file_writer(dir, config.rotation) {
+            Ok(writer) => {
+                let (writer, worker) = tracing_appender::non_blocking(writer);
+                guard = Some(worker);
+                layers.push(match json {
+                    true => tfmt::layer().json().with_writer(writer).with_filter(filter()).boxed(),
+                    false => tfmt::layer().with_ansi(false).with_writer(writer).with_filter(filter()).boxed(),
+                });
+            }
+            Err(e) => file_error = Some(format!("Log files disabled, {} is not writable: {}", dir.display(), e)),
+        }
+    }
+
+    // Our own debug events go to the job's bundle whatever the configured level is
+    layers.push(tfmt::layer()
+        .with_ansi(false)
+        .with_writer(crate::utils::debug::BundleWriter)
+        .with_filter(filter_fn(|meta| {
+            crate::utils::debug::is_recording()
+                && meta.target().starts_with("tapi")
+                && *meta.level() <= tracing::Level::DEBUG
+        }).with_max_level_hint(LevelFilter::DEBUG))
+        .boxed());
+
+    if tracing_subscriber::registry().with(layers).try_init().is_err() {
+        return None;
+    }
+    if let Some(e) = file_error {
+        tracing::warn!("{}", e);
+    }
+    guard
+}
+
+/// Bridge from `ProgressLogger` to tracing, so what the UI shows ends up in the log files too.
+pub fn trace_log(message: &str) {
+    tracing::info!(target: PROGRESS_TARGET, "{}", message);
+}
+
+pub fn trace_progress(current: usize, total: usize, message: &str) {
+    tracing::info!(target: PROGRESS_TARGET, current, total, "{}", message);
+}
+
+pub fn trace_event(event: &ProgressEvent) {
+    if let Ok(json) = serde_json::to_string(event) {
+        tracing::debug!(target: PROGRESS_TARGET, event = %json, "progress event");
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -14,66 +255,106 @@ pub struct ProgressPayload {
     pub message: String,
 }
 
+/// Payload of the downloader's `download_progress` and MangaDex's `mangadex-progress` events.
+/// `status` is `downloading_single`, `downloading` or `completed`.
+#[derive(Serialize, Clone)]
+pub struct DownloadProgress {
+    pub current: usize,
+    pub total: usize,
+    pub status: String,
+    pub filename: String,
+}
+
 pub trait ProgressLogger {
     fn log(&self, message: String);
     fn progress(&self, current: usize, total: usize, message: String);
+
+    /// Download progress, sent to the app as `event`. Loggers that only print text show it
+    /// as a progress line.
+    fn download(&self, _event: &str, progress: DownloadProgress) {
+        let message = if progress.filename.is_empty() { progress.status } else { progress.filename };
+        self.progress(progress.current, progress.total, message);
+    }
+
+    /// Typed stage, per-file and throughput events. Loggers that only print text can leave
+    /// this to the default, which logs the events worth a line.
+    fn event(&self, event: ProgressEvent) {
+        if let Some(line) = event.describe() {
+            self.log(line);
+        }
+    }
 }
 
 impl ProgressLogger for Window {
     fn log(&self, message: String) {
+        trace_log(&message);
         let _ = self.emit("translation-log", message);
     }
 
     fn progress(&self, current: usize, total: usize, message: String) {
+        trace_progress(current, total, &message);
         let percentage = if total > 0 {
              (current as f64 / total as f64) * 100.0
         } else { 0.0 };
-        
+
         let payload = ProgressPayload {
             current,
             total,
             percentage,
             message: message.clone(),
         };
-        
+
         // Emit progress specific event
         let _ = self.emit("translation-progress", payload);
         // Also emit log so it shows in the log output too
         let _ = self.emit("translation-log", message);
     }
+
+    fn event(&self, event: ProgressEvent) {
+        trace_event(&event);
+        let _ = self.emit("translation-event", event);
+    }
+
+    fn download(&self, event: &str, progress: DownloadProgress) {
+        trace_progress(progress.current, progress.total, &progress.filename);
+        let _ = self.emit(event, progress);
+    }
 }
 
 pub struct ConsoleLogger;
 
-impl ProgressLogger for ConsoleLogger {
-    fn log(&self, message: String) {
-        println!("{}", message);
+static CONSOLE_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+impl ConsoleLogger {
+    /// Sends the console output to stderr, leaving stdout to machine-readable output.
+    pub fn use_stderr(enabled: bool) {
+        CONSOLE_TO_STDERR.store(enabled, Ordering::Relaxed);
     }
 
-    fn progress(&self, current: usize, total: usize, message: String) {
-         println!("[{}/{}] {}", current, total, message);
+    fn print(line: &str) {
+        if CONSOLE_TO_STDERR.load(Ordering::Relaxed) {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
     }
 }
 
-pub fn log_debug(msg: &str) {
-    // Synchronous file append for crash debugging
-    use std::fs::OpenOptions;
-    use std::io::Write;
-    use std::time::{SystemTime, UNIX_EPOCH};
+impl ProgressLogger for ConsoleLogger {
+    fn log(&self, message: String) {
+        trace_log(&message);
+        Self::print(&message);
+    }
 
-    let start = SystemTime::now();
-    let since_the_epoch = start
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    
-    let timestamp = since_the_epoch.as_millis();
+    fn progress(&self, current: usize, total: usize, message: String) {
+        trace_progress(current, total, &message);
+        Self::print(&format!("[{}/{}] {}", current, total, message));
+    }
 
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("crash_debug.log") 
-    {
-        let _ = writeln!(file, "[{}] {}", timestamp, msg);
+    fn event(&self, event: ProgressEvent) {
+        trace_event(&event);
+        if let Some(line) = event.describe() {
+            Self::print(&line);
+        }
     }
 }
-
@@ -1,3 +1,5 @@
+pub mod debug;
 pub mod error;
 pub mod logger;
 pub mod monitor;
+pub mod progress;
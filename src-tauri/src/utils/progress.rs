@@ -0,0 +1,155 @@
+use crate::config::language::t;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::time::Instant;
+
+/// What a run (or one of its files) is doing right now.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Scanning,
+    Hashing,
+    Compressing,
+    Uploading,
+    Saving,
+    Done,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Scanning => "scanning",
+            Stage::Hashing => "hashing",
+            Stage::Compressing => "compressing",
+            Stage::Uploading => "uploading",
+            Stage::Saving => "saving",
+            Stage::Done => "done",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Done,
+    Failed,
+    Skipped,
+}
+
+/// Typed progress, for UIs that show more than a percentage. Serialized with a `type` tag.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// The run entered a stage, or with `file`, one file did
+    Stage { stage: Stage, file: Option<String> },
+    /// Progress through a stage that comes before translating (hashing)
+    StageProgress { stage: Stage, current: usize, total: usize },
+    /// How one file ended and what went over the wire for it
+    FileDone {
+        file: String,
+        status: FileStatus,
+        error: Option<String>,
+        elapsed_ms: u64,
+        bytes_sent: u64,
+        bytes_received: u64,
+    },
+    /// Rates over the last few files, and the time left at that pace
+    Throughput {
+        current: usize,
+        total: usize,
+        files_per_min: f64,
+        bytes_per_sec: f64,
+        bytes_total: u64,
+        eta_secs: Option<u64>,
+    },
+}
+
+impl ProgressEvent {
+    pub fn stage(stage: Stage) -> Self {
+        ProgressEvent::Stage { stage, file: None }
+    }
+
+    pub fn file_stage(stage: Stage, file: &Path) -> Self {
+        ProgressEvent::Stage { stage, file: Some(file_name(file)) }
+    }
+
+    /// The event as one line of text, for loggers that only print. None for events too
+    /// frequent to print (per-file stages) or already covered by the run's own log lines.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            ProgressEvent::Stage { stage, file: None } => Some(t("INFO_STAGE", &[("stage", stage)])),
+            ProgressEvent::StageProgress { stage, current, total } if current == total || current % 100 == 0 => {
+                Some(t("INFO_STAGE_PROGRESS", &[("stage", stage), ("i", current), ("total", total)]))
+            }
+            ProgressEvent::Throughput { files_per_min, eta_secs: Some(eta), current, total, .. } if current < total => {
+                Some(t("INFO_THROUGHPUT", &[("rate", &format!("{:.1}", files_per_min)), ("eta", &format_duration(*eta))]))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// `1h02m`, `4m10s` or `35s`.
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        s if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+// Files the rolling rates are averaged over
+const WINDOW: usize = 10;
+
+/// Rolling throughput and ETA of a run over `total` files.
+pub struct ProgressTracker {
+    total: usize,
+    done: usize,
+    started: Instant,
+    bytes_total: u64,
+    // Finish time and bytes of the last WINDOW + 1 files
+    recent: VecDeque<(Instant, u64)>,
+}
+
+impl ProgressTracker {
+    pub fn new(total: usize) -> Self {
+        Self { total, done: 0, started: Instant::now(), bytes_total: 0, recent: VecDeque::with_capacity(WINDOW + 1) }
+    }
+
+    /// Records a finished file (`bytes` sent plus received) and returns the updated rates.
+    pub fn file_done(&mut self, bytes: u64) -> ProgressEvent {
+        let now = Instant::now();
+        self.done += 1;
+        self.bytes_total += bytes;
+        self.recent.push_back((now, bytes));
+        if self.recent.len() > WINDOW + 1 {
+            self.recent.pop_front();
+        }
+
+        // Between the oldest and newest finish in the window; from the run start until there are two
+        let (since, files, window_bytes) = match self.recent.front() {
+            Some(&(first, _)) if self.recent.len() > 1 => {
+                (first, self.recent.len() - 1, self.recent.iter().skip(1).map(|(_, b)| b).sum::<u64>())
+            }
+            _ => (self.started, 1, bytes),
+        };
+        let secs = now.duration_since(since).as_secs_f64().max(0.001);
+        let per_sec = files as f64 / secs;
+        let left = self.total.saturating_sub(self.done);
+
+        ProgressEvent::Throughput {
+            current: self.done,
+            total: self.total,
+            files_per_min: per_sec * 60.0,
+            bytes_per_sec: window_bytes as f64 / secs,
+            bytes_total: self.bytes_total,
+            eta_secs: (per_sec > 0.0).then(|| (left as f64 / per_sec).round() as u64),
+        }
+    }
+}
@@ -34,9 +34,25 @@ export const api = {
                 options.body = JSON.stringify(args);
             }
 
-            const response = await fetch(endpoint.path, options);
+            let path = endpoint.path;
+            if (endpoint.method === 'GET') {
+                // GET endpoints take their filters from the query string
+                const params = new URLSearchParams();
+                for (const [key, value] of Object.entries(args.query ?? args)) {
+                    if (value !== null && value !== undefined) params.append(key, String(value));
+                }
+                const qs = params.toString();
+                if (qs) path += `?${qs}`;
+            }
+
+            const response = await fetch(path, options);
 
             if (!response.ok) {
+                // Structured errors (e.g. settings validation) are passed through like Tauri does
+                const errorType = response.headers.get('content-type');
+                if (errorType && errorType.includes('application/json')) {
+                    throw await response.json();
+                }
                 throw new Error(`Server Error: ${response.statusText}`);
             }
 
@@ -50,6 +66,15 @@ export const api = {
     }
 };
 
+// Turns a command error into readable text, including per-field settings errors
+export function formatError(e: any): string {
+    if (e && e.kind === 'invalid' && Array.isArray(e.errors)) {
+        return e.errors.map((f: { field: string; message: string }) => `${f.field}: ${f.message}`).join('\n');
+    }
+    if (e && e.kind === 'failed') return e.message;
+    return String(e);
+}
+
 interface EndpointConfig {
     path: string;
     method: 'GET' | 'POST';
@@ -61,6 +86,20 @@ function mapCommandToEndpoint(cmd: string): EndpointConfig | null {
             return { path: '/api/settings/load', method: 'GET' };
         case 'save_settings':
             return { path: '/api/settings/save', method: 'POST' };
+        case 'list_profiles':
+            return { path: '/api/profiles/list', method: 'GET' };
+        case 'create_profile':
+            return { path: '/api/profiles/create', method: 'POST' };
+        case 'clone_profile':
+            return { path: '/api/profiles/clone', method: 'POST' };
+        case 'switch_profile':
+            return { path: '/api/profiles/switch', method: 'POST' };
+        case 'delete_profile':
+            return { path: '/api/profiles/delete', method: 'POST' };
+        case 'unlock_profile':
+            return { path: '/api/profiles/unlock', method: 'POST' };
+        case 'set_master_passphrase':
+            return { path: '/api/profiles/passphrase', method: 'POST' };
         case 'list_hash_names':
             return { path: '/api/database/list', method: 'GET' };
         case 'save_hash_name':
@@ -73,8 +112,40 @@ function mapCommandToEndpoint(cmd: string): EndpointConfig | null {
             return { path: '/api/database/pull', method: 'POST' };
         case 'push_remote_database':
             return { path: '/api/database/push', method: 'POST' };
+        case 'sync_remote_database':
+            return { path: '/api/database/sync', method: 'POST' };
+        case 'import_history_file':
+            return { path: '/api/database/import_history', method: 'POST' };
+        case 'reconcile_history':
+            return { path: '/api/database/reconcile', method: 'POST' };
         case 'test_database_connection':
             return { path: '/api/database/test', method: 'POST' };
+        case 'estimate_translation':
+            return { path: '/api/translate/estimate', method: 'POST' };
+        case 'list_key_usage':
+            return { path: '/api/keys/usage', method: 'GET' };
+        case 'list_glossary':
+            return { path: '/api/glossary', method: 'GET' };
+        case 'list_glossary_series':
+            return { path: '/api/glossary/series', method: 'GET' };
+        case 'save_glossary_term':
+            return { path: '/api/glossary/save', method: 'POST' };
+        case 'delete_glossary_term':
+            return { path: '/api/glossary/delete', method: 'POST' };
+        case 'detect_series':
+            return { path: '/api/glossary/detect', method: 'GET' };
+        case 'start_storage_translation':
+            return { path: '/api/translate/storage', method: 'POST' };
+        case 'get_catalog':
+            return { path: '/api/catalog', method: 'GET' };
+        case 'list_debug_bundles':
+            return { path: '/api/debug/bundles', method: 'GET' };
+        case 'start_ocr':
+            return { path: '/api/ocr', method: 'POST' };
+        case 'export_script':
+            return { path: '/api/script/export', method: 'POST' };
+        case 'render_script':
+            return { path: '/api/script/render', method: 'POST' };
         default:
             console.warn(`Command map missing for: ${cmd}`);
             return null;